@@ -0,0 +1,77 @@
+// Error codes shown as `error[E0000]`, grouped by the phase that reports them.
+// Warnings use W and the number of the group they belong to.
+
+// Lexer
+pub const UNCLOSED_STRING: &str = "E0001";
+pub const INVALID_IDENTIFIER: &str = "E0002";
+pub const UNEXPECTED_CHARACTER: &str = "E0003";
+pub const INTEGER_TOO_LARGE: &str = "E0004";
+pub const INVALID_ESCAPE: &str = "E0005";
+pub const INVALID_NUMBER: &str = "E0006";
+pub const FLOAT_TOO_LARGE: &str = "E0007";
+pub const UNCLOSED_COMMENT: &str = "E0008";
+
+// Parser
+pub const UNEXPECTED_TOKEN: &str = "E0101";
+pub const UNEXPECTED_EOF: &str = "E0102";
+pub const EXPECTED_TYPE: &str = "E0103";
+pub const EXPECTED_IDENTIFIER: &str = "E0104";
+pub const INVALID_ASSIGNMENT_TARGET: &str = "E0106";
+pub const EXPECTED_ARRAY_SIZE: &str = "E0107";
+pub const ARRAY_TOO_LARGE: &str = "E0108";
+
+// Name resolution
+pub const REDECLARATION: &str = "E0201";
+pub const UNDEFINED_VARIABLE: &str = "E0202";
+pub const UNDEFINED_FUNCTION: &str = "E0203";
+pub const NOT_A_VARIABLE: &str = "E0204";
+pub const NOT_A_FUNCTION: &str = "E0205";
+pub const GLOBAL_USED_BEFORE_INIT: &str = "E0206";
+pub const USED_BEFORE_ASSIGNED: &str = "E0208";
+
+// Type checking
+pub const MISMATCHED_TYPES: &str = "E0301";
+pub const INVALID_OPERAND: &str = "E0302";
+pub const NON_BOOL_CONDITION: &str = "E0303";
+pub const WRONG_ARGUMENT_COUNT: &str = "E0304";
+pub const NARROWING_CONVERSION: &str = "E0305";
+pub const VOID_VALUE_USED: &str = "E0306";
+pub const NOT_AN_ARRAY: &str = "E0307";
+pub const NON_INT_INDEX: &str = "E0308";
+pub const UNSIZED_ARRAY: &str = "E0309";
+pub const EMPTY_ARRAY_LITERAL: &str = "E0310";
+pub const CONSTANT_INDEX_OUT_OF_BOUNDS: &str = "E0311";
+pub const UNKNOWN_TYPE: &str = "E0312";
+pub const UNKNOWN_FIELD: &str = "E0313";
+pub const NOT_A_STRUCT: &str = "E0314";
+pub const MISSING_FIELD: &str = "E0315";
+pub const DUPLICATE_FIELD: &str = "E0316";
+pub const RECURSIVE_STRUCT: &str = "E0317";
+
+// Control flow
+pub const LOOP_CONTROL_OUTSIDE_LOOP: &str = "E0401";
+pub const MISSING_RETURN: &str = "E0402";
+pub const UNREACHABLE_CODE: &str = "W0403";
+
+// Lints, warnings unless denied
+pub const UNUSED_VARIABLE: &str = "W0701";
+pub const UNUSED_PARAMETER: &str = "W0702";
+pub const UNUSED_FUNCTION: &str = "W0703";
+pub const SHADOWED_VARIABLE: &str = "W0704";
+pub const DEAD_ASSIGNMENT: &str = "W0705";
+pub const BOOL_COMPARISON: &str = "W0706";
+
+// IR generation
+pub const IR_GENERATION: &str = "E0501";
+
+// Runtime
+pub const DIVISION_BY_ZERO: &str = "E0601";
+pub const INTEGER_OVERFLOW: &str = "E0602";
+pub const NEGATIVE_EXPONENT: &str = "E0603";
+pub const INVALID_SHIFT: &str = "E0604";
+pub const INVALID_OPERATION: &str = "E0605";
+pub const STACK_OVERFLOW: &str = "E0606";
+pub const MISSING_MAIN: &str = "E0607";
+pub const OUTPUT_ERROR: &str = "E0608";
+pub const INDEX_OUT_OF_BOUNDS: &str = "E0609";
+pub const ALLOCATION_FAILED: &str = "E0610";
@@ -0,0 +1,103 @@
+use super::diagnostic::{Diagnostic, Label};
+use crate::lexer::span::LineMap;
+
+// Renders diagnostics rustc-style, quoting the offending source lines:
+//
+// error[E0202]: Variable 'x' is not defined
+//   --> data/code.txt:3:5
+//    |
+//  3 |     x = 5;
+//    |     ^ not found in this scope
+//
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    code: &'a str,
+    line_map: LineMap,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(file_name: &'a str, code: &'a str) -> Self {
+        Renderer {
+            file_name,
+            code,
+            line_map: LineMap::new(code),
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = format!(
+            "{}[{}]: {}\n",
+            diagnostic.severity, diagnostic.code, diagnostic.message
+        );
+
+        // primary label first so it wins ties on the same line
+        let mut labels: Vec<(&Label, char)> = vec![(&diagnostic.primary, '^')];
+        for label in &diagnostic.secondary {
+            labels.push((label, '-'));
+        }
+        labels.sort_by_key(|(label, _)| label.span.line);
+
+        let max_line = labels.iter().map(|(l, _)| l.span.line).max().unwrap_or(1);
+        let width = max_line.to_string().len();
+        let blank = " ".repeat(width);
+
+        out.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            blank, self.file_name, diagnostic.primary.span.line, diagnostic.primary.span.col
+        ));
+        out.push_str(&format!("{} |\n", blank));
+
+        let mut prev_line: Option<usize> = None;
+        for (label, marker) in &labels {
+            let line = label.span.line;
+            let text = self.line_map.line_text(self.code, line);
+
+            if prev_line != Some(line) {
+                if let Some(prev) = prev_line
+                    && line > prev + 1
+                {
+                    out.push_str("...\n");
+                }
+                out.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+                prev_line = Some(line);
+            }
+
+            // keep tabs in the padding so the carets line up with the quoted text
+            let padding: String = text
+                .chars()
+                .take(label.span.col - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let line_rest = text.chars().count().saturating_sub(label.span.col - 1);
+            let span_len = self.code[label.span.start..label.span.end].chars().count();
+            let underline_len = span_len.min(line_rest).max(1);
+            let underline = marker.to_string().repeat(underline_len);
+
+            if label.message.is_empty() {
+                out.push_str(&format!("{} | {}{}\n", blank, padding, underline));
+            } else {
+                out.push_str(&format!(
+                    "{} | {}{} {}\n",
+                    blank, padding, underline, label.message
+                ));
+            }
+        }
+
+        if !diagnostic.notes.is_empty() {
+            out.push_str(&format!("{} |\n", blank));
+            for note in &diagnostic.notes {
+                out.push_str(&format!("{} = note: {}\n", blank, note));
+            }
+        }
+
+        out
+    }
+
+    pub fn render_all(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|d| self.render(d))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::diagnostics::renderer::Renderer;
+use crate::interpreter::tree_walker;
+use crate::interpreter::value::Value;
+use crate::interpreter::vm;
+use crate::ir::ir_generator;
+use crate::ir::tac::Module;
+use crate::lexer::lexer;
+use crate::lexer::tokens::SpannedToken;
+use crate::parser::enums::RootList;
+use crate::parser::parser;
+use crate::semantics::lints::LintConfig;
+use crate::semantics::semantic_analysis;
+use crate::semantics::typed_ast::TypedProgram;
+
+// The phase a compilation stopped in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lex,
+    Parse,
+    Semantic,
+    Ir,
+    Runtime,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Lex => write!(f, "lexing"),
+            Phase::Parse => write!(f, "parsing"),
+            Phase::Semantic => write!(f, "semantic analysis"),
+            Phase::Ir => write!(f, "IR generation"),
+            Phase::Runtime => write!(f, "running"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub phase: Phase,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// A typed tree that passed semantic analysis, only `Session::check` can make one
+#[derive(Debug)]
+pub struct CheckedProgram {
+    pub program: TypedProgram,
+}
+
+// One source file going through the pipeline:
+//
+//     let mut session = Session::new("main.rlp", code);
+//     let tokens = session.lex()?;
+//     let ast = session.parse(tokens)?;
+//     let program = session.check(ast)?;
+//     let ir = session.generate_ir(&program)?;
+//
+pub struct Session {
+    pub file_name: String,
+    pub code: String,
+    pub lints: LintConfig,
+    diagnostics: Vec<Diagnostic>, // non-fatal diagnostics from the phases run so far
+}
+
+impl Session {
+    pub fn new(file_name: impl Into<String>, code: impl Into<String>) -> Self {
+        Session {
+            file_name: file_name.into(),
+            code: code.into(),
+            lints: LintConfig::default(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let code = fs::read_to_string(path)?;
+        Ok(Session::new(path.display().to_string(), code))
+    }
+
+    pub fn lex(&mut self) -> Result<Vec<SpannedToken>, CompileError> {
+        lexer::lex(self.code.clone()).map_err(|errors| CompileError {
+            phase: Phase::Lex,
+            diagnostics: errors.iter().map(|e| e.to_diagnostic()).collect(),
+        })
+    }
+
+    pub fn parse(&mut self, tokens: Vec<SpannedToken>) -> Result<RootList, CompileError> {
+        parser::parser(tokens).map_err(|diagnostics| CompileError {
+            phase: Phase::Parse,
+            diagnostics,
+        })
+    }
+
+    pub fn check(&mut self, ast: RootList) -> Result<CheckedProgram, CompileError> {
+        match semantic_analysis::semantic_analysis(&ast, &self.lints) {
+            Ok((program, warnings)) => {
+                self.diagnostics.extend(warnings);
+                Ok(CheckedProgram { program })
+            }
+            Err(diagnostics) => Err(CompileError {
+                phase: Phase::Semantic,
+                diagnostics,
+            }),
+        }
+    }
+
+    pub fn generate_ir(&mut self, program: &CheckedProgram) -> Result<Module, CompileError> {
+        ir_generator::ir_generator(&program.program).map_err(|diagnostics| CompileError {
+            phase: Phase::Ir,
+            diagnostics,
+        })
+    }
+
+    // Interprets the program starting at main(), print() writes to `out`
+    pub fn run(
+        &mut self,
+        program: &CheckedProgram,
+        out: &mut (dyn Write + Send),
+    ) -> Result<Value, CompileError> {
+        tree_walker::interpret(&program.program, out).map_err(|error| CompileError {
+            phase: Phase::Runtime,
+            diagnostics: vec![error.to_diagnostic()],
+        })
+    }
+
+    // Same as run but executes the generated IR instead of the syntax tree
+    pub fn run_ir(&mut self, module: &Module, out: &mut dyn Write) -> Result<Value, CompileError> {
+        vm::execute(module, out).map_err(|error| CompileError {
+            phase: Phase::Runtime,
+            diagnostics: vec![error.to_diagnostic()],
+        })
+    }
+
+    // Runs every phase up to and including semantic analysis
+    pub fn check_source(&mut self) -> Result<CheckedProgram, CompileError> {
+        let tokens = self.lex()?;
+        let ast = self.parse(tokens)?;
+        self.check(ast)
+    }
+
+    // Runs the whole pipeline and returns the three address code
+    pub fn compile(&mut self) -> Result<Module, CompileError> {
+        let program = self.check_source()?;
+        self.generate_ir(&program)
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn renderer(&self) -> Renderer<'_> {
+        Renderer::new(&self.file_name, &self.code)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::ir::tac::{BinaryOp, UnaryOp};
+use crate::lexer::span::Span;
+use crate::parser::parser::MAX_ARRAY_LEN;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    DivisionByZero(Span),
+    IntegerOverflow(Span),
+    NegativeExponent(i64, Span),
+    InvalidShift(i64, Span),
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+        span: Span,
+    },
+    InvalidIndex {
+        array: &'static str,
+        index: &'static str,
+        span: Span,
+    },
+    InvalidBinaryOperands {
+        operator: BinaryOp,
+        left: &'static str,
+        right: &'static str,
+        span: Span,
+    },
+    InvalidUnaryOperand(UnaryOp, &'static str, Span),
+    StackOverflow(String, Span),   // function whose call went too deep
+    ArrayTooLarge(usize, Span),    // values the array would have held in total
+    UnknownFunction(String, Span), // no body to run, never in a checked program
+    MissingMain,
+    MainHasParameters(Span),
+    Output(String, Span),
+}
+
+// Where there is no source location to point at, e.g. a missing main
+fn file_start() -> Span {
+    Span::new(0, 0, 1, 1)
+}
+
+impl RuntimeError {
+    fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::DivisionByZero(_) => codes::DIVISION_BY_ZERO,
+            RuntimeError::IntegerOverflow(_) => codes::INTEGER_OVERFLOW,
+            RuntimeError::NegativeExponent(..) => codes::NEGATIVE_EXPONENT,
+            RuntimeError::InvalidShift(..) => codes::INVALID_SHIFT,
+            RuntimeError::IndexOutOfBounds { .. } => codes::INDEX_OUT_OF_BOUNDS,
+            RuntimeError::InvalidBinaryOperands { .. }
+            | RuntimeError::InvalidUnaryOperand(..)
+            | RuntimeError::InvalidIndex { .. }
+            | RuntimeError::UnknownFunction(..) => codes::INVALID_OPERATION,
+            RuntimeError::StackOverflow(..) => codes::STACK_OVERFLOW,
+            RuntimeError::ArrayTooLarge(..) => codes::ALLOCATION_FAILED,
+            RuntimeError::MissingMain | RuntimeError::MainHasParameters(_) => codes::MISSING_MAIN,
+            RuntimeError::Output(..) => codes::OUTPUT_ERROR,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::DivisionByZero(span)
+            | RuntimeError::IntegerOverflow(span)
+            | RuntimeError::NegativeExponent(_, span)
+            | RuntimeError::InvalidShift(_, span)
+            | RuntimeError::IndexOutOfBounds { span, .. }
+            | RuntimeError::InvalidIndex { span, .. }
+            | RuntimeError::InvalidBinaryOperands { span, .. }
+            | RuntimeError::InvalidUnaryOperand(_, _, span)
+            | RuntimeError::StackOverflow(_, span)
+            | RuntimeError::ArrayTooLarge(_, span)
+            | RuntimeError::UnknownFunction(_, span)
+            | RuntimeError::MainHasParameters(span)
+            | RuntimeError::Output(_, span) => *span,
+            RuntimeError::MissingMain => file_start(),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), self.to_string(), self.span());
+        match self {
+            RuntimeError::InvalidShift(..) => {
+                diagnostic.with_note("shift amounts must be between 0 and 63")
+            }
+            RuntimeError::IntegerOverflow(_) => diagnostic.with_note(format!(
+                "ints are 64 bit, from {} to {}",
+                i64::MIN,
+                i64::MAX
+            )),
+            RuntimeError::IndexOutOfBounds { len, .. } => diagnostic.with_note(match len {
+                0 => "the array has no elements".to_string(),
+                _ => format!("valid indices are 0 to {}", len - 1),
+            }),
+            RuntimeError::StackOverflow(..) => diagnostic
+                .with_label("this call went too deep")
+                .with_note("is the recursion missing a base case?"),
+            RuntimeError::ArrayTooLarge(..) => diagnostic.with_note(format!(
+                "an array holds at most {} values, nested arrays included",
+                MAX_ARRAY_LEN
+            )),
+            RuntimeError::MissingMain => {
+                diagnostic.with_note("add `fn int main() { ... }` as the entry point")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::DivisionByZero(_) => write!(f, "attempt to divide by zero"),
+            RuntimeError::IntegerOverflow(_) => write!(f, "integer overflow"),
+            RuntimeError::NegativeExponent(exp, _) => {
+                write!(f, "cannot raise an int to a negative power ({})", exp)
+            }
+            RuntimeError::InvalidShift(amount, _) => {
+                write!(f, "invalid shift amount {}", amount)
+            }
+            RuntimeError::IndexOutOfBounds { index, len, .. } => write!(
+                f,
+                "index {} is out of bounds for an array of length {}",
+                index, len
+            ),
+            RuntimeError::InvalidIndex { array, index, .. } => {
+                write!(f, "cannot index '{}' with '{}'", array, index)
+            }
+            RuntimeError::InvalidBinaryOperands {
+                operator,
+                left,
+                right,
+                ..
+            } => write!(
+                f,
+                "cannot apply '{}' to '{}' and '{}'",
+                operator, left, right
+            ),
+            RuntimeError::InvalidUnaryOperand(operator, operand, _) => {
+                write!(f, "cannot apply '{}' to '{}'", operator, operand)
+            }
+            RuntimeError::StackOverflow(name, _) => {
+                write!(f, "stack overflow while calling '{}'", name)
+            }
+            RuntimeError::ArrayTooLarge(size, _) => {
+                write!(f, "cannot allocate an array of {} values", size)
+            }
+            RuntimeError::UnknownFunction(name, _) => {
+                write!(f, "call to unknown function '{}'", name)
+            }
+            RuntimeError::MissingMain => write!(f, "no 'main' function to run"),
+            RuntimeError::MainHasParameters(_) => {
+                write!(f, "'main' cannot take parameters")
+            }
+            RuntimeError::Output(message, _) => write!(f, "cannot write output: {}", message),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod errors;
+pub mod ops;
+pub mod tree_walker;
+pub mod value;
+pub mod vm;
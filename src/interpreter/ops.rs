@@ -0,0 +1,244 @@
+use super::errors::RuntimeError;
+use super::value::Value;
+use crate::ir::tac::{BinaryOp, IrType, UnaryOp};
+use crate::lexer::span::Span;
+use crate::parser::parser::MAX_ARRAY_LEN;
+
+// Operator semantics shared by the tree walker and the IR vm, so both agree on
+// overflow and division by zero. Both operands always have the same type, semantic
+// analysis spells out every int/float conversion.
+
+fn invalid_operands(op: BinaryOp, left: &Value, right: &Value, span: Span) -> RuntimeError {
+    RuntimeError::InvalidBinaryOperands {
+        operator: op,
+        left: left.type_name(),
+        right: right.type_name(),
+        span,
+    }
+}
+
+pub fn binary(op: BinaryOp, l: Value, r: Value, span: Span) -> Result<Value, RuntimeError> {
+    let overflow = || RuntimeError::IntegerOverflow(span);
+
+    match (&l, &r) {
+        (Value::Int(a), Value::Int(b)) => {
+            let (a, b) = (*a, *b);
+            let value = match op {
+                BinaryOp::Add => Value::Int(a.checked_add(b).ok_or_else(overflow)?),
+                BinaryOp::Sub => Value::Int(a.checked_sub(b).ok_or_else(overflow)?),
+                BinaryOp::Mul => Value::Int(a.checked_mul(b).ok_or_else(overflow)?),
+                BinaryOp::Div => {
+                    if b == 0 {
+                        return Err(RuntimeError::DivisionByZero(span));
+                    }
+                    Value::Int(a.checked_div(b).ok_or_else(overflow)?)
+                }
+                BinaryOp::Mod => {
+                    if b == 0 {
+                        return Err(RuntimeError::DivisionByZero(span));
+                    }
+                    Value::Int(a.checked_rem(b).ok_or_else(overflow)?)
+                }
+                BinaryOp::Pow => {
+                    if b < 0 {
+                        return Err(RuntimeError::NegativeExponent(b, span));
+                    }
+                    let exp = u32::try_from(b).map_err(|_| overflow())?;
+                    Value::Int(a.checked_pow(exp).ok_or_else(overflow)?)
+                }
+                BinaryOp::Shl | BinaryOp::Shr => {
+                    if !(0..64).contains(&b) {
+                        return Err(RuntimeError::InvalidShift(b, span));
+                    }
+                    if op == BinaryOp::Shl {
+                        Value::Int(a << b)
+                    } else {
+                        Value::Int(a >> b)
+                    }
+                }
+                BinaryOp::BitAnd => Value::Int(a & b),
+                BinaryOp::BitOr => Value::Int(a | b),
+                BinaryOp::BitXor => Value::Int(a ^ b),
+                BinaryOp::Eq => Value::Bool(a == b),
+                BinaryOp::Ne => Value::Bool(a != b),
+                BinaryOp::Lt => Value::Bool(a < b),
+                BinaryOp::Gt => Value::Bool(a > b),
+                BinaryOp::Le => Value::Bool(a <= b),
+                BinaryOp::Ge => Value::Bool(a >= b),
+                BinaryOp::And | BinaryOp::Or => return Err(invalid_operands(op, &l, &r, span)),
+            };
+            Ok(value)
+        }
+        (Value::Float(a), Value::Float(b)) => {
+            let (a, b) = (*a, *b);
+            let value = match op {
+                BinaryOp::Add => Value::Float(a + b),
+                BinaryOp::Sub => Value::Float(a - b),
+                BinaryOp::Mul => Value::Float(a * b),
+                BinaryOp::Div => Value::Float(a / b),
+                BinaryOp::Pow => Value::Float(a.powf(b)),
+                BinaryOp::Eq => Value::Bool(a == b),
+                BinaryOp::Ne => Value::Bool(a != b),
+                BinaryOp::Lt => Value::Bool(a < b),
+                BinaryOp::Gt => Value::Bool(a > b),
+                BinaryOp::Le => Value::Bool(a <= b),
+                BinaryOp::Ge => Value::Bool(a >= b),
+                _ => return Err(invalid_operands(op, &l, &r, span)),
+            };
+            Ok(value)
+        }
+        (Value::Str(a), Value::Str(b)) => {
+            let value = match op {
+                BinaryOp::Add => Value::Str(format!("{}{}", a, b)),
+                BinaryOp::Eq => Value::Bool(a == b),
+                BinaryOp::Ne => Value::Bool(a != b),
+                BinaryOp::Lt => Value::Bool(a < b),
+                BinaryOp::Gt => Value::Bool(a > b),
+                BinaryOp::Le => Value::Bool(a <= b),
+                BinaryOp::Ge => Value::Bool(a >= b),
+                _ => return Err(invalid_operands(op, &l, &r, span)),
+            };
+            Ok(value)
+        }
+        (Value::Bool(a), Value::Bool(b)) => match op {
+            BinaryOp::Eq => Ok(Value::Bool(a == b)),
+            BinaryOp::Ne => Ok(Value::Bool(a != b)),
+            BinaryOp::And => Ok(Value::Bool(*a && *b)),
+            BinaryOp::Or => Ok(Value::Bool(*a || *b)),
+            _ => Err(invalid_operands(op, &l, &r, span)),
+        },
+        _ => Err(invalid_operands(op, &l, &r, span)),
+    }
+}
+
+// A typed opcode from the IR, operands of any other type are an error
+pub fn typed_binary(
+    op: BinaryOp,
+    ty: IrType,
+    l: Value,
+    r: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    if l.ir_type() != Some(ty) || r.ir_type() != Some(ty) {
+        return Err(invalid_operands(op, &l, &r, span));
+    }
+    binary(op, l, r, span)
+}
+
+pub fn unary(op: UnaryOp, value: Value, span: Span) -> Result<Value, RuntimeError> {
+    match (op, value) {
+        (UnaryOp::Neg, Value::Int(i)) => i
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or(RuntimeError::IntegerOverflow(span)),
+        (UnaryOp::Neg, Value::Float(f)) => Ok(Value::Float(-f)),
+        (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (UnaryOp::BitNot, Value::Int(i)) => Ok(Value::Int(!i)),
+        (op, value) => Err(RuntimeError::InvalidUnaryOperand(
+            op,
+            value.type_name(),
+            span,
+        )),
+    }
+}
+
+pub fn typed_unary(
+    op: UnaryOp,
+    ty: IrType,
+    value: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    if value.ir_type() != Some(ty) {
+        return Err(RuntimeError::InvalidUnaryOperand(
+            op,
+            value.type_name(),
+            span,
+        ));
+    }
+    unary(op, value, span)
+}
+
+// An array holds at most MAX_ARRAY_LEN plain values, nested arrays included,
+// counted as `len` copies of its first element the way the IR builds one
+pub fn check_array_size(len: usize, first: &Value, span: Span) -> Result<usize, RuntimeError> {
+    let size = len.saturating_mul(first.size());
+    if size > MAX_ARRAY_LEN {
+        return Err(RuntimeError::ArrayTooLarge(size, span));
+    }
+    Ok(size)
+}
+
+// `len` copies of `fill`. Nested arrays are built from the inside out, so the
+// limit is checked on every level and a failed allocation is an error, not an abort.
+pub fn new_array(fill: Value, len: usize, span: Span) -> Result<Value, RuntimeError> {
+    let size = check_array_size(len, &fill, span)?;
+    let mut elements = Vec::new();
+    elements
+        .try_reserve_exact(len)
+        .map_err(|_| RuntimeError::ArrayTooLarge(size, span))?;
+    elements.resize(len, fill);
+    Ok(Value::Array(elements))
+}
+
+pub fn int_to_float(value: Value) -> Value {
+    match value {
+        Value::Int(i) => Value::Float(i as f64),
+        value => value,
+    }
+}
+
+// truncates toward zero, out of range values saturate
+pub fn float_to_int(value: Value) -> Value {
+    match value {
+        Value::Float(f) => Value::Int(f as i64),
+        value => value,
+    }
+}
+
+fn invalid_index(array: &Value, index: &Value, span: Span) -> RuntimeError {
+    RuntimeError::InvalidIndex {
+        array: array.type_name(),
+        index: index.type_name(),
+        span,
+    }
+}
+
+// Where `index` is in `array`, checked against its length. A struct's fields are
+// indexed by their offset the same way.
+fn position(array: &Value, index: &Value, span: Span) -> Result<usize, RuntimeError> {
+    let (len, i) = match (array, index) {
+        (Value::Array(elements), Value::Int(i)) => (elements.len(), i),
+        (Value::Struct { fields, .. }, Value::Int(i)) => (fields.len(), i),
+        _ => return Err(invalid_index(array, index, span)),
+    };
+    usize::try_from(*i)
+        .ok()
+        .filter(|i| *i < len)
+        .ok_or(RuntimeError::IndexOutOfBounds {
+            index: *i,
+            len,
+            span,
+        })
+}
+
+pub fn element<'v>(array: &'v Value, index: &Value, span: Span) -> Result<&'v Value, RuntimeError> {
+    let i = position(array, index, span)?;
+    match array {
+        Value::Array(elements) => Ok(&elements[i]),
+        Value::Struct { fields, .. } => Ok(&fields[i].1),
+        _ => Err(invalid_index(array, index, span)),
+    }
+}
+
+pub fn element_mut<'v>(
+    array: &'v mut Value,
+    index: &Value,
+    span: Span,
+) -> Result<&'v mut Value, RuntimeError> {
+    let i = position(array, index, span)?;
+    match array {
+        Value::Array(elements) => Ok(&mut elements[i]),
+        Value::Struct { fields, .. } => Ok(&mut fields[i].1),
+        _ => Err(invalid_index(array, index, span)),
+    }
+}
@@ -0,0 +1,455 @@
+use super::errors::RuntimeError;
+use super::ops;
+use super::value::Value;
+use crate::ir::tac::{BinaryOp, UnaryOp};
+use crate::lexer::span::Span;
+use crate::lexer::tokens::Token;
+use crate::parser::enums::Constants;
+use crate::semantics::builtins;
+use crate::semantics::semantic_analysis::Type;
+use crate::semantics::typed_ast::{
+    Callee, Step, SymbolId, TypedBlock, TypedExpr, TypedExprKind, TypedFor, TypedFunction, TypedIf,
+    TypedProgram, TypedStmt, TypedStmtKind, TypedVarDecl, TypedWhile, place,
+};
+use std::collections::HashMap;
+use std::io::Write;
+
+// deep enough for normal recursion, every call also recurses through exec / eval
+// on the rust side, so the interpreter gets a thread with STACK_SIZE to do it in
+const MAX_CALL_DEPTH: usize = 1000;
+
+// a debug build takes ~20KB of stack per source level call, this leaves room for
+// MAX_CALL_DEPTH of them with deeply nested statements in each
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// What a statement asks its enclosing loop / function to do next
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+// Every declaration has its own symbol, so a call needs no block scopes
+type Scope = HashMap<SymbolId, Value>;
+
+// Runs a checked syntax tree directly, no IR involved
+pub struct Interpreter<'a> {
+    program: &'a TypedProgram,
+    functions: HashMap<SymbolId, &'a TypedFunction>,
+    globals: Scope,
+    frames: Vec<Scope>, // one per active call
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(program: &'a TypedProgram, out: &'a mut dyn Write) -> Self {
+        Interpreter {
+            program,
+            functions: program
+                .functions
+                .iter()
+                .map(|func| (func.symbol, func))
+                .collect(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            out,
+        }
+    }
+
+    // Initializes the globals top to bottom, then calls main() and returns what it returned
+    pub fn run(&mut self) -> Result<Value, RuntimeError> {
+        let program = self.program;
+        for var in &program.globals {
+            self.exec_var_decl(var)?;
+        }
+
+        let main = match program.function("main") {
+            Some(main) => main,
+            None => return Err(RuntimeError::MissingMain),
+        };
+        if !main.params.is_empty() {
+            return Err(RuntimeError::MainHasParameters(main.span));
+        }
+        self.call(main, Vec::new(), main.span)
+    }
+
+    fn lookup(&self, id: SymbolId) -> Option<&Value> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(&id))
+            .or_else(|| self.globals.get(&id))
+    }
+
+    fn lookup_mut(&mut self, id: SymbolId) -> Option<&mut Value> {
+        match self.frames.last_mut() {
+            Some(frame) if frame.contains_key(&id) => frame.get_mut(&id),
+            _ => self.globals.get_mut(&id),
+        }
+    }
+
+    fn declare(&mut self, id: SymbolId, value: Value) {
+        match self.frames.last_mut() {
+            Some(frame) => frame.insert(id, value),
+            None => self.globals.insert(id, value),
+        };
+    }
+
+    fn call(
+        &mut self,
+        func: &'a TypedFunction,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            let name = self.program.symbol(func.symbol).name.clone();
+            return Err(RuntimeError::StackOverflow(name, span));
+        }
+
+        // Parameter binding, semantics already checked the count and converted the types
+        let params = func.params.iter().copied().zip(args).collect();
+
+        self.frames.push(params);
+        let flow = self.exec_block(&func.body);
+        self.frames.pop();
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Void),
+        }
+    }
+
+    fn exec_block(&mut self, block: &'a TypedBlock) -> Result<Flow, RuntimeError> {
+        for statement in &block.statements {
+            match self.exec(statement)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec(&mut self, statement: &'a TypedStmt) -> Result<Flow, RuntimeError> {
+        match &statement.kind {
+            TypedStmtKind::VarDecl(var) => {
+                self.exec_var_decl(var)?;
+                Ok(Flow::Normal)
+            }
+            TypedStmtKind::Expr(expr) => {
+                self.eval(expr)?;
+                Ok(Flow::Normal)
+            }
+            TypedStmtKind::Return(Some(expr)) => Ok(Flow::Return(self.eval(expr)?)),
+            TypedStmtKind::Return(None) => Ok(Flow::Return(Value::Void)),
+            TypedStmtKind::Break => Ok(Flow::Break),
+            TypedStmtKind::Continue => Ok(Flow::Continue),
+            TypedStmtKind::If(if_stmt) => self.exec_if(if_stmt),
+            TypedStmtKind::While(while_stmt) => self.exec_while(while_stmt),
+            TypedStmtKind::For(for_stmt) => self.exec_for(for_stmt),
+        }
+    }
+
+    fn exec_var_decl(&mut self, var: &'a TypedVarDecl) -> Result<(), RuntimeError> {
+        // semantics makes sure a declaration without a value is assigned before any
+        // read, except for arrays, structs and globals which really start out zeroed
+        let value = match &var.value {
+            Some(value) => self.eval(value)?,
+            None => zero(
+                self.program,
+                &self.program.symbol(var.symbol).value_type(),
+                var.span,
+            )?,
+        };
+        self.declare(var.symbol, value);
+        Ok(())
+    }
+
+    fn exec_if(&mut self, if_stmt: &'a TypedIf) -> Result<Flow, RuntimeError> {
+        if self.eval_condition(&if_stmt.condition)? {
+            return self.exec_block(&if_stmt.block);
+        }
+        for (condition, block) in &if_stmt.elif_blocks {
+            if self.eval_condition(condition)? {
+                return self.exec_block(block);
+            }
+        }
+        match &if_stmt.else_block {
+            Some(block) => self.exec_block(block),
+            None => Ok(Flow::Normal),
+        }
+    }
+
+    fn exec_while(&mut self, while_stmt: &'a TypedWhile) -> Result<Flow, RuntimeError> {
+        while self.eval_condition(&while_stmt.condition)? {
+            match self.exec_block(&while_stmt.block)? {
+                Flow::Break => break,
+                Flow::Normal | Flow::Continue => {}
+                flow @ Flow::Return(_) => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_for(&mut self, for_stmt: &'a TypedFor) -> Result<Flow, RuntimeError> {
+        if let Some(init) = &for_stmt.init_var {
+            self.exec_var_decl(init)?;
+        }
+        loop {
+            if let Some(condition) = &for_stmt.condition
+                && !self.eval_condition(condition)?
+            {
+                break;
+            }
+            match self.exec_block(&for_stmt.block)? {
+                Flow::Break => break,
+                Flow::Normal | Flow::Continue => {}
+                flow @ Flow::Return(_) => return Ok(flow),
+            }
+            if let Some(update) = &for_stmt.update {
+                self.eval(update)?;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn eval_condition(&mut self, condition: &'a TypedExpr) -> Result<bool, RuntimeError> {
+        // semantics only lets bool conditions through
+        Ok(self.eval(condition)? == Value::Bool(true))
+    }
+
+    fn eval(&mut self, expr: &'a TypedExpr) -> Result<Value, RuntimeError> {
+        match &expr.kind {
+            TypedExprKind::Literal(c) => Ok(match c {
+                Constants::Int(i) => Value::Int(*i),
+                Constants::Float(f) => Value::Float(*f),
+                Constants::Str(s) => Value::Str(s.clone()),
+                Constants::Bool(b) => Value::Bool(*b),
+            }),
+            // semantics made sure every name is declared before use
+            TypedExprKind::Variable(id) => Ok(self.lookup(*id).cloned().unwrap_or(Value::Void)),
+            TypedExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => self.eval_binary(left, operator, right, expr.span),
+            TypedExprKind::Unary { operator, operand } => {
+                let value = self.eval(operand)?;
+                match UnaryOp::from_token(operator) {
+                    Some(op) => ops::unary(op, value, expr.span),
+                    None => Ok(value), // the parser only makes '-', '!' and '~' unary
+                }
+            }
+            TypedExprKind::Assignment { target, value } => {
+                self.eval_assign(target, value, None, expr.span)
+            }
+            TypedExprKind::CompoundAssignment {
+                target,
+                operator,
+                value,
+                postfix,
+            } => {
+                let Some(op) = BinaryOp::from_token(operator) else {
+                    return Ok(Value::Void); // semantics only makes arithmetic ones
+                };
+                self.eval_assign(target, value, Some((op, *postfix)), expr.span)
+            }
+            TypedExprKind::Index { array, index } => {
+                if let Some(value) = self.read_place(expr)? {
+                    return Ok(value);
+                }
+                let array = self.eval(array)?;
+                let index = self.eval(index)?;
+                ops::element(&array, &index, expr.span).cloned()
+            }
+            TypedExprKind::Field { object, field } => {
+                if let Some(value) = self.read_place(expr)? {
+                    return Ok(value);
+                }
+                let object = self.eval(object)?;
+                ops::element(&object, &offset(*field), expr.span).cloned()
+            }
+            TypedExprKind::StructLiteral(fields) => {
+                let mut value = zero(self.program, &expr.ty, expr.span)?;
+                for (field, init) in fields {
+                    let init = self.eval(init)?;
+                    *ops::element_mut(&mut value, &offset(*field), expr.span)? = init;
+                }
+                Ok(value)
+            }
+            TypedExprKind::ArrayLiteral(elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.eval(element)?);
+                }
+                if let Some(first) = values.first() {
+                    ops::check_array_size(values.len(), first, expr.span)?;
+                }
+                Ok(Value::Array(values))
+            }
+            TypedExprKind::Call { callee, args } => self.eval_call(callee, args, expr.span),
+            TypedExprKind::IntToFloat(inner) => Ok(ops::int_to_float(self.eval(inner)?)),
+            TypedExprKind::FloatToInt(inner) => Ok(ops::float_to_int(self.eval(inner)?)),
+            TypedExprKind::Invalid => Ok(Value::Void), // never in a checked program
+        }
+    }
+
+    // Indices left to right, then the value. With an operator the value is combined
+    // with what the place holds, `postfix` gives back the old value instead of the new
+    fn eval_assign(
+        &mut self,
+        target: &'a TypedExpr,
+        value: &'a TypedExpr,
+        update: Option<(BinaryOp, bool)>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let Some((id, steps)) = place(target) else {
+            return Ok(Value::Void); // semantics only lets places through
+        };
+        let indices = self.eval_steps(&steps)?;
+        let value = self.eval(value)?;
+        let Some(mut slot) = self.lookup_mut(id) else {
+            return Ok(value);
+        };
+        for index in &indices {
+            slot = ops::element_mut(slot, index, span)?;
+        }
+        let Some((op, postfix)) = update else {
+            *slot = value.clone();
+            return Ok(value);
+        };
+        let old = slot.clone();
+        let new = ops::binary(op, old.clone(), value, span)?;
+        *slot = new.clone();
+        Ok(if postfix { old } else { new })
+    }
+
+    // A part of a variable is read in place, without copying the whole of it
+    fn read_place(&mut self, expr: &'a TypedExpr) -> Result<Option<Value>, RuntimeError> {
+        let Some((id, steps)) = place(expr) else {
+            return Ok(None);
+        };
+        let indices = self.eval_steps(&steps)?;
+        let mut value = self.lookup(id).unwrap_or(&Value::Void);
+        for index in &indices {
+            value = ops::element(value, index, expr.span)?;
+        }
+        Ok(Some(value.clone()))
+    }
+
+    // The index every step takes, evaluated left to right
+    fn eval_steps(&mut self, steps: &[Step<'a>]) -> Result<Vec<Value>, RuntimeError> {
+        let mut values = Vec::new();
+        for step in steps {
+            values.push(match step {
+                Step::Index(index) => self.eval(index)?,
+                Step::Field(field) => offset(*field),
+            });
+        }
+        Ok(values)
+    }
+
+    fn eval_call(
+        &mut self,
+        callee: &Callee,
+        arg_exprs: &'a [TypedExpr],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let mut args = Vec::new();
+        for arg in arg_exprs {
+            args.push(self.eval(arg)?);
+        }
+
+        match callee {
+            Callee::Function(id) => match self.functions.get(id) {
+                Some(func) => self.call(func, args, span),
+                None => {
+                    let name = self.program.symbol(*id).name.clone();
+                    Err(RuntimeError::UnknownFunction(name, span))
+                }
+            },
+            Callee::Builtin(name) if name == builtins::LEN => match args.first() {
+                Some(Value::Array(elements)) => Ok(Value::Int(elements.len() as i64)),
+                _ => Ok(Value::Void), // semantics checked it gets one array
+            },
+            Callee::Builtin(_) => {
+                let line = args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(self.out, "{}", line)
+                    .map_err(|e| RuntimeError::Output(e.to_string(), span))?;
+                Ok(Value::Void)
+            }
+        }
+    }
+
+    fn eval_binary(
+        &mut self,
+        left: &'a TypedExpr,
+        operator: &Token,
+        right: &'a TypedExpr,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let l = self.eval(left)?;
+        let Some(op) = BinaryOp::from_token(operator) else {
+            return Ok(l); // the parser only builds binary operations from operator tokens
+        };
+
+        // && and || only look at the right side when they have to
+        if let Value::Bool(b) = l
+            && ((op == BinaryOp::And && !b) || (op == BinaryOp::Or && b))
+        {
+            return Ok(Value::Bool(b));
+        }
+
+        let r = self.eval(right)?;
+        ops::binary(op, l, r, span)
+    }
+}
+
+// fields are indexed by their offset, see ops::element
+fn offset(field: usize) -> Value {
+    Value::Int(field as i64)
+}
+
+// What an array or struct declared without a value is filled with
+fn zero(program: &TypedProgram, ty: &Type, span: Span) -> Result<Value, RuntimeError> {
+    let value = match ty {
+        Type::Int => Value::Int(0),
+        Type::Float => Value::Float(0.0),
+        Type::Bool => Value::Bool(false),
+        Type::String => Value::Str(String::new()),
+        Type::Array(element, size) => {
+            return ops::new_array(zero(program, element, span)?, size.unwrap_or(0), span);
+        }
+        Type::Struct(name) => match program.struct_def(name) {
+            Some(def) => Value::Struct {
+                name: name.clone(),
+                fields: def
+                    .fields
+                    .iter()
+                    .map(|field| Ok((field.name.clone(), zero(program, &field.ty, span)?)))
+                    .collect::<Result<_, RuntimeError>>()?,
+            },
+            None => Value::Void,
+        },
+        Type::Void | Type::Unknown => Value::Void,
+    };
+    Ok(value)
+}
+
+pub fn interpret(
+    program: &TypedProgram,
+    out: &mut (dyn Write + Send),
+) -> Result<Value, RuntimeError> {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("interpreter".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || Interpreter::new(program, out).run())
+            .expect("failed to start the interpreter thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
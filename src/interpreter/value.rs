@@ -0,0 +1,76 @@
+use crate::ir::tac::IrType;
+use std::fmt;
+
+// A value a running program can hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Array(Vec<Value>), // copied like any other value, assigning one never aliases
+    Struct {
+        // fields in declaration order, copied like arrays
+        name: String,
+        fields: Vec<(String, Value)>,
+    },
+    Void, // what void functions "return"
+}
+
+impl Value {
+    // Type name the way it is written in source, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+            Value::Struct { .. } => "struct",
+            Value::Void => "void",
+        }
+    }
+
+    // The IR type an operation on this value is typed with
+    pub fn ir_type(&self) -> Option<IrType> {
+        match self {
+            Value::Int(_) => Some(IrType::Int),
+            Value::Float(_) => Some(IrType::Float),
+            Value::Bool(_) => Some(IrType::Bool),
+            Value::Str(_) => Some(IrType::Str),
+            Value::Array(_) | Value::Struct { .. } | Value::Void => None,
+        }
+    }
+
+    // How many plain values this is made of, 1 unless it is an array or a struct
+    pub fn size(&self) -> usize {
+        match self {
+            Value::Array(elements) => elements.iter().map(Value::size).sum(),
+            Value::Struct { fields, .. } => fields.iter().map(|(_, value)| value.size()).sum(),
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{:?}", x), // keeps the ".0" on whole numbers
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Value::Struct { name, fields } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Value::Void => write!(f, "void"),
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use super::errors::RuntimeError;
+use super::ops;
+use super::value::Value;
+use crate::ir::tac::{Constant, Function, Instr, Label, Module, Operand};
+use crate::lexer::span::Span;
+use crate::semantics::builtins;
+use std::collections::HashMap;
+use std::io::Write;
+
+// same limit as the tree walker so both paths fail on the same programs, frames
+// live in `stack` so no limit on the rust side applies here
+const MAX_CALL_DEPTH: usize = 1000;
+
+// A function's instructions flattened back into one list, with every label resolved
+// to the index it marks
+struct Code<'a> {
+    name: &'a str,
+    params: &'a [String],
+    instrs: Vec<&'a Instr>,
+    labels: HashMap<Label, usize>,
+    span: Span, // the function's name, for errors about the function itself
+}
+
+impl<'a> Code<'a> {
+    fn new(name: &'a str, params: &'a [String], instrs: Vec<&'a Instr>, span: Span) -> Self {
+        let labels = instrs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instr)| match instr {
+                Instr::Label(label) => Some((*label, i)),
+                _ => None,
+            })
+            .collect();
+        Code {
+            name,
+            params,
+            instrs,
+            labels,
+            span,
+        }
+    }
+
+    fn from_function(func: &'a Function) -> Self {
+        Code::new(&func.name, &func.params, func.instrs().collect(), func.span)
+    }
+}
+
+// One activation on the call stack
+struct Frame {
+    code: usize, // index into Vm::code
+    pc: usize,
+    vars: HashMap<String, Value>,
+    temps: HashMap<usize, Value>,
+    result: Option<Operand>, // where the caller wants the return value
+}
+
+// Executes the TAC emitted by IrGenerator. A Var lives in the current frame and a
+// Global in the globals, every local has its own name so no block scopes are needed.
+pub struct Vm<'a> {
+    code: Vec<Code<'a>>,
+    functions: HashMap<&'a str, usize>,
+    globals: HashMap<String, Value>,
+    stack: Vec<Frame>,
+    args: Vec<Value>, // pushed by Param, popped by the callee on entry
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(module: &'a Module, out: &'a mut dyn Write) -> Self {
+        // code[0] is the global initializers, then every function
+        let global_span = Span::new(0, 0, 1, 1);
+        let mut code = vec![Code::new(
+            "<globals>",
+            &[],
+            module.globals.iter().collect(),
+            global_span,
+        )];
+        let mut functions = HashMap::new();
+        for func in &module.functions {
+            functions.insert(func.name.as_str(), code.len());
+            code.push(Code::from_function(func));
+        }
+
+        Vm {
+            code,
+            functions,
+            globals: HashMap::new(),
+            stack: Vec::new(),
+            args: Vec::new(),
+            out,
+        }
+    }
+
+    // Runs the global initializers, then main(), and returns what main returned
+    pub fn run(&mut self) -> Result<Value, RuntimeError> {
+        self.push_frame(0, None, Span::new(0, 0, 1, 1))?;
+        self.execute()?;
+
+        let main = match self.functions.get("main") {
+            Some(main) => *main,
+            None => return Err(RuntimeError::MissingMain),
+        };
+        if !self.code[main].params.is_empty() {
+            return Err(RuntimeError::MainHasParameters(self.code[main].span));
+        }
+        let span = self.code[main].span;
+        self.push_frame(main, None, span)?;
+        self.execute()
+    }
+
+    fn push_frame(
+        &mut self,
+        code: usize,
+        result: Option<Operand>,
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        if self.stack.len() >= MAX_CALL_DEPTH {
+            let name = self.code[code].name.to_string();
+            return Err(RuntimeError::StackOverflow(name, span));
+        }
+
+        // PopParam, arguments were pushed in order so they come off the end
+        let params = self.code[code].params;
+        let args = self
+            .args
+            .split_off(self.args.len().saturating_sub(params.len()));
+        let vars = params.iter().cloned().zip(args).collect();
+
+        self.stack.push(Frame {
+            code,
+            pc: 0,
+            vars,
+            temps: HashMap::new(),
+            result,
+        });
+        Ok(())
+    }
+
+    // Pops the finished frame and hands its value to the caller, if there is one
+    fn return_from(&mut self, value: Value) -> Option<Value> {
+        let frame = self.stack.pop()?;
+        match (self.stack.is_empty(), frame.result) {
+            (true, _) => Some(value),
+            (false, Some(dest)) => {
+                self.store(&dest, value);
+                None
+            }
+            (false, None) => None,
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        &self.stack[self.stack.len() - 1]
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        let last = self.stack.len() - 1;
+        &mut self.stack[last]
+    }
+
+    fn load(&self, operand: &Operand) -> Value {
+        match operand {
+            Operand::Const(c) => match c {
+                Constant::Int(i) => Value::Int(*i),
+                Constant::Float(f) => Value::Float(*f),
+                Constant::Bool(b) => Value::Bool(*b),
+                Constant::Str(s) => Value::Str(s.clone()),
+            },
+            _ => self.get(operand).cloned().unwrap_or(Value::Void),
+        }
+    }
+
+    // What a Temp or Var holds, without copying it
+    fn get(&self, operand: &Operand) -> Option<&Value> {
+        let frame = self.frame();
+        match operand {
+            Operand::Temp(n) => frame.temps.get(n),
+            Operand::Var(name) => frame.vars.get(name),
+            Operand::Global(name) => self.globals.get(name),
+            Operand::Const(_) => None,
+        }
+    }
+
+    // Where a store to `dest` goes, created empty when it doesn't exist yet
+    fn slot(&mut self, dest: &Operand) -> Option<&mut Value> {
+        match dest {
+            Operand::Temp(n) => Some(self.frame_mut().temps.entry(*n).or_insert(Value::Void)),
+            Operand::Var(name) => Some(
+                self.frame_mut()
+                    .vars
+                    .entry(name.clone())
+                    .or_insert(Value::Void),
+            ),
+            Operand::Global(name) => Some(self.globals.entry(name.clone()).or_insert(Value::Void)),
+            Operand::Const(_) => None, // never a destination
+        }
+    }
+
+    fn store(&mut self, dest: &Operand, value: Value) {
+        if let Some(slot) = self.slot(dest) {
+            *slot = value;
+        }
+    }
+
+    fn jump(&mut self, label: Label) {
+        let code = &self.code[self.frame().code];
+        let target = code
+            .labels
+            .get(&label)
+            .copied()
+            .unwrap_or(code.instrs.len());
+        self.frame_mut().pc = target;
+    }
+
+    // Runs until the frame that was on top when called returns
+    fn execute(&mut self) -> Result<Value, RuntimeError> {
+        let depth = self.stack.len();
+        loop {
+            let frame = self.frame();
+            let code = &self.code[frame.code];
+
+            // every function returns before running off its end, this is for the globals
+            let Some(instr) = code.instrs.get(frame.pc).copied() else {
+                let returned = self.return_from(Value::Void);
+                if self.stack.len() < depth {
+                    return Ok(returned.unwrap_or(Value::Void));
+                }
+                continue;
+            };
+            self.frame_mut().pc += 1;
+
+            match instr {
+                Instr::Assign { dest, src } => {
+                    let value = self.load(src);
+                    self.store(dest, value);
+                }
+                Instr::BinOp {
+                    dest,
+                    op,
+                    ty,
+                    left,
+                    right,
+                    span,
+                } => {
+                    let (l, r) = (self.load(left), self.load(right));
+                    let value = ops::typed_binary(*op, *ty, l, r, *span)?;
+                    self.store(dest, value);
+                }
+                Instr::UnOp {
+                    dest,
+                    op,
+                    ty,
+                    operand,
+                    span,
+                } => {
+                    let value = ops::typed_unary(*op, *ty, self.load(operand), *span)?;
+                    self.store(dest, value);
+                }
+                Instr::IntToFloat { dest, src } => {
+                    let value = ops::int_to_float(self.load(src));
+                    self.store(dest, value);
+                }
+                Instr::FloatToInt { dest, src } => {
+                    let value = ops::float_to_int(self.load(src));
+                    self.store(dest, value);
+                }
+                Instr::NewArray {
+                    dest,
+                    len,
+                    fill,
+                    span,
+                } => {
+                    let value = ops::new_array(self.load(fill), *len, *span)?;
+                    self.store(dest, value);
+                }
+                Instr::NewStruct { dest, name, fields } => {
+                    // the generator stores every field right after
+                    let fields = fields.iter().map(|f| (f.clone(), Value::Void)).collect();
+                    let value = Value::Struct {
+                        name: name.clone(),
+                        fields,
+                    };
+                    self.store(dest, value);
+                }
+                Instr::Load {
+                    dest,
+                    array,
+                    index,
+                    span,
+                } => {
+                    // the element is copied out, not the whole array
+                    let index = self.load(index);
+                    let array = self.get(array).unwrap_or(&Value::Void);
+                    let value = ops::element(array, &index, *span)?.clone();
+                    self.store(dest, value);
+                }
+                Instr::Store {
+                    array,
+                    index,
+                    src,
+                    span,
+                } => {
+                    let (index, value) = (self.load(index), self.load(src));
+                    if let Some(array) = self.slot(array) {
+                        *ops::element_mut(array, &index, *span)? = value;
+                    }
+                }
+                Instr::IfZ { cond, target } => {
+                    if self.load(cond) != Value::Bool(true) {
+                        self.jump(*target);
+                    }
+                }
+                Instr::Goto(target) => self.jump(*target),
+                Instr::Param(operand) => {
+                    let value = self.load(operand);
+                    self.args.push(value);
+                }
+                Instr::Call {
+                    dest,
+                    func,
+                    argc,
+                    span,
+                } => {
+                    if let Some(&callee) = self.functions.get(func.as_str()) {
+                        self.push_frame(callee, Some(dest.clone()), *span)?;
+                    } else if builtins::is_builtin(func) {
+                        let args = self.args.split_off(self.args.len().saturating_sub(*argc));
+                        let value = self.call_builtin(func, args, *span)?;
+                        self.store(dest, value);
+                    } else {
+                        return Err(RuntimeError::UnknownFunction(func.clone(), *span));
+                    }
+                }
+                Instr::Return(operand) => {
+                    let value = match operand {
+                        Some(operand) => self.load(operand),
+                        None => Value::Void,
+                    };
+                    let returned = self.return_from(value);
+                    if self.stack.len() < depth {
+                        return Ok(returned.unwrap_or(Value::Void));
+                    }
+                }
+                Instr::Label(_) => {}
+            }
+        }
+    }
+
+    fn call_builtin(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        if name == builtins::LEN {
+            return Ok(match args.first() {
+                Some(Value::Array(elements)) => Value::Int(elements.len() as i64),
+                _ => Value::Void, // semantics checked it gets one array
+            });
+        }
+
+        let line = args
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(self.out, "{}", line).map_err(|e| RuntimeError::Output(e.to_string(), span))?;
+        Ok(Value::Void)
+    }
+}
+
+pub fn execute(module: &Module, out: &mut dyn Write) -> Result<Value, RuntimeError> {
+    Vm::new(module, out).run()
+}
@@ -1,181 +1,686 @@
+use super::tac::{BinaryOp, Constant, Function, Instr, IrType, Label, Module, Operand, UnaryOp};
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
 use crate::lexer::tokens::Token;
-use crate::parser::enums::{
-    Block, Constants, Expression, ForStatement, FunctionStatement, IfStatement, Root, RootList,
-    Statement, VariableDeclaration, WhileStatement,
+use crate::parser::enums::Constants;
+use crate::semantics::semantic_analysis::Type;
+use crate::semantics::typed_ast::{
+    Callee, Step, SymbolId, TypedBlock, TypedExpr, TypedExprKind, TypedFor, TypedFunction, TypedIf,
+    TypedProgram, TypedStmt, TypedStmtKind, TypedVarDecl, TypedWhile, place,
 };
+use std::collections::HashSet;
+
+// What values of a source type look like in the IR, void and unknown have none
+fn ir_type(ty: &Type) -> Option<IrType> {
+    match ty {
+        Type::Int => Some(IrType::Int),
+        Type::Float => Some(IrType::Float),
+        Type::Bool => Some(IrType::Bool),
+        Type::String => Some(IrType::Str),
+        Type::Array(..) | Type::Struct(_) | Type::Void | Type::Unknown => None,
+    }
+}
 
-pub struct IrGenerator {
+pub struct IrGenerator<'a> {
+    program: &'a TypedProgram,
+    globals: HashSet<SymbolId>,
     temp_counter: usize,
     label_counter: usize,
-    code: Vec<String>,
-    loop_stack: Vec<(String, String)>, // (continue_label, break_label)
+    code: Vec<Instr>, // instructions of the function (or global) being generated
+    module: Module,
+    loop_stack: Vec<(Label, Label)>, // (continue_label, break_label)
+    errors: Vec<Diagnostic>,
 }
 
-impl IrGenerator {
-    pub fn new() -> Self {
+impl<'a> IrGenerator<'a> {
+    pub fn new(program: &'a TypedProgram) -> Self {
         Self {
+            program,
+            globals: program.globals.iter().map(|var| var.symbol).collect(),
             temp_counter: 0,
             label_counter: 0,
             code: Vec::new(),
+            module: Module::default(),
             loop_stack: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    fn new_temp(&mut self) -> String {
-        let temp = format!("t{}", self.temp_counter);
+    fn new_temp(&mut self) -> Operand {
+        let temp = Operand::Temp(self.temp_counter);
         self.temp_counter += 1;
         temp
     }
 
-    fn new_label(&mut self) -> String {
-        let label = format!("L{}", self.label_counter);
+    fn new_label(&mut self) -> Label {
+        let label = Label(self.label_counter);
         self.label_counter += 1;
         label
     }
 
-    fn emit(&mut self, instr: String) {
+    // Globals keep their source names, locals get their symbol id appended so a
+    // shadowing `x` and the `x` it shadows are different variables
+    fn var(&self, id: SymbolId) -> Operand {
+        let name = &self.program.symbol(id).name;
+        match self.globals.contains(&id) {
+            true => Operand::Global(name.clone()),
+            false => Operand::Var(format!("{}.{}", name, id)),
+        }
+    }
+
+    fn emit(&mut self, instr: Instr) {
         self.code.push(instr);
     }
 
-    pub fn generate_ir(&mut self, ast: &RootList) -> Result<String, ()> {
-        for root in ast {
-            match root {
-                Root::Func(func) => self.gen_func(func),
-                Root::Var(var) => {
-                    self.gen_var_decl(var);
-                }
-            }
+    // A variable operand is only read when the instruction using it runs, so when
+    // code evaluated before that can change it, the value is copied out now
+    fn hold(&mut self, value: Operand, changed_later: bool) -> Operand {
+        if !changed_later || !matches!(value, Operand::Var(_) | Operand::Global(_)) {
+            return value;
+        }
+        let temp = self.new_temp();
+        self.emit(Instr::Assign {
+            dest: temp.clone(),
+            src: value,
+        });
+        temp
+    }
+
+    // Type of an operand, the typed tree only has void / unknown ones in bad programs
+    fn operand_type(&mut self, expr: &TypedExpr) -> IrType {
+        ir_type(&expr.ty).unwrap_or_else(|| {
+            self.internal_error(format!("operand of type '{}'", expr.ty), expr.span);
+            IrType::Int
+        })
+    }
+
+    // Semantic analysis should have rejected anything that ends up here
+    fn internal_error(&mut self, message: String, span: Span) {
+        self.errors.push(
+            Diagnostic::error(codes::IR_GENERATION, message, span)
+                .with_note("this should have been caught by semantic analysis"),
+        );
+    }
+
+    pub fn generate_ir(&mut self) -> Result<Module, Vec<Diagnostic>> {
+        let program = self.program;
+        for var in &program.globals {
+            self.gen_var_decl(var);
+        }
+        self.module.globals = std::mem::take(&mut self.code);
+
+        for func in &program.functions {
+            let func = self.gen_func(func);
+            self.module.functions.push(func);
+        }
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
-        Ok(self.code.join("\n"))
+        Ok(std::mem::take(&mut self.module))
     }
 
-    fn gen_func(&mut self, func: &FunctionStatement) {
-        self.emit(format!("{}:", func.identifier));
-        self.emit("BeginFunc".to_string());
+    fn gen_func(&mut self, func: &TypedFunction) -> Function {
+        self.gen_block(&func.body);
 
-        // Parameters
-        for param in &func.parameters {
-            self.emit(format!("PopParam {}", param.identifier));
+        // falling off the end returns from a void function, the flow checks made
+        // sure every other function returns on every path
+        if func.return_type == Type::Void && !matches!(self.code.last(), Some(Instr::Return(_))) {
+            self.emit(Instr::Return(None));
         }
 
-        self.gen_block(&func.block);
-        self.emit("EndFunc".to_string());
+        let params = func
+            .params
+            .iter()
+            .map(|param| self.var(*param).to_string())
+            .collect();
+        let name = self.program.symbol(func.symbol).name.clone();
+        let code = std::mem::take(&mut self.code);
+        Function::new(name, params, code, func.span)
     }
 
-    fn gen_block(&mut self, block: &Block) {
+    fn gen_block(&mut self, block: &TypedBlock) {
         for stmt in &block.statements {
             self.gen_stmt(stmt);
         }
     }
 
-    fn gen_stmt(&mut self, stmt: &Statement) {
-        match stmt {
-            Statement::VarDecl(var) => self.gen_var_decl(var),
-            Statement::Expr(expr) => {
-                self.gen_expr(expr);
-            }
-            Statement::Return(expr) => {
-                let val = self.gen_expr(expr);
-                self.emit(format!("Return {}", val));
+    fn gen_stmt(&mut self, stmt: &TypedStmt) {
+        match &stmt.kind {
+            TypedStmtKind::VarDecl(var) => self.gen_var_decl(var),
+            TypedStmtKind::Expr(expr) => self.gen_discarded(expr),
+            TypedStmtKind::Return(expr) => {
+                let val = expr.as_ref().map(|expr| self.gen_expr(expr));
+                self.emit(Instr::Return(val));
             }
-            Statement::Break => {
-                if let Some((_, end_label)) = self.loop_stack.last() {
-                    self.emit(format!("Goto {}", end_label));
+            TypedStmtKind::Break => {
+                if let Some(&(_, end_label)) = self.loop_stack.last() {
+                    self.emit(Instr::Goto(end_label));
+                } else {
+                    self.internal_error("'break' outside of a loop".to_string(), stmt.span);
                 }
             }
-            Statement::Continue => {
-                if let Some((continue_label, _)) = self.loop_stack.last() {
-                    self.emit(format!("Goto {}", continue_label));
+            TypedStmtKind::Continue => {
+                if let Some(&(continue_label, _)) = self.loop_stack.last() {
+                    self.emit(Instr::Goto(continue_label));
+                } else {
+                    self.internal_error("'continue' outside of a loop".to_string(), stmt.span);
                 }
             }
-            Statement::If(if_stmt) => self.gen_if(if_stmt),
-            Statement::While(while_stmt) => self.gen_while(while_stmt),
-            Statement::For(for_stmt) => self.gen_for(for_stmt),
+            TypedStmtKind::If(if_stmt) => self.gen_if(if_stmt),
+            TypedStmtKind::While(while_stmt) => self.gen_while(while_stmt),
+            TypedStmtKind::For(for_stmt) => self.gen_for(for_stmt),
+        }
+    }
+
+    // An expression whose value isn't used, an assignment needs no copy of it then
+    fn gen_discarded(&mut self, expr: &TypedExpr) {
+        match &expr.kind {
+            TypedExprKind::Assignment { target, value } => {
+                self.gen_assign(target, value, None, expr.span);
+            }
+            _ => {
+                self.gen_expr(expr);
+            }
         }
     }
 
-    fn gen_var_decl(&mut self, var: &VariableDeclaration) {
-        let val = self.gen_expr(&var.expression);
-        self.emit(format!("{} = {}", var.identifier, val));
+    fn gen_var_decl(&mut self, var: &TypedVarDecl) {
+        // `int x;` still defines x, with a zero semantics makes sure is never read.
+        // Arrays, structs and globals really start out zeroed.
+        let val = match &var.value {
+            Some(value) => self.gen_expr(value),
+            None => {
+                let ty = self.program.symbol(var.symbol).value_type();
+                self.gen_zero(&ty, var.span)
+            }
+        };
+        self.emit(Instr::Assign {
+            dest: self.var(var.symbol),
+            src: val,
+        });
     }
 
-    fn gen_expr(&mut self, expr: &Expression) -> String {
-        match expr {
-            Expression::Literal(c) => match c {
-                Constants::Int(i) => i.to_string(),
-                Constants::Float(f) => f.to_string(),
-                Constants::Str(s) => format!("\"{}\"", s),
-                Constants::Bool(b) => b.to_string(),
+    fn gen_zero(&mut self, ty: &Type, span: Span) -> Operand {
+        match ty {
+            Type::Array(element, Some(len)) => {
+                let fill = self.gen_zero(element, span);
+                let temp = self.new_temp();
+                self.emit(Instr::NewArray {
+                    dest: temp.clone(),
+                    len: *len,
+                    fill,
+                    span,
+                });
+                temp
+            }
+            Type::Struct(name) => {
+                let Some(def) = self.program.struct_def(name) else {
+                    self.internal_error(format!("unknown struct '{}'", name), span);
+                    return self.new_temp();
+                };
+                let fields: Vec<Operand> = def
+                    .fields
+                    .iter()
+                    .map(|field| self.gen_zero(&field.ty, span))
+                    .collect();
+                self.gen_struct(name, fields.into_iter().enumerate().collect(), span)
+            }
+            _ => match ir_type(ty) {
+                Some(ty) => Operand::Const(Constant::zero(ty)),
+                None => {
+                    self.internal_error(format!("zero value of type '{}'", ty), span);
+                    self.new_temp()
+                }
             },
-            Expression::Identifier(id) => id.clone(),
-            Expression::BinaryOperation {
+        }
+    }
+
+    fn gen_expr(&mut self, expr: &TypedExpr) -> Operand {
+        match &expr.kind {
+            TypedExprKind::Literal(c) => Operand::Const(match c {
+                Constants::Int(i) => Constant::Int(*i),
+                Constants::Float(f) => Constant::Float(*f),
+                Constants::Str(s) => Constant::Str(s.clone()),
+                Constants::Bool(b) => Constant::Bool(*b),
+            }),
+            TypedExprKind::Variable(id) => self.var(*id),
+            TypedExprKind::Binary {
                 left,
                 operator,
                 right,
             } => {
+                if matches!(operator, Token::T_AND_OPR | Token::T_OR_OPR) {
+                    return self.gen_logical(left, operator, right);
+                }
                 let l = self.gen_expr(left);
+                let l = self.hold(l, has_side_effects(right));
                 let r = self.gen_expr(right);
+                let Some(op) = BinaryOp::from_token(operator) else {
+                    self.internal_error(
+                        format!("{} is not a binary operator", operator),
+                        expr.span,
+                    );
+                    return l;
+                };
+                let ty = self.operand_type(left);
+                self.gen_binop(op, ty, l, r, expr.span)
+            }
+            TypedExprKind::Unary { operator, operand } => {
+                let e = self.gen_expr(operand);
+                let Some(op) = UnaryOp::from_token(operator) else {
+                    self.internal_error(format!("{} is not a unary operator", operator), expr.span);
+                    return e;
+                };
+                let ty = self.operand_type(operand);
+                if !op.supports(ty) {
+                    self.internal_error(format!("there is no opcode {}{}", op, ty), expr.span);
+                }
                 let temp = self.new_temp();
-                let op_str = self.token_to_op(operator);
-                self.emit(format!("{} = {} {} {}", temp, l, op_str, r));
+                self.emit(Instr::UnOp {
+                    dest: temp.clone(),
+                    op,
+                    ty,
+                    operand: e,
+                    span: expr.span,
+                });
                 temp
             }
-            Expression::UnaryOperation {
+            // a copy of what was stored, later changes to the variable don't show through
+            TypedExprKind::Assignment { target, value } => {
+                let stored = self.gen_assign(target, value, None, expr.span);
+                self.hold(stored, true)
+            }
+            TypedExprKind::CompoundAssignment {
+                target,
                 operator,
-                expression,
+                value,
+                postfix,
             } => {
-                let e = self.gen_expr(expression);
+                let Some(op) = BinaryOp::from_token(operator) else {
+                    self.internal_error(
+                        format!("{} is not a binary operator", operator),
+                        expr.span,
+                    );
+                    return self.new_temp();
+                };
+                self.gen_assign(target, value, Some((op, *postfix)), expr.span)
+            }
+            TypedExprKind::Index { array, index } => {
+                if let Some(value) = self.gen_place_read(expr) {
+                    return value;
+                }
+                let base = self.gen_expr(array);
+                let base = self.hold(base, has_side_effects(index));
+                let index = self.gen_expr(index);
+                self.gen_load(base, index, expr.span)
+            }
+            // the first element doubles as the fill, the rest are stored over it
+            TypedExprKind::ArrayLiteral(elements) => {
+                let values: Vec<Operand> = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| {
+                        let value = self.gen_expr(e);
+                        self.hold(value, elements[i + 1..].iter().any(has_side_effects))
+                    })
+                    .collect();
                 let temp = self.new_temp();
-                let op_str = self.token_to_op(operator);
-                self.emit(format!("{} = {} {}", temp, op_str, e));
+                let fill = values
+                    .first()
+                    .cloned()
+                    .unwrap_or(Operand::Const(Constant::Int(0)));
+                self.emit(Instr::NewArray {
+                    dest: temp.clone(),
+                    len: values.len(),
+                    fill,
+                    span: expr.span,
+                });
+                for (i, src) in values.into_iter().enumerate().skip(1) {
+                    self.emit(Instr::Store {
+                        array: temp.clone(),
+                        index: Operand::Const(Constant::Int(i as i64)),
+                        src,
+                        span: expr.span,
+                    });
+                }
                 temp
             }
-            Expression::Assignment { left, right } => {
-                let r = self.gen_expr(right);
-                match &**left {
-                    Expression::Identifier(id) => {
-                        self.emit(format!("{} = {}", id, r));
-                        id.clone()
-                    }
-                    _ => panic!("L value must be an identifier"),
+            TypedExprKind::Field { object, field } => {
+                if let Some(value) = self.gen_place_read(expr) {
+                    return value;
                 }
+                let object = self.gen_expr(object);
+                self.gen_load(object, offset(*field), expr.span)
+            }
+            // values in source order, then the struct is built from them
+            TypedExprKind::StructLiteral(fields) => {
+                let Type::Struct(name) = &expr.ty else {
+                    self.internal_error(
+                        "struct literal without a struct type".to_string(),
+                        expr.span,
+                    );
+                    return self.new_temp();
+                };
+                let values = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (field, value))| {
+                        let value = self.gen_expr(value);
+                        let later = fields[i + 1..].iter().any(|(_, v)| has_side_effects(v));
+                        (*field, self.hold(value, later))
+                    })
+                    .collect();
+                self.gen_struct(name, values, expr.span)
             }
-            Expression::FunctionCall(call) => {
-                for arg in &call.args {
+            TypedExprKind::Call { callee, args } => {
+                for arg in args {
                     let a = self.gen_expr(arg);
-                    self.emit(format!("Param {}", a));
+                    self.emit(Instr::Param(a));
                 }
+                let func = match callee {
+                    Callee::Function(id) => self.program.symbol(*id).name.clone(),
+                    Callee::Builtin(name) => name.clone(),
+                };
+                let temp = self.new_temp();
+                self.emit(Instr::Call {
+                    dest: temp.clone(),
+                    func,
+                    argc: args.len(),
+                    span: expr.span,
+                });
+                temp
+            }
+            TypedExprKind::IntToFloat(inner) => {
+                let src = self.gen_expr(inner);
                 let temp = self.new_temp();
-                self.emit(format!(
-                    "{} = Call {}, {}",
-                    temp,
-                    call.identifier,
-                    call.args.len()
-                ));
+                self.emit(Instr::IntToFloat {
+                    dest: temp.clone(),
+                    src,
+                });
                 temp
             }
+            TypedExprKind::FloatToInt(inner) => {
+                let src = self.gen_expr(inner);
+                let temp = self.new_temp();
+                self.emit(Instr::FloatToInt {
+                    dest: temp.clone(),
+                    src,
+                });
+                temp
+            }
+            TypedExprKind::Invalid => {
+                self.internal_error("expression failed to type check".to_string(), expr.span);
+                self.new_temp()
+            }
+        }
+    }
+
+    // A new struct with every field stored at its offset
+    fn gen_struct(&mut self, name: &str, values: Vec<(usize, Operand)>, span: Span) -> Operand {
+        let fields = match self.program.struct_def(name) {
+            Some(def) => def.fields.iter().map(|field| field.name.clone()).collect(),
+            None => Vec::new(),
+        };
+        let temp = self.new_temp();
+        self.emit(Instr::NewStruct {
+            dest: temp.clone(),
+            name: name.to_string(),
+            fields,
+        });
+        for (field, src) in values {
+            self.emit(Instr::Store {
+                array: temp.clone(),
+                index: offset(field),
+                src,
+                span,
+            });
+        }
+        temp
+    }
+
+    // Indices left to right, then the value. Arrays and structs are values, so
+    // storing into a nested one loads the inner value, changes it and stores it back:
+    //
+    //     a[i].x = v         t0 = a[i]
+    //                        t0[0] = v
+    //                        a[i] = t0
+    //
+    // With an operator the old value is loaded from the same place and combined with
+    // the value first, so `a[f()] += 1` calls f once:
+    //
+    //     a[i] += v          t0 = a[i]
+    //                        t1 = t0 + v
+    //                        a[i] = t1
+    fn gen_assign(
+        &mut self,
+        target: &TypedExpr,
+        value: &TypedExpr,
+        update: Option<(BinaryOp, bool)>,
+        span: Span,
+    ) -> Operand {
+        let Some((id, steps)) = place(target) else {
+            self.internal_error(
+                "assignment to something that isn't a variable".to_string(),
+                target.span,
+            );
+            return self.new_temp();
+        };
+
+        let indices = self.gen_steps(steps, has_side_effects(value));
+        let mut src = self.gen_expr(value);
+        let dest = self.var(id);
+
+        // arrays[k] is the array indices[k] goes into
+        let mut arrays = vec![dest.clone()];
+        for index in indices.iter().take(indices.len().saturating_sub(1)) {
+            let temp = self.gen_load(arrays[arrays.len() - 1].clone(), index.clone(), span);
+            arrays.push(temp);
+        }
+
+        // what the expression gives back
+        let mut result = if indices.is_empty() {
+            dest.clone()
+        } else {
+            src.clone()
+        };
+        if let Some((op, postfix)) = update {
+            let old = match indices.last() {
+                Some(index) => self.gen_load(arrays[arrays.len() - 1].clone(), index.clone(), span),
+                // `x++` needs x from before the store
+                None if postfix => {
+                    let temp = self.new_temp();
+                    self.emit(Instr::Assign {
+                        dest: temp.clone(),
+                        src: dest.clone(),
+                    });
+                    temp
+                }
+                None => dest.clone(),
+            };
+            let ty = self.operand_type(target);
+            src = self.gen_binop(op, ty, old.clone(), src, span);
+            result = if postfix { old } else { src.clone() };
+        }
+
+        if indices.is_empty() {
+            self.emit(Instr::Assign { dest, src });
+            return result;
+        }
+
+        let mut changed = src;
+        for (array, index) in arrays.into_iter().zip(indices).rev() {
+            self.emit(Instr::Store {
+                array: array.clone(),
+                index,
+                src: changed,
+                span,
+            });
+            changed = array;
+        }
+        result
+    }
+
+    fn gen_binop(
+        &mut self,
+        op: BinaryOp,
+        ty: IrType,
+        left: Operand,
+        right: Operand,
+        span: Span,
+    ) -> Operand {
+        if !op.supports(ty) {
+            self.internal_error(format!("there is no opcode {}{}", op, ty), span);
+        }
+        let temp = self.new_temp();
+        self.emit(Instr::BinOp {
+            dest: temp.clone(),
+            op,
+            ty,
+            left,
+            right,
+            span,
+        });
+        temp
+    }
+
+    fn gen_load(&mut self, array: Operand, index: Operand, span: Span) -> Operand {
+        let temp = self.new_temp();
+        self.emit(Instr::Load {
+            dest: temp.clone(),
+            array,
+            index,
+            span,
+        });
+        temp
+    }
+
+    // The index every step takes, evaluated left to right. `changed_later` is
+    // whether what's evaluated after the steps can change a variable
+    fn gen_steps(&mut self, steps: Vec<Step>, changed_later: bool) -> Vec<Operand> {
+        let mut indices = Vec::new();
+        for (i, step) in steps.iter().enumerate() {
+            let index = match step {
+                Step::Index(index) => {
+                    let value = self.gen_expr(index);
+                    let later = steps[i + 1..].iter().any(|step| match step {
+                        Step::Index(index) => has_side_effects(index),
+                        Step::Field(_) => false,
+                    });
+                    self.hold(value, later || changed_later)
+                }
+                Step::Field(field) => offset(*field),
+            };
+            indices.push(index);
+        }
+        indices
+    }
+
+    // `a[i].x` read straight out of `a`: every index first, then the loads, all
+    // pointing at the whole expression the way the tree walker reads places
+    fn gen_place_read(&mut self, expr: &TypedExpr) -> Option<Operand> {
+        let (id, steps) = place(expr)?;
+        let indices = self.gen_steps(steps, false);
+        let mut value = self.var(id);
+        for index in indices {
+            value = self.gen_load(value, index, expr.span);
+        }
+        Some(value)
+    }
+
+    // a && b / a || b as a value, b is only evaluated when it decides the result:
+    //
+    //     t = a                    t = a
+    //     IfZ t Goto Lend          IfZ t Goto Lrhs
+    //     t = b                    Goto Lend
+    //   Lend:                    Lrhs:
+    //                              t = b
+    //                            Lend:
+    fn gen_logical(&mut self, left: &TypedExpr, operator: &Token, right: &TypedExpr) -> Operand {
+        let result = self.new_temp();
+        let end_label = self.new_label();
+
+        let l = self.gen_expr(left);
+        self.emit(Instr::Assign {
+            dest: result.clone(),
+            src: l,
+        });
+        if *operator == Token::T_AND_OPR {
+            self.emit(Instr::IfZ {
+                cond: result.clone(),
+                target: end_label,
+            });
+        } else {
+            let rhs_label = self.new_label();
+            self.emit(Instr::IfZ {
+                cond: result.clone(),
+                target: rhs_label,
+            });
+            self.emit(Instr::Goto(end_label));
+            self.emit(Instr::Label(rhs_label));
+        }
+        let r = self.gen_expr(right);
+        self.emit(Instr::Assign {
+            dest: result.clone(),
+            src: r,
+        });
+        self.emit(Instr::Label(end_label));
+        result
+    }
+
+    // Jumps to false_label when cond is false and falls through when it is true.
+    // && and || turn into jumps directly, no bool temp is built for them.
+    fn gen_cond(&mut self, cond: &TypedExpr, false_label: Label) {
+        if let TypedExprKind::Binary {
+            left,
+            operator,
+            right,
+        } = &cond.kind
+        {
+            match operator {
+                Token::T_AND_OPR => {
+                    self.gen_cond(left, false_label);
+                    self.gen_cond(right, false_label);
+                    return;
+                }
+                Token::T_OR_OPR => {
+                    let rhs_label = self.new_label();
+                    let true_label = self.new_label();
+                    self.gen_cond(left, rhs_label);
+                    self.emit(Instr::Goto(true_label));
+                    self.emit(Instr::Label(rhs_label));
+                    self.gen_cond(right, false_label);
+                    self.emit(Instr::Label(true_label));
+                    return;
+                }
+                _ => {}
+            }
         }
+
+        let value = self.gen_expr(cond);
+        self.emit(Instr::IfZ {
+            cond: value,
+            target: false_label,
+        });
     }
 
-    fn gen_if(&mut self, if_stmt: &IfStatement) {
+    fn gen_if(&mut self, if_stmt: &TypedIf) {
         let end_label = self.new_label();
 
         // Main If
         let next_label = self.new_label();
-        let cond = self.gen_expr(&if_stmt.condition);
-        self.emit(format!("IfZ {} Goto {}", cond, next_label));
+        self.gen_cond(&if_stmt.condition, next_label);
         self.gen_block(&if_stmt.block);
-        self.emit(format!("Goto {}", end_label));
-        self.emit(format!("{}:", next_label));
+        self.emit(Instr::Goto(end_label));
+        self.emit(Instr::Label(next_label));
 
         // Elifs
-        for elif in &if_stmt.elif_blocks {
+        for (condition, block) in &if_stmt.elif_blocks {
             let next_elif_label = self.new_label();
-            let cond = self.gen_expr(&elif.condition);
-            self.emit(format!("IfZ {} Goto {}", cond, next_elif_label));
-            self.gen_block(&elif.block);
-            self.emit(format!("Goto {}", end_label));
-            self.emit(format!("{}:", next_elif_label));
+            self.gen_cond(condition, next_elif_label);
+            self.gen_block(block);
+            self.emit(Instr::Goto(end_label));
+            self.emit(Instr::Label(next_elif_label));
         }
 
         // Else
@@ -183,85 +688,88 @@ impl IrGenerator {
             self.gen_block(else_block);
         }
 
-        self.emit(format!("{}:", end_label));
+        self.emit(Instr::Label(end_label));
     }
 
-    fn gen_while(&mut self, while_stmt: &WhileStatement) {
+    fn gen_while(&mut self, while_stmt: &TypedWhile) {
         let start_label = self.new_label();
         let end_label = self.new_label();
 
-        self.loop_stack
-            .push((start_label.clone(), end_label.clone()));
+        self.loop_stack.push((start_label, end_label));
 
-        self.emit(format!("{}:", start_label));
-        let cond = self.gen_expr(&while_stmt.condition);
-        self.emit(format!("IfZ {} Goto {}", cond, end_label));
+        self.emit(Instr::Label(start_label));
+        self.gen_cond(&while_stmt.condition, end_label);
 
         self.gen_block(&while_stmt.block);
-        self.emit(format!("Goto {}", start_label));
+        self.emit(Instr::Goto(start_label));
 
-        self.emit(format!("{}:", end_label));
+        self.emit(Instr::Label(end_label));
 
         self.loop_stack.pop();
     }
 
-    fn gen_for(&mut self, for_stmt: &ForStatement) {
+    fn gen_for(&mut self, for_stmt: &TypedFor) {
         let start_label = self.new_label();
         let continue_label = self.new_label();
         let end_label = self.new_label();
 
-        self.loop_stack
-            .push((continue_label.clone(), end_label.clone()));
+        self.loop_stack.push((continue_label, end_label));
 
         if let Some(init) = &for_stmt.init_var {
             self.gen_var_decl(init);
         }
 
-        self.emit(format!("{}:", start_label));
+        self.emit(Instr::Label(start_label));
 
         if let Some(cond_expr) = &for_stmt.condition {
-            let cond = self.gen_expr(cond_expr);
-            self.emit(format!("IfZ {} Goto {}", cond, end_label));
+            self.gen_cond(cond_expr, end_label);
         }
 
         self.gen_block(&for_stmt.block);
 
-        self.emit(format!("{}:", continue_label));
+        self.emit(Instr::Label(continue_label));
         if let Some(update) = &for_stmt.update {
-            self.gen_expr(update);
+            self.gen_discarded(update);
         }
-        self.emit(format!("Goto {}", start_label));
+        self.emit(Instr::Goto(start_label));
 
-        self.emit(format!("{}:", end_label));
+        self.emit(Instr::Label(end_label));
 
         self.loop_stack.pop();
     }
+}
 
-    fn token_to_op(&self, token: &Token) -> String {
-        match token {
-            Token::T_PLUS_OPR => "+".to_string(),
-            Token::T_MINUS_OPR => "-".to_string(),
-            Token::T_MULTIPLY_OPR => "*".to_string(),
-            Token::T_DIVIDE_OPR => "/".to_string(),
-            Token::T_EXPONENT_OPR => "^".to_string(),
-            Token::T_EQUALS_OPR => "==".to_string(),
-            Token::T_NOT_EQUALS_OPR => "!=".to_string(),
-            Token::T_LESS_THAN_OPR => "<".to_string(),
-            Token::T_GREATER_THAN_OPR => ">".to_string(),
-            Token::T_LESS_THAN_EQUAL_TO_OPR => "<=".to_string(),
-            Token::T_GREATER_THAN_EQUAL_TO_OPR => ">=".to_string(),
-            Token::T_AND_OPR => "&&".to_string(),
-            Token::T_OR_OPR => "||".to_string(),
-            Token::T_RIGHT_SHIFT_OPR => ">>".to_string(),
-            Token::T_LEFT_SHIFT_OPR => "<<".to_string(),
-            Token::T_ASSIGNMENT_OPR => "=".to_string(),
-            Token::T_NOT => "!".to_string(),
-            _ => format!("{:?}", token),
-        }
+// fields are loaded and stored like array elements, at their offset
+fn offset(field: usize) -> Operand {
+    Operand::Const(Constant::Int(field as i64))
+}
+
+// Whether evaluating expr can change a variable
+fn has_side_effects(expr: &TypedExpr) -> bool {
+    match &expr.kind {
+        TypedExprKind::Assignment { .. }
+        | TypedExprKind::CompoundAssignment { .. }
+        | TypedExprKind::Call {
+            callee: Callee::Function(_),
+            ..
+        } => true,
+        TypedExprKind::Literal(_) | TypedExprKind::Variable(_) | TypedExprKind::Invalid => false,
+        TypedExprKind::Binary { left, right, .. }
+        | TypedExprKind::Index {
+            array: left,
+            index: right,
+        } => has_side_effects(left) || has_side_effects(right),
+        TypedExprKind::Unary { operand: inner, .. }
+        | TypedExprKind::Field { object: inner, .. }
+        | TypedExprKind::IntToFloat(inner)
+        | TypedExprKind::FloatToInt(inner) => has_side_effects(inner),
+        TypedExprKind::Call { args, .. } => args.iter().any(has_side_effects),
+        TypedExprKind::ArrayLiteral(elements) => elements.iter().any(has_side_effects),
+        TypedExprKind::StructLiteral(fields) => fields.iter().any(|(_, v)| has_side_effects(v)),
     }
 }
 
-pub fn ir_generator(ast: &RootList) -> Result<String, ()> {
-    let mut ir_gen = IrGenerator::new();
-    ir_gen.generate_ir(ast)
+pub fn ir_generator(program: &TypedProgram) -> Result<Module, Vec<Diagnostic>> {
+    let mut ir_gen = IrGenerator::new(program);
+    ir_gen.generate_ir()
 }
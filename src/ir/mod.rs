@@ -1 +1,2 @@
 pub mod ir_generator;
+pub mod tac;
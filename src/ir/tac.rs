@@ -0,0 +1,457 @@
+use crate::lexer::span::Span;
+use crate::lexer::tokens::Token;
+use std::fmt;
+
+// Three address code. Every instruction does at most one operation and reads at most
+// two operands, the generator breaks nested expressions down into temporaries.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String), // already decoded by the lexer, Display escapes it again
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Temp(usize),    // t0, t1, ... compiler made
+    Var(String),    // a local, `x.3`: name and symbol id, so shadowed names stay apart
+    Global(String), // a global, by its source name, no two globals share one
+    Const(Constant),
+}
+
+// What an operation works on, so int and float arithmetic are separate opcodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrType {
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    And,
+    Or,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+impl Constant {
+    pub fn zero(ty: IrType) -> Constant {
+        match ty {
+            IrType::Int => Constant::Int(0),
+            IrType::Float => Constant::Float(0.0),
+            IrType::Bool => Constant::Bool(false),
+            IrType::Str => Constant::Str(String::new()),
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn from_token(token: &Token) -> Option<BinaryOp> {
+        let op = match token {
+            Token::T_PLUS_OPR => BinaryOp::Add,
+            Token::T_MINUS_OPR => BinaryOp::Sub,
+            Token::T_MULTIPLY_OPR => BinaryOp::Mul,
+            Token::T_DIVIDE_OPR => BinaryOp::Div,
+            Token::T_MODULO_OPR => BinaryOp::Mod,
+            Token::T_EXPONENT_OPR => BinaryOp::Pow,
+            Token::T_EQUALS_OPR => BinaryOp::Eq,
+            Token::T_NOT_EQUALS_OPR => BinaryOp::Ne,
+            Token::T_LESS_THAN_OPR => BinaryOp::Lt,
+            Token::T_GREATER_THAN_OPR => BinaryOp::Gt,
+            Token::T_LESS_THAN_EQUAL_TO_OPR => BinaryOp::Le,
+            Token::T_GREATER_THAN_EQUAL_TO_OPR => BinaryOp::Ge,
+            Token::T_AND_OPR => BinaryOp::And,
+            Token::T_OR_OPR => BinaryOp::Or,
+            Token::T_RIGHT_SHIFT_OPR => BinaryOp::Shr,
+            Token::T_LEFT_SHIFT_OPR => BinaryOp::Shl,
+            Token::T_BIT_AND_OPR => BinaryOp::BitAnd,
+            Token::T_BIT_OR_OPR => BinaryOp::BitOr,
+            Token::T_BIT_XOR_OPR => BinaryOp::BitXor,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    // Whether the opcode is defined on two operands of type `ty`, the generator
+    // never builds one that isn't
+    pub fn supports(self, ty: IrType) -> bool {
+        match ty {
+            IrType::Int => !matches!(self, BinaryOp::And | BinaryOp::Or),
+            IrType::Float => matches!(
+                self,
+                BinaryOp::Add
+                    | BinaryOp::Sub
+                    | BinaryOp::Mul
+                    | BinaryOp::Div
+                    | BinaryOp::Pow
+                    | BinaryOp::Eq
+                    | BinaryOp::Ne
+                    | BinaryOp::Lt
+                    | BinaryOp::Gt
+                    | BinaryOp::Le
+                    | BinaryOp::Ge
+            ),
+            IrType::Str => matches!(
+                self,
+                BinaryOp::Add
+                    | BinaryOp::Eq
+                    | BinaryOp::Ne
+                    | BinaryOp::Lt
+                    | BinaryOp::Gt
+                    | BinaryOp::Le
+                    | BinaryOp::Ge
+            ),
+            IrType::Bool => matches!(
+                self,
+                BinaryOp::Eq | BinaryOp::Ne | BinaryOp::And | BinaryOp::Or
+            ),
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn from_token(token: &Token) -> Option<UnaryOp> {
+        match token {
+            Token::T_MINUS_OPR => Some(UnaryOp::Neg),
+            Token::T_NOT => Some(UnaryOp::Not),
+            Token::T_BIT_NOT => Some(UnaryOp::BitNot),
+            _ => None,
+        }
+    }
+
+    pub fn supports(self, ty: IrType) -> bool {
+        matches!(
+            (self, ty),
+            (UnaryOp::Neg, IrType::Int | IrType::Float)
+                | (UnaryOp::Not, IrType::Bool)
+                | (UnaryOp::BitNot, IrType::Int)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(pub usize);
+
+// `dest` is always a Temp, a Var or a Global, never a Const. Instructions that can
+// fail at run time carry the span of the expression they were made for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Assign {
+        dest: Operand,
+        src: Operand,
+    },
+    BinOp {
+        dest: Operand,
+        op: BinaryOp,
+        ty: IrType, // of both operands, the generator converts them first
+        left: Operand,
+        right: Operand,
+        span: Span,
+    },
+    UnOp {
+        dest: Operand,
+        op: UnaryOp,
+        ty: IrType,
+        operand: Operand,
+        span: Span,
+    },
+    IntToFloat {
+        dest: Operand,
+        src: Operand,
+    },
+    FloatToInt {
+        dest: Operand,
+        src: Operand,
+    }, // truncates toward zero
+    NewArray {
+        dest: Operand,
+        len: usize,
+        fill: Operand, // every element starts out as a copy of this
+        span: Span,
+    },
+    NewStruct {
+        dest: Operand,
+        name: String,
+        fields: Vec<String>, // every field is stored right after, see IrGenerator::gen_zero
+    },
+    // a struct's field is an element too, at its offset in the declaration
+    Load {
+        dest: Operand,
+        array: Operand,
+        index: Operand,
+        span: Span,
+    }, // dest = array[index], bounds checked
+    Store {
+        array: Operand, // a Temp, Var or Global like `dest` elsewhere
+        index: Operand,
+        src: Operand,
+        span: Span,
+    }, // array[index] = src, bounds checked
+    IfZ {
+        cond: Operand,
+        target: Label,
+    }, // jump when cond is false
+    Goto(Label),
+    Param(Operand), // pushes one argument for the next Call
+    Call {
+        dest: Operand,
+        func: String,
+        argc: usize,
+        span: Span,
+    },
+    Return(Option<Operand>), // None in void functions
+    Label(Label),
+}
+
+impl Instr {
+    // Instructions after which control does not simply fall to the next one
+    pub fn ends_block(&self) -> bool {
+        matches!(self, Instr::IfZ { .. } | Instr::Goto(_) | Instr::Return(_))
+    }
+}
+
+// Straight line code: only the first instruction can be jumped to (a Label, unless
+// it is the entry block) and only the last one can jump away
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BasicBlock {
+    pub instrs: Vec<Instr>,
+}
+
+impl BasicBlock {
+    pub fn label(&self) -> Option<Label> {
+        match self.instrs.first() {
+            Some(Instr::Label(label)) => Some(*label),
+            _ => None,
+        }
+    }
+
+    // Cuts a flat instruction list before every label and after every jump
+    pub fn split(instrs: Vec<Instr>) -> Vec<BasicBlock> {
+        let mut blocks = Vec::new();
+        let mut current = BasicBlock::default();
+
+        for instr in instrs {
+            if matches!(instr, Instr::Label(_)) && !current.instrs.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+            let ends_block = instr.ends_block();
+            current.instrs.push(instr);
+            if ends_block {
+                blocks.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.instrs.is_empty() {
+            blocks.push(current);
+        }
+        blocks
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>, // popped in this order on entry
+    pub blocks: Vec<BasicBlock>,
+    pub span: Span, // the function's name in source
+}
+
+impl Function {
+    pub fn new(name: String, params: Vec<String>, instrs: Vec<Instr>, span: Span) -> Self {
+        Function {
+            name,
+            params,
+            blocks: BasicBlock::split(instrs),
+            span,
+        }
+    }
+
+    pub fn instrs(&self) -> impl Iterator<Item = &Instr> {
+        self.blocks.iter().flat_map(|block| block.instrs.iter())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Module {
+    pub globals: Vec<Instr>, // global initializers, run once before anything else
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|func| func.name == name)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(i) => write!(f, "{}", i),
+            Constant::Float(x) => write!(f, "{}", x),
+            Constant::Bool(b) => write!(f, "{}", b),
+            // escaped the way the lexer reads them back
+            Constant::Str(s) => write!(f, "\"{}\"", s.escape_debug()),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Temp(n) => write!(f, "t{}", n),
+            Operand::Var(name) | Operand::Global(name) => write!(f, "{}", name),
+            Operand::Const(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+// Suffix on the operators, `t0 = a +i b` is int addition and `t0 = a +f b` float
+impl fmt::Display for IrType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IrType::Int => write!(f, "i"),
+            IrType::Float => write!(f, "f"),
+            IrType::Bool => write!(f, "b"),
+            IrType::Str => write!(f, "s"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "**",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Le => "<=",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::BitNot => write!(f, "~"),
+        }
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L{}", self.0)
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Assign { dest, src } => write!(f, "{} = {}", dest, src),
+            Instr::BinOp {
+                dest,
+                op,
+                ty,
+                left,
+                right,
+                ..
+            } => write!(f, "{} = {} {}{} {}", dest, left, op, ty, right),
+            Instr::UnOp {
+                dest,
+                op,
+                ty,
+                operand,
+                ..
+            } => write!(f, "{} = {}{} {}", dest, op, ty, operand),
+            Instr::IntToFloat { dest, src } => write!(f, "{} = IntToFloat {}", dest, src),
+            Instr::FloatToInt { dest, src } => write!(f, "{} = FloatToInt {}", dest, src),
+            Instr::NewArray {
+                dest, len, fill, ..
+            } => {
+                write!(f, "{} = NewArray {}, {}", dest, len, fill)
+            }
+            Instr::NewStruct { dest, name, fields } => {
+                write!(f, "{} = NewStruct {} {{{}}}", dest, name, fields.join(", "))
+            }
+            Instr::Load {
+                dest, array, index, ..
+            } => write!(f, "{} = {}[{}]", dest, array, index),
+            Instr::Store {
+                array, index, src, ..
+            } => write!(f, "{}[{}] = {}", array, index, src),
+            Instr::IfZ { cond, target } => write!(f, "IfZ {} Goto {}", cond, target),
+            Instr::Goto(target) => write!(f, "Goto {}", target),
+            Instr::Param(operand) => write!(f, "Param {}", operand),
+            Instr::Call {
+                dest, func, argc, ..
+            } => write!(f, "{} = Call {}, {}", dest, func, argc),
+            Instr::Return(Some(operand)) => write!(f, "Return {}", operand),
+            Instr::Return(None) => write!(f, "Return"),
+            Instr::Label(label) => write!(f, "{}:", label),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        writeln!(f, "BeginFunc")?;
+        for param in &self.params {
+            writeln!(f, "PopParam {}", param)?;
+        }
+        for instr in self.instrs() {
+            writeln!(f, "{}", instr)?;
+        }
+        write!(f, "EndFunc")
+    }
+}
+
+// One instruction per line, globals first then every function in source order
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<String> = self.globals.iter().map(|i| i.to_string()).collect();
+        lines.extend(self.functions.iter().map(|func| func.to_string()));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
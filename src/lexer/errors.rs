@@ -0,0 +1,119 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexError {
+    UnclosedString(Span),         // span of the opening quote
+    UnclosedComment(Span, usize), // the outermost `/*`, and how many were still open
+    InvalidIdentifier(String, Span),
+    UnexpectedCharacter(char, Span),
+    IntegerTooLarge(String, Span),
+    FloatTooLarge(String, Span),
+    InvalidNumber(String, Span), // a digit the base doesn't have, or a broken float
+    UnknownEscape(char, Span),   // the letter after the backslash
+    InvalidEscape(String, Span), // a \x or \u escape as far as it got
+}
+
+impl LexError {
+    fn code(&self) -> &'static str {
+        match self {
+            LexError::UnclosedString(_) => codes::UNCLOSED_STRING,
+            LexError::UnclosedComment(..) => codes::UNCLOSED_COMMENT,
+            LexError::InvalidIdentifier(..) => codes::INVALID_IDENTIFIER,
+            LexError::UnexpectedCharacter(..) => codes::UNEXPECTED_CHARACTER,
+            LexError::IntegerTooLarge(..) => codes::INTEGER_TOO_LARGE,
+            LexError::FloatTooLarge(..) => codes::FLOAT_TOO_LARGE,
+            LexError::InvalidNumber(..) => codes::INVALID_NUMBER,
+            LexError::UnknownEscape(..) | LexError::InvalidEscape(..) => codes::INVALID_ESCAPE,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnclosedString(span)
+            | LexError::UnclosedComment(span, _)
+            | LexError::InvalidIdentifier(_, span)
+            | LexError::UnexpectedCharacter(_, span)
+            | LexError::IntegerTooLarge(_, span)
+            | LexError::FloatTooLarge(_, span)
+            | LexError::InvalidNumber(_, span)
+            | LexError::UnknownEscape(_, span)
+            | LexError::InvalidEscape(_, span) => *span,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), self.to_string(), self.span());
+        match self {
+            LexError::UnclosedString(_) => {
+                diagnostic.with_label("string starts here and never ends")
+            }
+            LexError::UnclosedComment(_, open) => {
+                let diagnostic = diagnostic.with_label("comment starts here and never ends");
+                if *open > 1 {
+                    diagnostic.with_note(format!(
+                        "block comments nest, {} of them are still open",
+                        open
+                    ))
+                } else {
+                    diagnostic
+                }
+            }
+            LexError::IntegerTooLarge(..) => {
+                diagnostic.with_note(format!("the largest int is {}", i64::MAX))
+            }
+            LexError::FloatTooLarge(..) => {
+                diagnostic.with_note(format!("the largest float is about {:e}", f64::MAX))
+            }
+            LexError::InvalidNumber(text, _) => {
+                let note = match text.get(..2) {
+                    Some("0x") => "hex literals are written with 0-9 and a-f, like 0xFF",
+                    Some("0b") => "binary literals are written with 0 and 1, like 0b1010",
+                    Some("0o") => "octal literals are written with 0-7, like 0o17",
+                    _ => "floats are written like 1.5, 2e10 or 1.5e-3",
+                };
+                diagnostic.with_note(note)
+            }
+            LexError::UnknownEscape(..) => diagnostic
+                .with_label("unknown escape")
+                .with_note(r#"the escapes are \n \t \r \0 \\ \" \' \x41 and \u{1F600}"#),
+            LexError::InvalidEscape(escape, _) if escape.starts_with("\\x") => diagnostic
+                .with_label("expected two hex digits, at most 7F")
+                .with_note(r"\x only goes up to \x7F, larger characters are written \u{...}"),
+            LexError::InvalidEscape(..) => diagnostic
+                .with_label("not a valid character code")
+                .with_note(
+                    r"\u{...} takes 1 to 6 hex digits that name a character, like \u{1F600}",
+                ),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnclosedString(_) => write!(f, "Unclosed string literal"),
+            LexError::UnclosedComment(..) => write!(f, "Unclosed block comment"),
+            LexError::InvalidIdentifier(name, _) => {
+                write!(f, "Identifiers should not start with numbers: {}", name)
+            }
+            LexError::UnexpectedCharacter(c, _) => write!(f, "unexpected character '{}'", c),
+            LexError::IntegerTooLarge(digits, _) => {
+                write!(f, "integer literal is too large: {}", digits)
+            }
+            LexError::FloatTooLarge(text, _) => {
+                write!(f, "float literal is out of range: {}", text)
+            }
+            LexError::InvalidNumber(text, _) => write!(f, "invalid number literal: {}", text),
+            LexError::UnknownEscape(c, _) => {
+                write!(f, "unknown escape sequence '\\{}'", c.escape_debug())
+            }
+            LexError::InvalidEscape(escape, _) => {
+                write!(f, "invalid escape sequence '{}'", escape)
+            }
+        }
+    }
+}
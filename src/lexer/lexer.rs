@@ -1,199 +1,462 @@
+use super::errors::LexError;
+use super::span::{LineMap, Span};
+use super::tokens::SpannedToken;
 use super::tokens::Token::{self, *};
 
-fn find_delim(c: char) -> bool {
-    return c.is_whitespace()
-        || [
-            '(', ')', '[', ']', '{', '}', ',', //'.',
-            ';', '"', '=', '!', '<', '>', '&', '|', '+', '-', '*', '/', '^', '#',
-        ]
-        .contains(&c);
+// characters that can make up a word: identifiers, keywords and number literals.
+// Numbers also take '.' and an exponent's sign, see lex_number
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
 }
 
-pub(crate) fn lex(code: String) -> Vec<Token> {
-    let mut tokens: Vec<Token> = Vec::new();
-    let code = code.trim().to_string();
+struct Lexer<'a> {
+    code: &'a str,
+    curr: usize, // byte offset of the next unread char
+    line_map: LineMap,
+    tokens: Vec<SpannedToken>,
+    errors: Vec<LexError>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(code: &'a str) -> Self {
+        Lexer {
+            code,
+            curr: 0,
+            line_map: LineMap::new(code),
+            tokens: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.code[self.curr..].chars().next()
+    }
 
-    let mut curr = 0;
-    let mut string_lit = false;
+    fn rest(&self) -> &'a str {
+        &self.code[self.curr..]
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        self.line_map.span(self.code, start, end)
+    }
 
-    while curr < code.len() {
-        //println!("{}", &code[curr..]);
+    fn push(&mut self, token: Token, start: usize) {
+        let span = self.span(start, self.curr);
+        self.tokens.push(SpannedToken { token, span });
+    }
 
-        // current ka baad spaced khatam karo
-        if curr < code.len() {
-            while code.chars().nth(curr).unwrap().is_whitespace() {
-                curr += 1;
+    fn run(&mut self) {
+        while let Some(c) = self.peek() {
+            // current ka baad spaced khatam karo
+            if c.is_whitespace() {
+                self.curr += c.len_utf8();
+                continue;
             }
-        }
 
-        let mut idx = if string_lit {
-            let mut _idx = curr;
-            loop {
-                if let Some(pos) = code[_idx..].find('"') {
-                    _idx += pos;
-                    let mut backslash_count = 0;
-                    let mut check_pos = _idx;
-                    while check_pos > curr && code.chars().nth(check_pos - 1).unwrap() == '\\' {
-                        backslash_count += 1;
-                        check_pos -= 1;
-                    }
-                    // not escaped when back slash count is even
-                    if backslash_count % 2 == 0 {
-                        break;
-                    }
-                    _idx += 1;
-                } else {
-                    panic!("Unclosed string literal");
-                }
+            if c == '#' {
+                self.lex_line_comment();
+                continue;
+            }
+
+            if self.rest().starts_with("/*") {
+                self.skip_block_comment();
+                continue;
             }
-            _idx
-        } else {
-            let _idx = code[curr..].find(find_delim).unwrap() + curr;
 
-            if _idx == curr {
-                _idx + 1 // abhi tak delimeters 1 length k hain uper
+            if c == '"' {
+                self.lex_string();
+            } else if c.is_ascii_digit() {
+                self.lex_number();
+            } else if is_word_char(c) {
+                self.lex_word();
             } else {
-                _idx
+                self.lex_symbol(c);
             }
+        }
+    }
+
+    // `# ...` is skipped till end of line, `## ...` is a doc comment and kept as a
+    // token. `###` and longer are ordinary comments, for banners like `#########`
+    fn lex_line_comment(&mut self) {
+        let start = self.curr;
+        let line = match self.rest().find('\n') {
+            Some(pos) => &self.rest()[..pos],
+            None => self.rest(),
         };
+        self.curr += line.len();
 
-        let substr = code[curr..idx].to_string();
-        //println!("Abhi wala: {substr}\n");
+        if let Some(text) = line.strip_prefix("##")
+            && !text.starts_with('#')
+        {
+            let text = text.strip_prefix(' ').unwrap_or(text).trim_end();
+            self.push(T_DOC_COMMENT(text.to_string()), start);
+        }
+    }
 
-        if &code[curr..curr + 1] == "#" {
-            if let Some(pos) = code[curr..].find('\n') {
-                curr += pos + 1;
-                continue;
+    // /* ... */, nested ones have to be closed too: /* a /* b */ c */
+    fn skip_block_comment(&mut self) {
+        let start = self.curr;
+        let mut depth = 0;
+        while !self.rest().is_empty() {
+            if self.rest().starts_with("/*") {
+                depth += 1;
+                self.curr += 2;
+            } else if self.rest().starts_with("*/") {
+                depth -= 1;
+                self.curr += 2;
+                if depth == 0 {
+                    return;
+                }
             } else {
-                break;
+                self.curr += self.peek().map_or(1, char::len_utf8);
             }
         }
 
-        if substr == "fn" {
-            tokens.push(T_FUNCTION);
-        } else if substr == "if" {
-            tokens.push(T_IF);
-        } else if substr == "else" {
-            tokens.push(T_ELSE);
-        } else if substr == "elif" {
-            tokens.push(T_ELSE_IF);
-        } else if substr == "while" {
-            tokens.push(T_WHILE);
-        } else if substr == "for" {
-            tokens.push(T_FOR);
-        } else if substr == "return" {
-            tokens.push(T_RETURN);
-        } else if substr == "break" {
-            tokens.push(T_BREAK);
-        } else if substr == "continue" {
-            tokens.push(T_CONTINUE);
-        } else if substr == "int" {
-            tokens.push(T_INT);
-        } else if substr == "float" {
-            tokens.push(T_FLOAT);
-        } else if substr == "bool" {
-            tokens.push(T_BOOL);
-        } else if substr == "string" {
-            tokens.push(T_STRING);
-        } else if substr == "\"" {
-            tokens.push(T_DOUBLE_QUOTE);
-            string_lit = !string_lit;
-        } else if substr == "(" {
-            tokens.push(T_ROUND_BRACKET_OPEN);
-        } else if substr == ")" {
-            tokens.push(T_ROUND_BRACKET_CLOSE);
-        } else if substr == "[" {
-            tokens.push(T_SQUARE_BRACKET_OPEN);
-        } else if substr == "]" {
-            tokens.push(T_SQUARE_BRACKET_CLOSE);
-        } else if substr == "{" {
-            tokens.push(T_CURLY_BRACKET_OPEN);
-        } else if substr == "}" {
-            tokens.push(T_CURLY_BRACKET_CLOSE);
-        } else if substr == "," {
-            tokens.push(T_COMMA);
-        } else if substr == ";" {
-            tokens.push(T_SEMICOLON);
-        } else if substr == "=" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == "==" {
-                tokens.push(T_EQUALS_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_ASSIGNMENT_OPR);
-            }
-        } else if substr == "!" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == "!=" {
-                tokens.push(T_NOT_EQUALS_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_NOT);
+        let span = self.span(start, start + 2);
+        self.errors.push(LexError::UnclosedComment(span, depth));
+    }
+
+    // "..." -> one T_STRINGLIT holding the decoded text, its span covers the quotes
+    fn lex_string(&mut self) {
+        let start = self.curr;
+        self.curr += 1; // opening quote
+
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.curr += 1;
+                    self.push(T_STRINGLIT(value), start);
+                    return;
+                }
+                '\\' => self.lex_escape(&mut value),
+                _ => {
+                    value.push(c);
+                    self.curr += c.len_utf8();
+                }
             }
-        } else if substr == "<" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == "<=" {
-                tokens.push(T_LESS_THAN_EQUAL_TO_OPR);
-                idx = curr + 2;
-            } else if curr + 1 < code.len() && &code[curr..curr + 2] == "<<" {
-                tokens.push(T_LEFT_SHIFT_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_LESS_THAN_OPR);
+        }
+
+        // the rest of the file is the literal, nothing left to lex
+        let quote_span = self.span(start, start + 1);
+        self.errors.push(LexError::UnclosedString(quote_span));
+    }
+
+    // A backslash and what follows it, decoded onto `value`. A bad escape is reported
+    // and left out, the rest of the string is still lexed
+    fn lex_escape(&mut self, value: &mut String) {
+        let start = self.curr;
+        self.curr += 1; // the backslash
+        let Some(c) = self.peek() else {
+            return; // file ends inside the string, lex_string reports it
+        };
+        self.curr += c.len_utf8();
+
+        let decoded = match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'x' => self.lex_hex_escape(),
+            'u' => self.lex_unicode_escape(),
+            _ => {
+                let span = self.span(start, self.curr);
+                self.errors.push(LexError::UnknownEscape(c, span));
+                return;
             }
-        } else if substr == ">" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == ">=" {
-                tokens.push(T_GREATER_THAN_EQUAL_TO_OPR);
-                idx = curr + 2;
-            } else if curr + 1 < code.len() && &code[curr..curr + 2] == ">>" {
-                tokens.push(T_RIGHT_SHIFT_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_GREATER_THAN_OPR);
+        };
+
+        match decoded {
+            Some(c) => value.push(c),
+            None => {
+                let span = self.span(start, self.curr);
+                let escape = self.code[start..self.curr].to_string();
+                self.errors.push(LexError::InvalidEscape(escape, span));
             }
-        } else if substr == "&" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == "&&" {
-                tokens.push(T_AND_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_AND_OPR); // single & treated as and too
+        }
+    }
+
+    // \x41: exactly two hex digits and ASCII only, like Rust
+    fn lex_hex_escape(&mut self) -> Option<char> {
+        let len = self
+            .rest()
+            .chars()
+            .take(2)
+            .take_while(|c| c.is_ascii_hexdigit())
+            .count();
+        let digits = &self.rest()[..len];
+        self.curr += len;
+        if len != 2 {
+            return None;
+        }
+        u8::from_str_radix(digits, 16)
+            .ok()
+            .filter(u8::is_ascii)
+            .map(char::from)
+    }
+
+    // \u{1F600}: 1 to 6 hex digits in braces that name a character
+    fn lex_unicode_escape(&mut self) -> Option<char> {
+        if !self.rest().starts_with('{') {
+            return None;
+        }
+        self.curr += 1;
+        let len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(self.rest().len());
+        let digits = &self.rest()[..len];
+        self.curr += len;
+        if !self.rest().starts_with('}') {
+            return None;
+        }
+        self.curr += 1;
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    // 42, 1_000, 0xFF, 0b1010, 0o17, 1.5, 2e10, 1.5e-3. Ints go up to 2^63 so that
+    // -9223372036854775808 can be written, the parser only allows that one after a '-'
+    fn lex_number(&mut self) {
+        let start = self.curr;
+        let radix = match self.rest().get(..2) {
+            Some("0x") => Some(16),
+            Some("0b") => Some(2),
+            Some("0o") => Some(8),
+            _ => None,
+        };
+
+        // the whole literal: word chars and '.', and the sign of a decimal exponent
+        let mut prev = ' ';
+        let len = self
+            .rest()
+            .find(|c: char| {
+                let sign = radix.is_none() && matches!(c, '+' | '-') && matches!(prev, 'e' | 'E');
+                prev = c;
+                !(is_word_char(c) || c == '.' || sign)
+            })
+            .unwrap_or(self.rest().len());
+        self.curr += len;
+        let text = &self.code[start..self.curr];
+        let span = self.span(start, self.curr);
+        let digits: String = text.chars().filter(|&c| c != '_').collect();
+
+        let token = match radix {
+            Some(radix) => {
+                let body = &digits[2..];
+                if body.is_empty() || !body.chars().all(|c| c.is_digit(radix)) {
+                    self.errors
+                        .push(LexError::InvalidNumber(text.to_string(), span));
+                    return;
+                }
+                self.int_token(u64::from_str_radix(body, radix).ok(), text, span)
             }
-        } else if substr == "|" {
-            if curr + 1 < code.len() && &code[curr..curr + 2] == "||" {
-                tokens.push(T_OR_OPR);
-                idx = curr + 2;
-            } else {
-                tokens.push(T_OR_OPR); // single | treated as OR too
+            None if digits.chars().all(|c| c.is_ascii_digit()) => {
+                self.int_token(digits.parse().ok(), text, span)
             }
-        } else if substr == "+" {
-            tokens.push(T_PLUS_OPR);
-        } else if substr == "-" {
-            tokens.push(T_MINUS_OPR);
-        } else if substr == "*" {
-            tokens.push(T_MULTIPLY_OPR);
-        } else if substr == "/" {
-            tokens.push(T_DIVIDE_OPR);
-        } else if substr == "^" {
-            tokens.push(T_EXPONENT_OPR);
-        } else if string_lit {
-            tokens.push(T_STRINGLIT(substr.clone()));
-            string_lit = false;
-            tokens.push(T_DOUBLE_QUOTE);
-            idx += 1; // move past closing quote
-        } else if substr.chars().all(|c| c.is_digit(10)) {
-            let val: i64 = substr.parse().unwrap();
-            tokens.push(T_CONST_INT(val));
-        } else if substr.parse::<f64>().is_ok() {
-            let val: f64 = substr.parse().unwrap();
-            tokens.push(T_CONST_FLOAT(val));
-        } else if substr == "true" || substr == "false" {
-            tokens.push(T_CONST_BOOL(substr == "true"));
-        } else {
             // not the job of lexer but requirment ma ikha ha...
-            if substr.chars().next().unwrap().is_digit(10) {
-                panic!("Identifiers should not start with numbers: {}", substr);
+            None if digits
+                .chars()
+                .any(|c| c.is_alphabetic() && c != 'e' && c != 'E') =>
+            {
+                self.errors
+                    .push(LexError::InvalidIdentifier(text.to_string(), span));
+                return;
+            }
+            None => match digits.parse::<f64>() {
+                Ok(val) if val.is_finite() => Some(T_CONST_FLOAT(val)),
+                Ok(_) => {
+                    self.errors
+                        .push(LexError::FloatTooLarge(text.to_string(), span));
+                    return;
+                }
+                Err(_) => {
+                    self.errors
+                        .push(LexError::InvalidNumber(text.to_string(), span));
+                    return;
+                }
+            },
+        };
+
+        if let Some(token) = token {
+            self.tokens.push(SpannedToken { token, span });
+        }
+    }
+
+    // None (and an error) when the value is out of range, or didn't fit a u64 at all
+    fn int_token(&mut self, value: Option<u64>, text: &str, span: Span) -> Option<Token> {
+        match value {
+            Some(val) if val <= i64::MIN.unsigned_abs() => Some(T_CONST_INT(val)),
+            _ => {
+                self.errors
+                    .push(LexError::IntegerTooLarge(text.to_string(), span));
+                None
             }
-            tokens.push(T_IDENTIFIER(substr.clone()));
         }
-        curr = idx;
     }
 
-    tokens
+    fn lex_word(&mut self) {
+        let start = self.curr;
+        let len = self
+            .rest()
+            .find(|c| !is_word_char(c))
+            .unwrap_or(self.rest().len());
+        self.curr += len;
+        let word = &self.code[start..self.curr];
+        let span = self.span(start, self.curr);
+
+        let token = if word == "fn" {
+            T_FUNCTION
+        } else if word == "if" {
+            T_IF
+        } else if word == "else" {
+            T_ELSE
+        } else if word == "elif" {
+            T_ELSE_IF
+        } else if word == "while" {
+            T_WHILE
+        } else if word == "for" {
+            T_FOR
+        } else if word == "return" {
+            T_RETURN
+        } else if word == "break" {
+            T_BREAK
+        } else if word == "continue" {
+            T_CONTINUE
+        } else if word == "int" {
+            T_INT
+        } else if word == "float" {
+            T_FLOAT
+        } else if word == "bool" {
+            T_BOOL
+        } else if word == "string" {
+            T_STRING
+        } else if word == "void" {
+            T_VOID
+        } else if word == "struct" {
+            T_STRUCT
+        } else if word == "true" || word == "false" {
+            T_CONST_BOOL(word == "true")
+        } else {
+            T_IDENTIFIER(word.to_string())
+        };
+
+        self.tokens.push(SpannedToken { token, span });
+    }
+
+    fn lex_symbol(&mut self, c: char) {
+        let start = self.curr;
+        let rest = self.rest();
+
+        // longest operators first, `<<=` before `<<` before `<`
+        let triple = if rest.starts_with("<<=") {
+            Some(T_LEFT_SHIFT_ASSIGN_OPR)
+        } else if rest.starts_with(">>=") {
+            Some(T_RIGHT_SHIFT_ASSIGN_OPR)
+        } else {
+            None
+        };
+
+        if let Some(token) = triple {
+            self.curr += 3;
+            self.push(token, start);
+            return;
+        }
+
+        let double = if rest.starts_with("==") {
+            Some(T_EQUALS_OPR)
+        } else if rest.starts_with("!=") {
+            Some(T_NOT_EQUALS_OPR)
+        } else if rest.starts_with("<=") {
+            Some(T_LESS_THAN_EQUAL_TO_OPR)
+        } else if rest.starts_with("<<") {
+            Some(T_LEFT_SHIFT_OPR)
+        } else if rest.starts_with(">=") {
+            Some(T_GREATER_THAN_EQUAL_TO_OPR)
+        } else if rest.starts_with(">>") {
+            Some(T_RIGHT_SHIFT_OPR)
+        } else if rest.starts_with("&&") {
+            Some(T_AND_OPR)
+        } else if rest.starts_with("||") {
+            Some(T_OR_OPR)
+        } else if rest.starts_with("**") {
+            Some(T_EXPONENT_OPR)
+        } else if rest.starts_with("++") {
+            Some(T_INCREMENT_OPR)
+        } else if rest.starts_with("--") {
+            Some(T_DECREMENT_OPR)
+        } else if rest.starts_with("+=") {
+            Some(T_PLUS_ASSIGN_OPR)
+        } else if rest.starts_with("-=") {
+            Some(T_MINUS_ASSIGN_OPR)
+        } else if rest.starts_with("*=") {
+            Some(T_MULTIPLY_ASSIGN_OPR)
+        } else if rest.starts_with("/=") {
+            Some(T_DIVIDE_ASSIGN_OPR)
+        } else if rest.starts_with("%=") {
+            Some(T_MODULO_ASSIGN_OPR)
+        } else {
+            None
+        };
+
+        if let Some(token) = double {
+            self.curr += 2;
+            self.push(token, start);
+            return;
+        }
+
+        let token = match c {
+            '(' => T_ROUND_BRACKET_OPEN,
+            ')' => T_ROUND_BRACKET_CLOSE,
+            '[' => T_SQUARE_BRACKET_OPEN,
+            ']' => T_SQUARE_BRACKET_CLOSE,
+            '{' => T_CURLY_BRACKET_OPEN,
+            '}' => T_CURLY_BRACKET_CLOSE,
+            ',' => T_COMMA,
+            '.' => T_DOT,
+            ':' => T_COLON,
+            ';' => T_SEMICOLON,
+            '=' => T_ASSIGNMENT_OPR,
+            '!' => T_NOT,
+            '<' => T_LESS_THAN_OPR,
+            '>' => T_GREATER_THAN_OPR,
+            '&' => T_BIT_AND_OPR,
+            '|' => T_BIT_OR_OPR,
+            '^' => T_BIT_XOR_OPR,
+            '~' => T_BIT_NOT,
+            '+' => T_PLUS_OPR,
+            '-' => T_MINUS_OPR,
+            '*' => T_MULTIPLY_OPR,
+            '/' => T_DIVIDE_OPR,
+            '%' => T_MODULO_OPR,
+            _ => {
+                // report and skip the character, keep lexing the rest
+                self.curr += c.len_utf8();
+                let span = self.span(start, self.curr);
+                self.errors.push(LexError::UnexpectedCharacter(c, span));
+                return;
+            }
+        };
+
+        self.curr += 1;
+        self.push(token, start);
+    }
+}
+
+pub fn lex(code: String) -> Result<Vec<SpannedToken>, Vec<LexError>> {
+    let mut lexer = Lexer::new(&code);
+    lexer.run();
+
+    if lexer.errors.is_empty() {
+        Ok(lexer.tokens)
+    } else {
+        Err(lexer.errors)
+    }
 }
@@ -1,2 +1,5 @@
+pub mod errors;
+#[allow(clippy::module_inception)]
 pub mod lexer;
+pub mod span;
 pub mod tokens;
@@ -0,0 +1,83 @@
+use std::fmt;
+
+// Location of a piece of source code: byte range plus 1-based line/column of the start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize, // byte offset, inclusive
+    pub end: usize,   // byte offset, exclusive
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    // Span covering everything from the start of self to the end of other
+    pub fn to(&self, other: Span) -> Span {
+        if other.end < self.end {
+            return *self;
+        }
+        Span {
+            start: self.start,
+            end: other.end,
+            line: self.line,
+            col: self.col,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+// Byte offsets where every line starts, so offsets can be turned into line/column cheaply
+#[derive(Debug)]
+pub struct LineMap {
+    line_starts: Vec<usize>,
+}
+
+impl LineMap {
+    pub fn new(code: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, c) in code.char_indices() {
+            if c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        LineMap { line_starts }
+    }
+
+    // 1-based (line, column), column counted in chars
+    pub fn locate(&self, code: &str, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let col = code[self.line_starts[line]..offset].chars().count();
+        (line + 1, col + 1)
+    }
+
+    // Text of a 1-based line without its trailing newline
+    pub fn line_text<'a>(&self, code: &'a str, line: usize) -> &'a str {
+        let start = self.line_starts[line - 1];
+        let end = match self.line_starts.get(line) {
+            Some(next) => *next,
+            None => code.len(),
+        };
+        code[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    pub fn span(&self, code: &str, start: usize, end: usize) -> Span {
+        let (line, col) = self.locate(code, start);
+        Span::new(start, end, line, col)
+    }
+}
@@ -1,3 +1,7 @@
+use super::span::Span;
+use Token::*;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Token {
@@ -10,9 +14,11 @@ pub enum Token {
     T_RETURN,   // return
     T_BREAK,    // break
     T_CONTINUE, // continue
+    T_STRUCT,   // struct
     T_IDENTIFIER(String),
-    T_STRINGLIT(String),
-    T_CONST_INT(i64),
+    T_STRINGLIT(String),   // escapes already decoded
+    T_DOC_COMMENT(String), // one `## ...` line, without the `##`
+    T_CONST_INT(u64),      // up to 2^63, only i64::MIN needs the last one
     T_CONST_FLOAT(f64),
     T_CONST_BOOL(bool),
 
@@ -23,11 +29,21 @@ pub enum Token {
     T_CURLY_BRACKET_OPEN,   // {
     T_CURLY_BRACKET_CLOSE,  // }
 
-    T_COMMA,        // ,
-    T_SEMICOLON,    // ;
-    T_DOUBLE_QUOTE, // "
+    T_COMMA,     // ,
+    T_DOT,       // .
+    T_COLON,     // :
+    T_SEMICOLON, // ;
 
     T_ASSIGNMENT_OPR,            // =
+    T_PLUS_ASSIGN_OPR,           // +=
+    T_MINUS_ASSIGN_OPR,          // -=
+    T_MULTIPLY_ASSIGN_OPR,       // *=
+    T_DIVIDE_ASSIGN_OPR,         // /=
+    T_MODULO_ASSIGN_OPR,         // %=
+    T_LEFT_SHIFT_ASSIGN_OPR,     // <<=
+    T_RIGHT_SHIFT_ASSIGN_OPR,    // >>=
+    T_INCREMENT_OPR,             // ++
+    T_DECREMENT_OPR,             // --
     T_EQUALS_OPR,                // ==
     T_NOT,                       // !
     T_NOT_EQUALS_OPR,            // !=
@@ -35,16 +51,21 @@ pub enum Token {
     T_GREATER_THAN_OPR,          // >
     T_LESS_THAN_EQUAL_TO_OPR,    // <=
     T_GREATER_THAN_EQUAL_TO_OPR, // >=
-    T_AND_OPR,                   // && , &
-    T_OR_OPR,                    // || , |
+    T_AND_OPR,                   // &&
+    T_OR_OPR,                    // ||
     T_RIGHT_SHIFT_OPR,           // >>
     T_LEFT_SHIFT_OPR,            // <<
+    T_BIT_AND_OPR,               // &
+    T_BIT_OR_OPR,                // |
+    T_BIT_XOR_OPR,               // ^
+    T_BIT_NOT,                   // ~
 
     T_PLUS_OPR,     // +
     T_MINUS_OPR,    // -
     T_MULTIPLY_OPR, // *
     T_DIVIDE_OPR,   // /
-    T_EXPONENT_OPR, // ^
+    T_MODULO_OPR,   // %
+    T_EXPONENT_OPR, // **
 
     T_INT,    // 0
     T_FLOAT,  // 0.0
@@ -52,3 +73,97 @@ pub enum Token {
     T_STRING, // string
     T_VOID,   // void
 }
+
+impl Token {
+    // The operator a compound assignment applies, `+=` is `+`. `++`/`--` are `+ 1`/`- 1`
+    pub fn compound_operator(&self) -> Option<Token> {
+        Some(match self {
+            T_PLUS_ASSIGN_OPR | T_INCREMENT_OPR => T_PLUS_OPR,
+            T_MINUS_ASSIGN_OPR | T_DECREMENT_OPR => T_MINUS_OPR,
+            T_MULTIPLY_ASSIGN_OPR => T_MULTIPLY_OPR,
+            T_DIVIDE_ASSIGN_OPR => T_DIVIDE_OPR,
+            T_MODULO_ASSIGN_OPR => T_MODULO_OPR,
+            T_LEFT_SHIFT_ASSIGN_OPR => T_LEFT_SHIFT_OPR,
+            T_RIGHT_SHIFT_ASSIGN_OPR => T_RIGHT_SHIFT_OPR,
+            _ => return None,
+        })
+    }
+}
+
+// A token together with the location it was lexed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+// Prints the token the way it appears in source, for error messages
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            T_FUNCTION => "fn",
+            T_IF => "if",
+            T_ELSE => "else",
+            T_ELSE_IF => "elif",
+            T_WHILE => "while",
+            T_FOR => "for",
+            T_RETURN => "return",
+            T_BREAK => "break",
+            T_CONTINUE => "continue",
+            T_STRUCT => "struct",
+            T_IDENTIFIER(name) => return write!(f, "identifier '{}'", name),
+            T_STRINGLIT(s) => return write!(f, "string \"{}\"", s.escape_debug()),
+            T_DOC_COMMENT(_) => return write!(f, "doc comment"),
+            T_CONST_INT(i) => return write!(f, "'{}'", i),
+            T_CONST_FLOAT(x) => return write!(f, "'{}'", x),
+            T_CONST_BOOL(b) => return write!(f, "'{}'", b),
+            T_ROUND_BRACKET_OPEN => "(",
+            T_ROUND_BRACKET_CLOSE => ")",
+            T_SQUARE_BRACKET_OPEN => "[",
+            T_SQUARE_BRACKET_CLOSE => "]",
+            T_CURLY_BRACKET_OPEN => "{",
+            T_CURLY_BRACKET_CLOSE => "}",
+            T_COMMA => ",",
+            T_DOT => ".",
+            T_COLON => ":",
+            T_SEMICOLON => ";",
+            T_ASSIGNMENT_OPR => "=",
+            T_PLUS_ASSIGN_OPR => "+=",
+            T_MINUS_ASSIGN_OPR => "-=",
+            T_MULTIPLY_ASSIGN_OPR => "*=",
+            T_DIVIDE_ASSIGN_OPR => "/=",
+            T_MODULO_ASSIGN_OPR => "%=",
+            T_LEFT_SHIFT_ASSIGN_OPR => "<<=",
+            T_RIGHT_SHIFT_ASSIGN_OPR => ">>=",
+            T_INCREMENT_OPR => "++",
+            T_DECREMENT_OPR => "--",
+            T_EQUALS_OPR => "==",
+            T_NOT => "!",
+            T_NOT_EQUALS_OPR => "!=",
+            T_LESS_THAN_OPR => "<",
+            T_GREATER_THAN_OPR => ">",
+            T_LESS_THAN_EQUAL_TO_OPR => "<=",
+            T_GREATER_THAN_EQUAL_TO_OPR => ">=",
+            T_AND_OPR => "&&",
+            T_OR_OPR => "||",
+            T_RIGHT_SHIFT_OPR => ">>",
+            T_LEFT_SHIFT_OPR => "<<",
+            T_BIT_AND_OPR => "&",
+            T_BIT_OR_OPR => "|",
+            T_BIT_XOR_OPR => "^",
+            T_BIT_NOT => "~",
+            T_PLUS_OPR => "+",
+            T_MINUS_OPR => "-",
+            T_MULTIPLY_OPR => "*",
+            T_DIVIDE_OPR => "/",
+            T_MODULO_OPR => "%",
+            T_EXPONENT_OPR => "**",
+            T_INT => "int",
+            T_FLOAT => "float",
+            T_BOOL => "bool",
+            T_STRING => "string",
+            T_VOID => "void",
+        };
+        write!(f, "'{}'", text)
+    }
+}
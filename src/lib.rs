@@ -0,0 +1,11 @@
+// Relipmoc compiler as a library: each phase is reachable on its own,
+// or through `Session` which runs them in order and collects diagnostics.
+pub mod diagnostics;
+pub mod driver;
+pub mod interpreter;
+pub mod ir;
+pub mod lexer;
+pub mod parser;
+pub mod semantics;
+
+pub use driver::session::{CheckedProgram, CompileError, Phase, Session};
@@ -1,42 +1,318 @@
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Read, Write};
 use std::process::exit;
 
-mod ir;
-mod lexer;
-mod parser;
-mod semantics;
+use relipmoc::diagnostics::diagnostic::Diagnostic;
+use relipmoc::diagnostics::renderer::Renderer;
+use relipmoc::interpreter::value::Value;
+use relipmoc::semantics::lints::{Level, Lint, LintConfig};
+use relipmoc::{CompileError, Phase, Session};
 
-fn get_code() -> String {
-    // TODO: allow custom file path
-    // let mut file_name = String::new();
-    // print!("Enter the file name: ");
-    // io::stdout().flush().unwrap(); // flush to print on screen
-    // io::stdin().read_line(&mut file_name).unwrap();
+const USAGE: &str = "\
+Usage: relipmoc <file> [-o <out>] [--emit=<stages>]
+       relipmoc run [--vm] <file>
 
-    let path = Path::new("data").join("code.txt");
-    let file_content = fs::read_to_string(path).unwrap();
+Compiles <file>, or standard input when <file> is '-'.
+With 'run' the program is interpreted instead, starting at main(), and the
+exit code is the int main returns. --vm runs the generated IR on the virtual
+machine instead of walking the syntax tree.
 
-    return file_content;
+Options:
+  -o <out>           write the emitted output to <out> instead of stdout
+  --emit=<stages>    comma separated list of what to print (default: ir)
+                       tokens  the token stream
+                       ast     the parsed syntax tree
+                       ir      three address code
+                       check   nothing, stop after semantic analysis
+  --allow <lint>     don't report <lint>
+  --warn <lint>      report <lint> as a warning (the default)
+  --deny <lint>      report <lint> as an error
+  -h, --help         print this message
+
+Lints ('all' sets every one of them):
+  unused-variable    a local variable that is never read
+  unused-parameter   a parameter that is never read
+  unused-function    a function nothing calls
+  shadowing          a variable hiding one from an outer scope
+  dead-assignment    a value that is overwritten before it is read
+  bool-comparison    comparing a bool with 'true' or 'false'
+  unreachable-code   statements that can never run
+
+Exit codes:
+  0  success
+  1  usage or I/O error
+  2  lexing failed
+  3  parsing failed
+  4  semantic analysis failed
+  5  IR generation failed
+  6  the program hit a runtime error (run only)";
+
+// Exit codes, one per phase so build tooling can tell failures apart
+const EXIT_USAGE: i32 = 1;
+
+fn exit_code(phase: Phase) -> i32 {
+    match phase {
+        Phase::Lex => 2,
+        Phase::Parse => 3,
+        Phase::Semantic => 4,
+        Phase::Ir => 5,
+        Phase::Runtime => 6,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    Tokens,
+    Ast,
+    Ir,
+    Check,
+}
+
+#[derive(Debug)]
+struct Options {
+    input: String, // "-" for stdin
+    output: Option<String>,
+    emit: Vec<Emit>,
+    run: bool, // interpret instead of emitting
+    vm: bool,  // run the IR instead of the syntax tree
+    lints: LintConfig,
+}
+
+fn parse_emit(list: &str) -> Result<Vec<Emit>, String> {
+    let mut emit = Vec::new();
+    for stage in list.split(',') {
+        emit.push(match stage {
+            "tokens" => Emit::Tokens,
+            "ast" => Emit::Ast,
+            "ir" => Emit::Ir,
+            "check" => Emit::Check,
+            other => return Err(format!("unknown --emit stage '{}'", other)),
+        });
+    }
+    Ok(emit)
+}
+
+fn lint_level(flag: &str) -> Option<Level> {
+    match flag {
+        "--allow" => Some(Level::Allow),
+        "--warn" => Some(Level::Warn),
+        "--deny" => Some(Level::Deny),
+        _ => None,
+    }
+}
+
+fn parse_lint(lints: &mut LintConfig, name: &str, level: Level) -> Result<(), String> {
+    if name == "all" {
+        lints.set_all(level);
+        return Ok(());
+    }
+    match Lint::from_name(name) {
+        Some(lint) => {
+            lints.set(lint, level);
+            Ok(())
+        }
+        None => Err(format!("unknown lint '{}'", name)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut emit = vec![Emit::Ir];
+    let mut emit_given = false;
+    let mut vm = false;
+    let mut lints = LintConfig::default();
+
+    let run = args.first().is_some_and(|arg| arg == "run");
+    let mut i = if run { 1 } else { 0 };
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            exit(0);
+        } else if arg == "-o" {
+            i += 1;
+            match args.get(i) {
+                Some(path) => output = Some(path.clone()),
+                None => return Err("'-o' needs a file name".to_string()),
+            }
+        } else if let Some(list) = arg.strip_prefix("--emit=") {
+            emit = parse_emit(list)?;
+            emit_given = true;
+        } else if arg == "--emit" {
+            i += 1;
+            match args.get(i) {
+                Some(list) => {
+                    emit = parse_emit(list)?;
+                    emit_given = true;
+                }
+                None => return Err("'--emit' needs a list of stages".to_string()),
+            }
+        } else if let Some(level) = lint_level(arg.split('=').next().unwrap_or(arg)) {
+            let name = match arg.split_once('=') {
+                Some((_, name)) => name,
+                None => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(name) => name.as_str(),
+                        None => return Err(format!("'{}' needs a lint name", arg)),
+                    }
+                }
+            };
+            parse_lint(&mut lints, name, level)?;
+        } else if run && arg == "--vm" {
+            vm = true;
+        } else if arg.starts_with('-') && arg != "-" {
+            return Err(format!("unknown option '{}'", arg));
+        } else if input.is_none() {
+            input = Some(arg.clone());
+        } else {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+        i += 1;
+    }
+
+    // running prints nothing of its own unless asked to
+    if run && !emit_given {
+        emit.clear();
+    }
+
+    match input {
+        Some(input) => Ok(Options {
+            input,
+            output,
+            emit,
+            run,
+            vm,
+            lints,
+        }),
+        None => Err("no input file given".to_string()),
+    }
+}
+
+fn get_code(input: &str) -> io::Result<String> {
+    if input == "-" {
+        let mut code = String::new();
+        io::stdin().read_to_string(&mut code)?;
+        Ok(code)
+    } else {
+        fs::read_to_string(input)
+    }
+}
+
+// Prints every diagnostic with its source snippet, then a summary line
+fn report(renderer: &Renderer, diagnostics: &[Diagnostic]) {
+    eprintln!("{}", renderer.render_all(diagnostics));
+    let error_count = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warning_count = diagnostics.len() - error_count;
+    if error_count > 0 {
+        eprintln!(
+            "error: aborting due to {} previous error{}",
+            error_count,
+            if error_count == 1 { "" } else { "s" }
+        );
+    } else if warning_count > 0 {
+        eprintln!(
+            "warning: {} warning{} emitted",
+            warning_count,
+            if warning_count == 1 { "" } else { "s" }
+        );
+    }
+}
+
+fn abort(renderer: &Renderer, error: CompileError) -> ! {
+    report(renderer, &error.diagnostics);
+    exit(exit_code(error.phase));
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    exit(EXIT_USAGE);
 }
 
 fn main() {
-    let code = get_code();
-    let tokens = lexer::lexer::lex(code);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            exit(EXIT_USAGE);
+        }
+    };
+
+    let code = match get_code(&options.input) {
+        Ok(code) => code,
+        Err(e) => fail(&format!("cannot read '{}': {}", options.input, e)),
+    };
+    let file_name = if options.input == "-" {
+        "<stdin>"
+    } else {
+        options.input.as_str()
+    };
+    let renderer = Renderer::new(file_name, &code);
+    let mut session = Session::new(file_name, code.clone());
+    session.lints = options.lints.clone();
+    let mut out = String::new();
+
+    let tokens = session.lex().unwrap_or_else(|e| abort(&renderer, e));
+
+    if options.emit.contains(&Emit::Tokens) {
+        for token in &tokens {
+            out.push_str(&format!("{}\t{:?}\n", token.span, token.token));
+        }
+    }
+
+    let ast = session
+        .parse(tokens)
+        .unwrap_or_else(|e| abort(&renderer, e));
 
-    println!("{:?}\n\n", tokens);
+    if options.emit.contains(&Emit::Ast) {
+        out.push_str(&format!("{:#?}\n", ast));
+    }
+
+    let program = session.check(ast).unwrap_or_else(|e| abort(&renderer, e));
+    if !session.diagnostics().is_empty() {
+        report(&renderer, session.diagnostics());
+    }
 
-    let ast = parser::parser::parser(tokens);
+    if options.emit.contains(&Emit::Ir) {
+        let code = session
+            .generate_ir(&program)
+            .unwrap_or_else(|e| abort(&renderer, e));
+        out.push_str(&format!("{}\n", code));
+    }
 
-    println!("{:#?}\n\n", ast);
+    if !options.run || !out.is_empty() {
+        write_output(&options, &out);
+    }
 
-    match semantics::semantic_analysis::semantic_analysis(&ast) {
-        Ok(_) => {}
-        Err(_) => exit(1),
+    if options.run {
+        let mut stdout = io::stdout(); // not locked, the tree walker runs on its own thread
+        let result = if options.vm {
+            let module = session
+                .generate_ir(&program)
+                .unwrap_or_else(|e| abort(&renderer, e));
+            session.run_ir(&module, &mut stdout)
+        } else {
+            session.run(&program, &mut stdout)
+        };
+        let _ = stdout.flush(); // program output goes before any error
+        match result {
+            Ok(Value::Int(code)) => exit(code as i32),
+            Ok(_) => exit(0),
+            Err(e) => abort(&renderer, e),
+        }
     }
+}
 
-    match ir::ir_generator::ir_generator(&ast) {
-        Ok(code) => println!("TAC IR:\n{}\n", code),
-        Err(_) => println!("Error generating IR"),
+fn write_output(options: &Options, out: &str) {
+    match &options.output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, out) {
+                fail(&format!("cannot write '{}': {}", path, e));
+            }
+        }
+        None => print!("{}", out),
     }
 }
@@ -1,3 +1,4 @@
+use crate::lexer::span::Span;
 use crate::lexer::tokens::Token;
 
 pub type RootList = Vec<Root>;
@@ -6,18 +7,40 @@ pub type RootList = Vec<Root>;
 pub enum Root {
     Var(VariableDeclaration),
     Func(FunctionStatement),
+    Struct(StructDeclaration),
+}
+
+// A type as written in source
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeName {
+    Base(Token),          // int, float, bool, string or void
+    Struct(String, Span), // a struct declared somewhere in the program
+    Array {
+        // `int[3]`, or `int[]` for an array of any length
+        element: Box<TypeName>,
+        size: Option<usize>,
+    },
 }
 
 #[derive(Debug)]
 pub struct VariableDeclaration {
     // for declaration of variables
-    pub type_token: Token,
+    pub var_type: TypeName,
     pub identifier: String,
-    pub expression: Expression,
+    pub expression: Option<Expression>, // None for `int x;`
+    pub doc: Option<String>,            // `##` lines above a global, locals never have one
+    pub identifier_span: Span,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum ExpressionKind {
     Literal(Constants),
     Identifier(String),
     BinaryOperation {
@@ -35,10 +58,47 @@ pub enum Expression {
         left: Box<Expression>,
         right: Box<Expression>,
     },
+    CompoundAssignment {
+        // like x += 2, operator is the `+=` token
+        left: Box<Expression>,
+        operator: Token,
+        right: Box<Expression>,
+    },
+    Increment {
+        // like ++x or x--
+        target: Box<Expression>,
+        operator: Token, // `++` or `--`
+        postfix: bool,
+    },
     FunctionCall(FunctionCallStatement),
+    Index {
+        // like a[i]
+        array: Box<Expression>,
+        index: Box<Expression>,
+    },
+    ArrayLiteral(Vec<Expression>), // like [1, 2, 3]
+    FieldAccess {
+        // like p.x
+        object: Box<Expression>,
+        field: String,
+        field_span: Span,
+    },
+    StructLiteral {
+        // like Point { x: 1, y: 2.0 }
+        identifier: String,
+        fields: Vec<FieldInit>,
+        identifier_span: Span,
+    },
 }
 
 #[derive(Debug)]
+pub struct FieldInit {
+    pub identifier: String,
+    pub value: Expression,
+    pub span: Span, // the field's name
+}
+
+#[derive(Debug, Clone)]
 pub enum Constants {
     Int(i64),    // T_CONST_INT
     Float(f64),  // T_CONST_FLOAT
@@ -50,32 +110,60 @@ pub enum Constants {
 pub struct FunctionCallStatement {
     pub identifier: String,
     pub args: Vec<Expression>,
+    pub identifier_span: Span,
 }
 
 #[derive(Debug)]
 pub struct Parameter {
-    pub param_type: Token,
+    pub param_type: TypeName,
     pub identifier: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct FunctionStatement {
-    pub return_type: Token,
+    pub return_type: TypeName,
     pub identifier: String,
     pub parameters: Vec<Parameter>,
     pub block: Block,
+    pub doc: Option<String>, // `##` lines above the function
+    pub identifier_span: Span,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct StructDeclaration {
+    pub identifier: String,
+    pub fields: Vec<StructField>,
+    pub identifier_span: Span,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct StructField {
+    pub field_type: TypeName,
+    pub identifier: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Block {
     pub statements: Vec<Statement>,
+    pub span: Span, // from '{' to '}'
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub enum Statement {
+#[allow(clippy::large_enum_variant)]
+pub enum StatementKind {
     VarDecl(VariableDeclaration),
     Expr(Expression),
-    Return(Expression),
+    Return(Option<Expression>), // None for a bare `return;`
     Break,
     Continue,
     If(IfStatement),
@@ -95,6 +183,7 @@ pub struct IfStatement {
 pub struct ElifBlock {
     pub condition: Expression,
     pub block: Block,
+    pub span: Span,
 }
 
 #[derive(Debug)]
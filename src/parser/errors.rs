@@ -1,16 +1,106 @@
+use std::fmt;
+
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
 use crate::lexer::tokens::Token;
+use crate::parser::parser::MAX_ARRAY_LEN;
 
 #[derive(Debug)]
 pub enum Errors {
-    UnexpectedEOF,
-    FailedToFindToken(Token),
-    ExpectedTypeToken(Token),
-    ExpectedIdentifier(Token),
-    UnexpectedToken(Token),
-    ExpectedFloatLit,
-    ExpectedIntLit,
-    ExpectedStringLit,
-    ExpectedBoolLit,
-    ExpectedExpr,
-    InvalidAssignmentTarget,
+    UnexpectedEOF(Span),
+    ExpectedTypeToken(Token, Span),
+    ExpectedIdentifier(Token, Span),
+    UnexpectedToken(Token, Span),
+    ExpectedToken(Token, Token, Span), // (expected, found)
+    ExpectedExpression(Token, Span),
+    InvalidAssignmentTarget(Span),
+    ExpectedArraySize(Token, Span),
+    IntegerTooLarge(u64, Span), // 2^63 without a '-' in front
+    ArrayTooLarge(u64, Span),
+}
+
+impl Errors {
+    fn code(&self) -> &'static str {
+        match self {
+            Errors::UnexpectedEOF(_) => codes::UNEXPECTED_EOF,
+            Errors::ExpectedTypeToken(..) => codes::EXPECTED_TYPE,
+            Errors::ExpectedIdentifier(..) => codes::EXPECTED_IDENTIFIER,
+            Errors::UnexpectedToken(..)
+            | Errors::ExpectedToken(..)
+            | Errors::ExpectedExpression(..) => codes::UNEXPECTED_TOKEN,
+            Errors::InvalidAssignmentTarget(_) => codes::INVALID_ASSIGNMENT_TARGET,
+            Errors::ExpectedArraySize(..) => codes::EXPECTED_ARRAY_SIZE,
+            Errors::IntegerTooLarge(..) => codes::INTEGER_TOO_LARGE,
+            Errors::ArrayTooLarge(..) => codes::ARRAY_TOO_LARGE,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), self.to_string(), self.span());
+        match self {
+            Errors::InvalidAssignmentTarget(_) => diagnostic
+                .with_label("cannot assign to this expression")
+                .with_note("only variables, array elements and fields can be assigned to"),
+            Errors::UnexpectedEOF(_) => diagnostic.with_label("file ends here"),
+            Errors::ExpectedToken(expected, _, _) => {
+                diagnostic.with_label(format!("expected {} here", expected))
+            }
+            Errors::ExpectedArraySize(..) => diagnostic
+                .with_label("expected a whole number")
+                .with_note("sizes are written out, like `int[10]`, or left out: `int[]`"),
+            Errors::IntegerTooLarge(value, _) => diagnostic.with_note(format!(
+                "the largest int is {}, {} is only allowed as -{}",
+                i64::MAX,
+                value,
+                value
+            )),
+            Errors::ArrayTooLarge(..) => {
+                diagnostic.with_note(format!("an array holds at most {} elements", MAX_ARRAY_LEN))
+            }
+            _ => diagnostic,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Errors::UnexpectedEOF(span)
+            | Errors::ExpectedTypeToken(_, span)
+            | Errors::ExpectedIdentifier(_, span)
+            | Errors::UnexpectedToken(_, span)
+            | Errors::ExpectedToken(_, _, span)
+            | Errors::ExpectedExpression(_, span)
+            | Errors::InvalidAssignmentTarget(span)
+            | Errors::ExpectedArraySize(_, span)
+            | Errors::IntegerTooLarge(_, span)
+            | Errors::ArrayTooLarge(_, span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Errors::UnexpectedEOF(_) => write!(f, "unexpected end of file"),
+            Errors::ExpectedTypeToken(token, _) => write!(f, "expected a type, found {}", token),
+            Errors::ExpectedIdentifier(token, _) => {
+                write!(f, "expected an identifier, found {}", token)
+            }
+            Errors::UnexpectedToken(token, _) => write!(f, "unexpected token {}", token),
+            Errors::ExpectedToken(expected, found, _) => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            Errors::ExpectedExpression(token, _) => {
+                write!(f, "expected an expression, found {}", token)
+            }
+            Errors::InvalidAssignmentTarget(_) => write!(f, "invalid assignment target"),
+            Errors::ExpectedArraySize(token, _) => {
+                write!(f, "expected an array size, found {}", token)
+            }
+            Errors::IntegerTooLarge(value, _) => {
+                write!(f, "integer literal is too large: {}", value)
+            }
+            Errors::ArrayTooLarge(size, _) => write!(f, "array size is too large: {}", size),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 pub mod enums;
 mod errors;
+#[allow(clippy::module_inception)]
 pub mod parser;
 mod token_iterator;
@@ -1,4 +1,5 @@
-use crate::lexer::tokens::Token;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::tokens::{SpannedToken, Token};
 use crate::parser::enums::*;
 use crate::parser::errors::Errors;
 use crate::parser::token_iterator::TokenIterator;
@@ -8,365 +9,481 @@ fn parse_expression(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
 }
 
 fn parse_assignment(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let expr = parse_logical_or(tokens)?;
+    let expr = parse_binary(tokens, 0)?;
 
     if let Some(Token::T_ASSIGNMENT_OPR) = tokens.peek_curr() {
         tokens.consume()?;
         let right = parse_assignment(tokens)?;
 
-        // Ensure left side is an identifier for assignment
-        if let Expression::Identifier(_) = expr {
-            return Ok(Expression::Assignment {
-                left: Box::new(expr),
-                right: Box::new(right),
+        // Ensure left side is a variable or an element of one for assignment
+        if is_place(&expr) {
+            let span = expr.span.to(right.span);
+            return Ok(Expression {
+                kind: ExpressionKind::Assignment {
+                    left: Box::new(expr),
+                    right: Box::new(right),
+                },
+                span,
             });
         } else {
-            return Err(Errors::InvalidAssignmentTarget);
+            return Err(Errors::InvalidAssignmentTarget(expr.span));
         }
     }
 
-    Ok(expr)
-}
-
-fn parse_logical_or(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_logical_and(tokens)?;
-
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_OR_OPR => {
-                tokens.consume()?;
-                let right = parse_logical_and(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_OR_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
+    // `x += 1`, same rules as `=`
+    if let Some(token) = tokens.peek_curr()
+        && token.compound_operator().is_some()
+        && !matches!(token, Token::T_INCREMENT_OPR | Token::T_DECREMENT_OPR)
+    {
+        let operator = tokens.consume()?.clone();
+        let right = parse_assignment(tokens)?;
+        if !is_place(&expr) {
+            return Err(Errors::InvalidAssignmentTarget(expr.span));
         }
+        let span = expr.span.to(right.span);
+        return Ok(Expression {
+            kind: ExpressionKind::CompoundAssignment {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        });
     }
 
     Ok(expr)
 }
 
-fn parse_logical_and(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_equality(tokens)?;
-
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_AND_OPR => {
-                tokens.consume()?;
-                let right = parse_equality(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_AND_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
-        }
+// Something that names a storage location: `a`, `a[i]`, `a[i][j]`, `a[i].x`
+fn is_place(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Identifier(_) => true,
+        ExpressionKind::Index { array, .. } => is_place(array),
+        ExpressionKind::FieldAccess { object, .. } => is_place(object),
+        _ => false,
     }
-
-    Ok(expr)
 }
 
-fn parse_equality(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_comparison(tokens)?;
+// Largest size an array type can be written with. The runtimes hold the elements
+// of nested arrays to the same limit together.
+pub const MAX_ARRAY_LEN: usize = 1 << 24;
 
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_EQUALS_OPR => {
-                tokens.consume()?;
-                let right = parse_comparison(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_EQUALS_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_NOT_EQUALS_OPR => {
-                tokens.consume()?;
-                let right = parse_comparison(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_NOT_EQUALS_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
-        }
-    }
+#[derive(Clone, Copy, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+}
 
-    Ok(expr)
+// Every binary operator with its binding power and associativity, loosest first.
+// Adding an operator is one line here (plus the lexer token and the BNF rule).
+const BINARY_OPERATORS: &[(Token, u8, Assoc)] = &[
+    (Token::T_OR_OPR, 1, Assoc::Left),
+    (Token::T_AND_OPR, 2, Assoc::Left),
+    (Token::T_BIT_OR_OPR, 3, Assoc::Left),
+    (Token::T_BIT_XOR_OPR, 4, Assoc::Left),
+    (Token::T_BIT_AND_OPR, 5, Assoc::Left),
+    (Token::T_EQUALS_OPR, 6, Assoc::Left),
+    (Token::T_NOT_EQUALS_OPR, 6, Assoc::Left),
+    (Token::T_GREATER_THAN_OPR, 7, Assoc::Left),
+    (Token::T_LESS_THAN_OPR, 7, Assoc::Left),
+    (Token::T_GREATER_THAN_EQUAL_TO_OPR, 7, Assoc::Left),
+    (Token::T_LESS_THAN_EQUAL_TO_OPR, 7, Assoc::Left),
+    (Token::T_LEFT_SHIFT_OPR, 8, Assoc::Left),
+    (Token::T_RIGHT_SHIFT_OPR, 8, Assoc::Left),
+    (Token::T_PLUS_OPR, 9, Assoc::Left),
+    (Token::T_MINUS_OPR, 9, Assoc::Left),
+    (Token::T_MULTIPLY_OPR, 10, Assoc::Left),
+    (Token::T_DIVIDE_OPR, 10, Assoc::Left),
+    (Token::T_MODULO_OPR, 10, Assoc::Left),
+    (Token::T_EXPONENT_OPR, 11, Assoc::Right),
+];
+
+fn binary_operator(token: Option<&Token>) -> Option<(u8, Assoc)> {
+    let token = token?;
+    BINARY_OPERATORS
+        .iter()
+        .find(|(operator, _, _)| operator == token)
+        .map(|&(_, power, assoc)| (power, assoc))
 }
 
-fn parse_comparison(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_shift(tokens)?;
+// Precedence climbing: parses operands joined by operators that bind tighter than
+// `min_power`. A left-associative operator parses its right side one level up so the
+// next operator of the same power ends up outside; a right-associative one doesn't,
+// so `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+fn parse_binary(tokens: &mut TokenIterator, min_power: u8) -> Result<Expression, Errors> {
+    let mut expr = parse_unary(tokens)?;
 
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_GREATER_THAN_OPR => {
-                tokens.consume()?;
-                let right = parse_shift(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_GREATER_THAN_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_LESS_THAN_OPR => {
-                tokens.consume()?;
-                let right = parse_shift(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_LESS_THAN_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_GREATER_THAN_EQUAL_TO_OPR => {
-                tokens.consume()?;
-                let right = parse_shift(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_GREATER_THAN_EQUAL_TO_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_LESS_THAN_EQUAL_TO_OPR => {
-                tokens.consume()?;
-                let right = parse_shift(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_LESS_THAN_EQUAL_TO_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
+    while let Some((power, assoc)) = binary_operator(tokens.peek_curr()) {
+        if power <= min_power {
+            break;
         }
+        let operator = tokens.consume()?.clone();
+        let next = match assoc {
+            Assoc::Left => power,
+            Assoc::Right => power - 1,
+        };
+        let right = parse_binary(tokens, next)?;
+        let span = expr.span.to(right.span);
+        expr = Expression {
+            kind: ExpressionKind::BinaryOperation {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        };
     }
 
     Ok(expr)
 }
 
-fn parse_shift(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_term(tokens)?;
+// `-5` as one literal, which is how i64::MIN gets written. Not when a postfix
+// operator follows, `-5[0]` stays a minus on `5[0]`
+fn negative_literal(tokens: &TokenIterator) -> Option<Constants> {
+    if tokens.peek_curr() != Some(&Token::T_MINUS_OPR)
+        || matches!(
+            tokens.peek_at(2),
+            Some(
+                Token::T_SQUARE_BRACKET_OPEN
+                    | Token::T_DOT
+                    | Token::T_INCREMENT_OPR
+                    | Token::T_DECREMENT_OPR
+            )
+        )
+    {
+        return None;
+    }
+    match tokens.peek_next()? {
+        // the lexer keeps ints at or below 2^63, so this never wraps
+        Token::T_CONST_INT(value) => Some(Constants::Int(0i64.wrapping_sub_unsigned(*value))),
+        Token::T_CONST_FLOAT(value) => Some(Constants::Float(-value)),
+        _ => None,
+    }
+}
 
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_LEFT_SHIFT_OPR => {
-                tokens.consume()?;
-                let right = parse_term(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_LEFT_SHIFT_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_RIGHT_SHIFT_OPR => {
-                tokens.consume()?;
-                let right = parse_term(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_RIGHT_SHIFT_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
+fn parse_unary(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
+    // ++x / --x
+    if let Some(Token::T_INCREMENT_OPR | Token::T_DECREMENT_OPR) = tokens.peek_curr() {
+        let start = tokens.curr_span();
+        let operator = tokens.consume()?.clone();
+        let target = parse_unary(tokens)?;
+        if !is_place(&target) {
+            return Err(Errors::InvalidAssignmentTarget(target.span));
         }
+        let span = start.to(target.span);
+        return Ok(Expression {
+            kind: ExpressionKind::Increment {
+                target: Box::new(target),
+                operator,
+                postfix: false,
+            },
+            span,
+        });
     }
 
-    Ok(expr)
-}
-
-fn parse_term(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_factor(tokens)?;
+    if let Some(constant) = negative_literal(tokens) {
+        let start = tokens.curr_span();
+        tokens.consume()?;
+        tokens.consume()?;
+        return Ok(Expression {
+            kind: ExpressionKind::Literal(constant),
+            span: tokens.span_from(start),
+        });
+    }
 
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_PLUS_OPR => {
-                tokens.consume()?;
-                let right = parse_factor(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_PLUS_OPR,
-                    right: Box::new(right),
-                };
-            }
-            Token::T_MINUS_OPR => {
-                tokens.consume()?;
-                let right = parse_factor(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_MINUS_OPR,
-                    right: Box::new(right),
-                };
-            }
-            _ => break,
-        }
+    if let Some(Token::T_NOT | Token::T_MINUS_OPR | Token::T_BIT_NOT) = tokens.peek_curr() {
+        let start = tokens.curr_span();
+        let operator = tokens.consume()?.clone();
+        let expression = parse_unary(tokens)?;
+        let span = start.to(expression.span);
+        return Ok(Expression {
+            kind: ExpressionKind::UnaryOperation {
+                operator,
+                expression: Box::new(expression),
+            },
+            span,
+        });
     }
 
-    Ok(expr)
+    parse_postfix(tokens)
 }
 
-fn parse_factor(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_exponential(tokens)?;
+// Indexing, field access and postfix `++`/`--` bind tighter than any operator:
+// `-a[i].x` is `-((a[i]).x)`
+fn parse_postfix(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
+    let mut expr = parse_primary(tokens)?;
 
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_MULTIPLY_OPR => {
+    loop {
+        let start = expr.span;
+        let kind = match tokens.peek_curr() {
+            Some(Token::T_SQUARE_BRACKET_OPEN) => {
                 tokens.consume()?;
-                let right = parse_exponential(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_MULTIPLY_OPR,
-                    right: Box::new(right),
-                };
+                let index = parse_expression(tokens)?;
+                tokens.seek_if(Token::T_SQUARE_BRACKET_CLOSE)?;
+                ExpressionKind::Index {
+                    array: Box::new(expr),
+                    index: Box::new(index),
+                }
             }
-            Token::T_DIVIDE_OPR => {
+            Some(Token::T_DOT) => {
                 tokens.consume()?;
-                let right = parse_exponential(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_DIVIDE_OPR,
-                    right: Box::new(right),
+                let field_span = tokens.curr_span();
+                let field = match tokens.consume()? {
+                    Token::T_IDENTIFIER(name) => name.clone(),
+                    other => return Err(Errors::ExpectedIdentifier(other.clone(), field_span)),
                 };
+                ExpressionKind::FieldAccess {
+                    object: Box::new(expr),
+                    field,
+                    field_span,
+                }
             }
-            _ => break,
-        }
-    }
-
-    Ok(expr)
-}
-
-fn parse_exponential(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    let mut expr = parse_unary(tokens)?;
-
-    while let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_EXPONENT_OPR => {
-                tokens.consume()?;
-                let right = parse_unary(tokens)?;
-                expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
-                    operator: Token::T_EXPONENT_OPR,
-                    right: Box::new(right),
-                };
+            // x++ / x--, gives the value from before
+            Some(Token::T_INCREMENT_OPR | Token::T_DECREMENT_OPR) => {
+                if !is_place(&expr) {
+                    return Err(Errors::InvalidAssignmentTarget(expr.span));
+                }
+                let operator = tokens.consume()?.clone();
+                ExpressionKind::Increment {
+                    target: Box::new(expr),
+                    operator,
+                    postfix: true,
+                }
             }
             _ => break,
-        }
+        };
+        expr = Expression {
+            kind,
+            span: tokens.span_from(start),
+        };
     }
 
     Ok(expr)
 }
 
-fn parse_unary(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
-    if let Some(token) = tokens.peek_curr() {
-        match token {
-            Token::T_NOT | Token::T_MINUS_OPR => {
-                let operator = tokens.consume()?.clone();
-                let expression = parse_unary(tokens)?;
-                return Ok(Expression::UnaryOperation {
-                    operator: operator,
-                    expression: Box::new(expression),
-                });
-            }
-            _ => {}
-        }
-    }
-
-    parse_primary(tokens)
-}
-
 fn parse_primary(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
+    let start = tokens.curr_span();
+
     // Check for identifier first to handle function calls properly
     if let Some(Token::T_IDENTIFIER(_)) = tokens.peek_curr() {
         if let Some(Token::T_ROUND_BRACKET_OPEN) = tokens.peek_next() {
             // Function call - don't consume the identifier here, let parse_function_call handle it
             let func_call = parse_function_call(tokens)?;
-            return Ok(Expression::FunctionCall(func_call));
+            return Ok(Expression {
+                kind: ExpressionKind::FunctionCall(func_call),
+                span: tokens.span_from(start),
+            });
+        } else if let Some(Token::T_CURLY_BRACKET_OPEN) = tokens.peek_next() {
+            // no expression is ever followed by a block, so `Name {` is a struct
+            return parse_struct_literal(tokens);
         } else {
             // Regular identifier
             let name = match tokens.consume()? {
                 Token::T_IDENTIFIER(name) => name.clone(),
-                other => return Err(Errors::ExpectedIdentifier(other.clone())),
+                other => return Err(Errors::ExpectedIdentifier(other.clone(), start)),
             };
-            return Ok(Expression::Identifier(name));
+            return Ok(Expression {
+                kind: ExpressionKind::Identifier(name),
+                span: start,
+            });
         }
     }
 
-    let current = tokens.consume()?;
+    // only consume once we know the token starts an expression, recovery resumes from it otherwise
+    let current = match tokens.peek_curr() {
+        Some(token) => token.clone(),
+        None => return Err(Errors::UnexpectedEOF(start)),
+    };
 
-    match current {
-        Token::T_CONST_INT(value) => Ok(Expression::Literal(Constants::Int(value.clone()))),
-        Token::T_CONST_FLOAT(value) => Ok(Expression::Literal(Constants::Float(value.clone()))),
-        Token::T_DOUBLE_QUOTE => {
-            let value = match tokens.consume()? {
-                Token::T_STRINGLIT(s) => s.clone(),
-                _ => return Err(Errors::ExpectedStringLit),
+    let kind = match current {
+        Token::T_CONST_INT(value) => {
+            // 2^63 only exists as -9223372036854775808, parse_unary takes that one
+            let Ok(value) = i64::try_from(value) else {
+                return Err(Errors::IntegerTooLarge(value, start));
             };
-            tokens.seek_if(Token::T_DOUBLE_QUOTE)?;
-            Ok(Expression::Literal(Constants::Str(value.clone())))
+            tokens.consume()?;
+            ExpressionKind::Literal(Constants::Int(value))
+        }
+        Token::T_CONST_FLOAT(value) => {
+            tokens.consume()?;
+            ExpressionKind::Literal(Constants::Float(value))
+        }
+        Token::T_STRINGLIT(value) => {
+            tokens.consume()?;
+            ExpressionKind::Literal(Constants::Str(value))
+        }
+        Token::T_CONST_BOOL(value) => {
+            tokens.consume()?;
+            ExpressionKind::Literal(Constants::Bool(value))
         }
-        Token::T_CONST_BOOL(value) => Ok(Expression::Literal(Constants::Bool(value.clone()))),
         Token::T_ROUND_BRACKET_OPEN => {
+            tokens.consume()?;
             let expr = parse_expression(tokens)?;
             tokens.seek_if(Token::T_ROUND_BRACKET_CLOSE)?;
-            Ok(expr)
+            // keep the parentheses in the span so errors point at the whole group
+            expr.kind
+        }
+        Token::T_SQUARE_BRACKET_OPEN => {
+            tokens.consume()?;
+            let mut elements = Vec::new();
+            while !matches!(tokens.peek_curr(), Some(Token::T_SQUARE_BRACKET_CLOSE)) {
+                elements.push(parse_expression(tokens)?);
+                if let Some(Token::T_COMMA) = tokens.peek_curr() {
+                    tokens.consume()?;
+                } else {
+                    break;
+                }
+            }
+            tokens.seek_if(Token::T_SQUARE_BRACKET_CLOSE)?;
+            ExpressionKind::ArrayLiteral(elements)
+        }
+
+        other => return Err(Errors::ExpectedExpression(other, start)),
+    };
+
+    Ok(Expression {
+        kind,
+        span: tokens.span_from(start),
+    })
+}
+
+// `Point { x: 1, y: 2.0 }`, fields in any order
+fn parse_struct_literal(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
+    let start = tokens.curr_span();
+    let identifier = match tokens.consume()? {
+        Token::T_IDENTIFIER(name) => name.clone(),
+        other => return Err(Errors::ExpectedIdentifier(other.clone(), start)),
+    };
+    tokens.seek_if(Token::T_CURLY_BRACKET_OPEN)?;
+
+    let mut fields = Vec::new();
+    while !matches!(tokens.peek_curr(), Some(Token::T_CURLY_BRACKET_CLOSE)) {
+        let span = tokens.curr_span();
+        let field = match tokens.consume()? {
+            Token::T_IDENTIFIER(name) => name.clone(),
+            other => return Err(Errors::ExpectedIdentifier(other.clone(), span)),
+        };
+        tokens.seek_if(Token::T_COLON)?;
+        fields.push(FieldInit {
+            identifier: field,
+            value: parse_expression(tokens)?,
+            span,
+        });
+        if let Some(Token::T_COMMA) = tokens.peek_curr() {
+            tokens.consume()?;
+        } else {
+            break;
         }
+    }
+    tokens.seek_if(Token::T_CURLY_BRACKET_CLOSE)?;
+
+    Ok(Expression {
+        kind: ExpressionKind::StructLiteral {
+            identifier,
+            fields,
+            identifier_span: start,
+        },
+        span: tokens.span_from(start),
+    })
+}
+
+// type like int, float, bool, string or a struct's name, each optionally followed
+// by array sizes. Sizes are in indexing order, `int[2][3]` is two `int[3]`s.
+fn parse_type(tokens: &mut TokenIterator) -> Result<TypeName, Errors> {
+    let start = tokens.curr_span();
+    let base = match tokens.consume()? {
+        Token::T_INT => TypeName::Base(Token::T_INT),
+        Token::T_STRING => TypeName::Base(Token::T_STRING),
+        Token::T_FLOAT => TypeName::Base(Token::T_FLOAT),
+        Token::T_BOOL => TypeName::Base(Token::T_BOOL),
+        Token::T_IDENTIFIER(name) => TypeName::Struct(name.clone(), start),
+        other => return Err(Errors::ExpectedTypeToken(other.clone(), start)),
+    };
 
-        other => Err(Errors::UnexpectedToken(other.clone())),
+    let mut sizes = Vec::new();
+    while let Some(Token::T_SQUARE_BRACKET_OPEN) = tokens.peek_curr() {
+        tokens.consume()?;
+        let size = match tokens.peek_curr() {
+            Some(Token::T_SQUARE_BRACKET_CLOSE) => None,
+            Some(Token::T_CONST_INT(size)) => {
+                // the lexer never makes negative ints
+                let size = match usize::try_from(*size) {
+                    Ok(len) if len <= MAX_ARRAY_LEN => len,
+                    _ => return Err(Errors::ArrayTooLarge(*size, tokens.curr_span())),
+                };
+                tokens.consume()?;
+                Some(size)
+            }
+            Some(other) => {
+                return Err(Errors::ExpectedArraySize(other.clone(), tokens.curr_span()));
+            }
+            None => return Err(Errors::UnexpectedEOF(tokens.curr_span())),
+        };
+        tokens.seek_if(Token::T_SQUARE_BRACKET_CLOSE)?;
+        sizes.push(size);
     }
+
+    Ok(sizes
+        .into_iter()
+        .rev()
+        .fold(base, |element, size| TypeName::Array {
+            element: Box::new(element),
+            size,
+        }))
 }
 
 fn parse_variable_declaration(tokens: &mut TokenIterator) -> Result<VariableDeclaration, Errors> {
-    // type like int, float, bool, string
-    let var_type = match tokens.consume()? {
-        Token::T_INT => Token::T_INT,
-        Token::T_STRING => Token::T_STRING,
-        Token::T_FLOAT => Token::T_FLOAT,
-        Token::T_BOOL => Token::T_BOOL,
-        other => return Err(Errors::ExpectedTypeToken(other.clone())),
-    };
+    let start = tokens.curr_span();
+
+    let var_type = parse_type(tokens)?;
 
     // identifier the name of the variable
+    let identifier_span = tokens.curr_span();
     let var_identifier = match tokens.consume()? {
         Token::T_IDENTIFIER(name) => name.clone(), // matching and if it matched copy (clone) the name
-        other => return Err(Errors::ExpectedIdentifier(other.clone())), // no match: send error
+        other => return Err(Errors::ExpectedIdentifier(other.clone(), identifier_span)), // no match: send error
     };
 
-    // '=' token
-    tokens.seek_if(Token::T_ASSIGNMENT_OPR)?;
-
-    let expression = parse_expression(tokens)?;
+    // '= expression' is optional, `int x;` declares x without a value
+    let expression = if let Some(Token::T_SEMICOLON) = tokens.peek_curr() {
+        None
+    } else {
+        tokens.seek_if(Token::T_ASSIGNMENT_OPR)?;
+        Some(parse_expression(tokens)?)
+    };
 
     // ';' token
     tokens.seek_if(Token::T_SEMICOLON)?;
 
     Ok(VariableDeclaration {
-        type_token: var_type,
+        var_type,
         identifier: var_identifier,
-        expression: expression,
+        expression,
+        doc: None, // the root loop fills it in for globals
+        identifier_span,
+        span: tokens.span_from(start),
     })
 }
 
 fn parse_parameter(tokens: &mut TokenIterator) -> Result<Parameter, Errors> {
-    let param_type = match tokens.consume()? {
-        Token::T_INT => Token::T_INT,
-        Token::T_STRING => Token::T_STRING,
-        Token::T_FLOAT => Token::T_FLOAT,
-        Token::T_BOOL => Token::T_BOOL,
-        other => return Err(Errors::ExpectedTypeToken(other.clone())),
-    };
+    let start = tokens.curr_span();
+    let param_type = parse_type(tokens)?;
 
     // identifier the name of the variable
     let param_identifier = match tokens.consume()? {
         Token::T_IDENTIFIER(name) => name.clone(),
-        other => return Err(Errors::ExpectedIdentifier(other.clone())),
+        other => {
+            return Err(Errors::ExpectedIdentifier(
+                other.clone(),
+                tokens.prev_span(),
+            ));
+        }
     };
 
     Ok(Parameter {
-        param_type: param_type,
+        param_type,
         identifier: param_identifier,
+        span: tokens.span_from(start),
     })
 }
 
@@ -394,42 +511,30 @@ fn parse_parameter_list(tokens: &mut TokenIterator) -> Result<Vec<Parameter>, Er
 }
 
 fn parse_function_statement(tokens: &mut TokenIterator) -> Result<FunctionStatement, Errors> {
+    let start = tokens.curr_span();
     tokens.seek_if(Token::T_FUNCTION)?;
 
-    let (return_type, func_name) = match tokens.peek_curr() {
-        Some(Token::T_INT) | Some(Token::T_STRING) | Some(Token::T_FLOAT) | Some(Token::T_BOOL) => {
-            // Explicit return type provided
-            let ret_type = match tokens.consume()? {
-                Token::T_INT => Token::T_INT,
-                Token::T_STRING => Token::T_STRING,
-                Token::T_FLOAT => Token::T_FLOAT,
-                Token::T_BOOL => Token::T_BOOL,
-                other => return Err(Errors::ExpectedTypeToken(other.clone())),
-            };
-
-            // function name
-            let name = match tokens.consume()? {
-                Token::T_IDENTIFIER(name) => name.clone(),
-                other => return Err(Errors::ExpectedIdentifier(other.clone())),
-            };
-
-            (ret_type, name)
+    // the return type can be left out, `fn main()` is void. A name right in front
+    // of '(' is the function's, anything else starts with the type
+    let return_type = match tokens.peek_curr() {
+        Some(Token::T_VOID) => {
+            tokens.consume()?;
+            TypeName::Base(Token::T_VOID)
         }
-        Some(Token::T_IDENTIFIER(_)) => {
-            let name = match tokens.consume()? {
-                Token::T_IDENTIFIER(name) => name.clone(),
-                other => return Err(Errors::ExpectedIdentifier(other.clone())),
-            };
-
-            (Token::T_VOID, name) // Default to void
-        }
-        other => {
-            // Handle the Option properly
-            match other {
-                Some(token) => return Err(Errors::ExpectedIdentifier(token.clone())),
-                None => return Err(Errors::UnexpectedEOF),
-            }
+        Some(Token::T_IDENTIFIER(_))
+            if matches!(tokens.peek_next(), Some(Token::T_ROUND_BRACKET_OPEN)) =>
+        {
+            TypeName::Base(Token::T_VOID) // Default to void
         }
+        Some(_) => parse_type(tokens)?,
+        None => return Err(Errors::UnexpectedEOF(tokens.curr_span())),
+    };
+
+    // function name
+    let identifier_span = tokens.curr_span();
+    let func_name = match tokens.consume()? {
+        Token::T_IDENTIFIER(name) => name.clone(),
+        other => return Err(Errors::ExpectedIdentifier(other.clone(), identifier_span)),
     };
 
     // opening parenthesis
@@ -454,60 +559,161 @@ fn parse_function_statement(tokens: &mut TokenIterator) -> Result<FunctionStatem
         identifier: func_name,
         parameters,
         block,
+        doc: None, // the root loop fills it in
+        identifier_span,
+        span: tokens.span_from(start),
+    })
+}
+
+// struct Name { type field; ... }
+fn parse_struct_declaration(tokens: &mut TokenIterator) -> Result<StructDeclaration, Errors> {
+    let start = tokens.curr_span();
+    tokens.seek_if(Token::T_STRUCT)?;
+
+    let identifier_span = tokens.curr_span();
+    let identifier = match tokens.consume()? {
+        Token::T_IDENTIFIER(name) => name.clone(),
+        other => return Err(Errors::ExpectedIdentifier(other.clone(), identifier_span)),
+    };
+
+    tokens.seek_if(Token::T_CURLY_BRACKET_OPEN)?;
+    let mut fields = Vec::new();
+    while !matches!(
+        tokens.peek_curr(),
+        Some(Token::T_CURLY_BRACKET_CLOSE) | None
+    ) {
+        let field_start = tokens.curr_span();
+        let field_type = parse_type(tokens)?;
+        let field = match tokens.consume()? {
+            Token::T_IDENTIFIER(name) => name.clone(),
+            other => {
+                return Err(Errors::ExpectedIdentifier(
+                    other.clone(),
+                    tokens.prev_span(),
+                ));
+            }
+        };
+        let span = tokens.span_from(field_start);
+        tokens.seek_if(Token::T_SEMICOLON)?;
+        fields.push(StructField {
+            field_type,
+            identifier: field,
+            span,
+        });
+    }
+    tokens.seek_if(Token::T_CURLY_BRACKET_CLOSE)?;
+
+    Ok(StructDeclaration {
+        identifier,
+        fields,
+        identifier_span,
+        span: tokens.span_from(start),
     })
 }
 
+// Expects the opening '{' to be consumed already, stops in front of the closing '}'
 fn parse_block(tokens: &mut TokenIterator) -> Result<Block, Errors> {
+    let open_span = tokens.prev_span();
     let mut statements = Vec::new();
 
     while let Some(token) = tokens.peek_curr() {
         match token {
-            Token::T_INT | Token::T_FLOAT | Token::T_BOOL | Token::T_STRING => {
-                let var_decl = parse_variable_declaration(tokens)?;
-                statements.push(Statement::VarDecl(var_decl));
-            }
-            Token::T_IF => {
-                let if_stmt = parse_if_statement(tokens)?;
-                statements.push(Statement::If(if_stmt));
-            }
-            Token::T_FOR => {
-                let for_stmt = parse_for_statement(tokens)?;
-                statements.push(Statement::For(for_stmt));
-            }
-            Token::T_WHILE => {
-                let while_stmt = while_loop_parser(tokens)?;
-                statements.push(Statement::While(while_stmt));
-            }
-            Token::T_RETURN => {
-                let ret_stmt = parse_return_statement(tokens)?;
-                statements.push(Statement::Return(ret_stmt));
-            }
-            Token::T_BREAK => {
-                tokens.consume()?; // consume 'break'
-                tokens.seek_if(Token::T_SEMICOLON)?; // consume ';'
-                statements.push(Statement::Break);
-            }
-            Token::T_CONTINUE => {
-                tokens.consume()?; // consume 'continue'
-                tokens.seek_if(Token::T_SEMICOLON)?; // consume ';'
-                statements.push(Statement::Continue);
-            }
             Token::T_CURLY_BRACKET_CLOSE => break, // End of block
-            _ => {
-                // Try parsing as an expression statement (e.g., function call)
-                let expr = parse_expression(tokens)?;
+            // 'fn' cannot appear inside a block, the closing '}' is missing so let the caller report it
+            Token::T_FUNCTION => break,
+            _ => {}
+        }
 
-                // check semicolon after expr
-                // not checked in parse_expression because expression can be in middle
-                // of if or while etc where semicolon is not needed
-                tokens.seek_if(Token::T_SEMICOLON)?;
+        match parse_statement(tokens) {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                // report and skip to the next statement so later errors are found too
+                tokens.report(e.to_diagnostic());
+                tokens.synchronize();
+            }
+        }
+    }
 
-                statements.push(Statement::Expr(expr));
+    Ok(Block {
+        statements,
+        span: open_span.to(tokens.curr_span()),
+    })
+}
+
+// Whether the tokens ahead declare a variable. A struct's name starts a type just
+// like `int` does, `Point p` and `Point[2] ps` are declarations while `p[0] = q`
+// is not: only sizes can be inside the brackets of a type and a name follows them.
+fn at_declaration(tokens: &TokenIterator) -> bool {
+    match tokens.peek_curr() {
+        Some(Token::T_INT | Token::T_FLOAT | Token::T_BOOL | Token::T_STRING) => true,
+        Some(Token::T_IDENTIFIER(_)) => {
+            let mut offset = 1;
+            while let Some(Token::T_SQUARE_BRACKET_OPEN) = tokens.peek_at(offset) {
+                offset += 1;
+                if let Some(Token::T_CONST_INT(_)) = tokens.peek_at(offset) {
+                    offset += 1;
+                }
+                if tokens.peek_at(offset) != Some(&Token::T_SQUARE_BRACKET_CLOSE) {
+                    return false;
+                }
+                offset += 1;
             }
+            matches!(tokens.peek_at(offset), Some(Token::T_IDENTIFIER(_)))
         }
+        _ => false,
     }
+}
+
+fn parse_statement(tokens: &mut TokenIterator) -> Result<Statement, Errors> {
+    let start = tokens.curr_span();
+    let kind = match tokens.peek_curr() {
+        _ if at_declaration(tokens) => {
+            let var_decl = parse_variable_declaration(tokens)?;
+            StatementKind::VarDecl(var_decl)
+        }
+        Some(Token::T_IF) => {
+            let if_stmt = parse_if_statement(tokens)?;
+            StatementKind::If(if_stmt)
+        }
+        Some(Token::T_FOR) => {
+            let for_stmt = parse_for_statement(tokens)?;
+            StatementKind::For(for_stmt)
+        }
+        Some(Token::T_WHILE) => {
+            let while_stmt = while_loop_parser(tokens)?;
+            StatementKind::While(while_stmt)
+        }
+        Some(Token::T_RETURN) => {
+            let ret_stmt = parse_return_statement(tokens)?;
+            StatementKind::Return(ret_stmt)
+        }
+        Some(Token::T_BREAK) => {
+            tokens.consume()?; // consume 'break'
+            tokens.seek_if(Token::T_SEMICOLON)?; // consume ';'
+            StatementKind::Break
+        }
+        Some(Token::T_CONTINUE) => {
+            tokens.consume()?; // consume 'continue'
+            tokens.seek_if(Token::T_SEMICOLON)?; // consume ';'
+            StatementKind::Continue
+        }
+        _ => {
+            // Try parsing as an expression statement (e.g., function call)
+            let expr = parse_expression(tokens)?;
 
-    Ok(Block { statements })
+            // check semicolon after expr
+            // not checked in parse_expression because expression can be in middle
+            // of if or while etc where semicolon is not needed
+            tokens.seek_if(Token::T_SEMICOLON)?;
+
+            StatementKind::Expr(expr)
+        }
+    };
+
+    Ok(Statement {
+        kind,
+        span: tokens.span_from(start),
+    })
 }
 
 fn parse_if_statement(tokens: &mut TokenIterator) -> Result<IfStatement, Errors> {
@@ -571,6 +777,7 @@ fn parse_elif_blocks(tokens: &mut TokenIterator) -> Result<Vec<ElifBlock>, Error
     let mut elif_blocks = Vec::new();
 
     while let Some(Token::T_ELSE_IF) = tokens.peek_curr() {
+        let start = tokens.curr_span();
         tokens.consume()?; // consume ELIF
 
         // opening bracket
@@ -593,6 +800,7 @@ fn parse_elif_blocks(tokens: &mut TokenIterator) -> Result<Vec<ElifBlock>, Error
         elif_blocks.push(ElifBlock {
             condition: elif_condition,
             block: elif_block,
+            span: tokens.span_from(start),
         });
     }
 
@@ -629,15 +837,14 @@ fn parse_for_statement(tokens: &mut TokenIterator) -> Result<ForStatement, Error
             tokens.consume()?; // consume semicolon
             None // empty initialization
         } // Otherwise parse variable declaration.
-        Some(Token::T_INT) | Some(Token::T_FLOAT) | Some(Token::T_BOOL) | Some(Token::T_STRING) => {
+        _ if at_declaration(tokens) => {
             let var_decl = parse_variable_declaration(tokens)?;
             Some(var_decl)
         }
-        other => {
-            return Err(Errors::UnexpectedToken(
-                other.cloned().unwrap_or(Token::T_SEMICOLON),
-            ));
+        Some(other) => {
+            return Err(Errors::UnexpectedToken(other.clone(), tokens.curr_span()));
         }
+        None => return Err(Errors::UnexpectedEOF(tokens.curr_span())),
     };
 
     // Parse loop_condition:
@@ -652,7 +859,7 @@ fn parse_for_statement(tokens: &mut TokenIterator) -> Result<ForStatement, Error
     };
 
     let update = if let Some(Token::T_ROUND_BRACKET_CLOSE) = tokens.peek_curr() {
-        None //no update. 
+        None //no update.
     } else {
         Some(parse_expression(tokens)?)
     };
@@ -674,11 +881,16 @@ fn parse_for_statement(tokens: &mut TokenIterator) -> Result<ForStatement, Error
     })
 }
 
-fn parse_return_statement(tokens: &mut TokenIterator) -> Result<Expression, Errors> {
+// `return expr;`, or a bare `return;` in a void function
+fn parse_return_statement(tokens: &mut TokenIterator) -> Result<Option<Expression>, Errors> {
     tokens.seek_if(Token::T_RETURN)?;
+    if let Some(Token::T_SEMICOLON) = tokens.peek_curr() {
+        tokens.consume()?;
+        return Ok(None);
+    }
     let expr = parse_expression(tokens)?;
     tokens.seek_if(Token::T_SEMICOLON)?;
-    Ok(expr)
+    Ok(Some(expr))
 }
 
 pub fn parse_function_call_arguments(
@@ -707,9 +919,10 @@ pub fn parse_function_call_arguments(
 }
 
 pub fn parse_function_call(tokens: &mut TokenIterator) -> Result<FunctionCallStatement, Errors> {
+    let identifier_span = tokens.curr_span();
     let func_name = match tokens.consume()? {
         Token::T_IDENTIFIER(name) => name.clone(),
-        other => return Err(Errors::ExpectedIdentifier(other.clone())),
+        other => return Err(Errors::ExpectedIdentifier(other.clone(), identifier_span)),
     };
 
     tokens.seek_if(Token::T_ROUND_BRACKET_OPEN)?;
@@ -721,37 +934,57 @@ pub fn parse_function_call(tokens: &mut TokenIterator) -> Result<FunctionCallSta
     Ok(FunctionCallStatement {
         identifier: func_name,
         args,
+        identifier_span,
     })
 }
 
-pub fn parser(tokens: Vec<Token>) -> RootList {
+pub fn parser(tokens: Vec<SpannedToken>) -> Result<RootList, Vec<Diagnostic>> {
     let mut token_iterator = TokenIterator::new(tokens);
     let mut roots: RootList = vec![];
 
     while !token_iterator.is_at_end() {
+        let doc = token_iterator.doc_comment();
         let current = token_iterator.peek_curr();
-        match current {
+        let result = match current {
             Some(Token::T_INT)
             | Some(Token::T_FLOAT)
             | Some(Token::T_BOOL)
-            | Some(Token::T_STRING) => match parse_variable_declaration(&mut token_iterator) {
-                Ok(var_decl) => roots.push(Root::Var(var_decl)),
-                Err(e) => {
-                    panic!("Error parsing variable declaration: {:?}", e);
-                }
-            },
-            Some(Token::T_FUNCTION) => match parse_function_statement(&mut token_iterator) {
-                Ok(func_stmt) => roots.push(Root::Func(func_stmt)),
-                Err(e) => {
-                    panic!("Error parsing function statement: {:?}", e);
-                }
-            },
+            | Some(Token::T_STRING)
+            | Some(Token::T_IDENTIFIER(_)) => parse_variable_declaration(&mut token_iterator)
+                .map(|var_decl| roots.push(Root::Var(VariableDeclaration { doc, ..var_decl }))),
+            Some(Token::T_FUNCTION) => parse_function_statement(&mut token_iterator)
+                .map(|func_stmt| roots.push(Root::Func(FunctionStatement { doc, ..func_stmt }))),
+            Some(Token::T_STRUCT) => parse_struct_declaration(&mut token_iterator)
+                .map(|struct_decl| roots.push(Root::Struct(struct_decl))),
             Some(other) => {
-                panic!("Unexpected token: {:?}", other);
+                let error = Errors::UnexpectedToken(other.clone(), token_iterator.curr_span());
+                token_iterator.report(
+                    error
+                        .to_diagnostic()
+                        .with_label("expected 'fn', 'struct' or a global variable declaration"),
+                );
+                token_iterator.consume().ok();
+                token_iterator.synchronize();
+                Ok(())
             }
             None => break,
+        };
+
+        if let Err(e) = result {
+            token_iterator.report(e.to_diagnostic());
+            token_iterator.synchronize();
+
+            // synchronize stops in front of an unmatched '}', at the top level there is no block to close
+            if let Some(Token::T_CURLY_BRACKET_CLOSE) = token_iterator.peek_curr() {
+                token_iterator.consume().ok();
+            }
         }
     }
 
-    return roots;
+    let diagnostics = token_iterator.take_diagnostics();
+    if diagnostics.is_empty() {
+        Ok(roots)
+    } else {
+        Err(diagnostics)
+    }
 }
@@ -1,52 +1,178 @@
-use crate::lexer::tokens::Token;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
+use crate::lexer::tokens::{SpannedToken, Token};
 use crate::parser::errors::Errors;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct TokenIterator {
     position: usize,
-    stream: Vec<Token>,
+    stream: Vec<SpannedToken>,
+    docs: HashMap<usize, String>, // `##` lines, by the position of the token below them
+    diagnostics: Vec<Diagnostic>, // syntax errors we recovered from
 }
 
 impl TokenIterator {
     pub fn peek_curr(&self) -> Option<&Token> {
-        self.stream.get(self.position)
+        self.stream.get(self.position).map(|t| &t.token)
     }
 
     pub fn peek_next(&self) -> Option<&Token> {
-        self.stream.get(self.position + 1)
+        self.peek_at(1)
+    }
+
+    // `offset` tokens past the current one
+    pub fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.stream.get(self.position + offset).map(|t| &t.token)
+    }
+
+    // Span of the current token, or an empty span just past the last token at EOF
+    pub fn curr_span(&self) -> Span {
+        match self.stream.get(self.position) {
+            Some(t) => t.span,
+            None => self.eof_span(),
+        }
+    }
+
+    // Span of the most recently consumed token
+    pub fn prev_span(&self) -> Span {
+        match self
+            .position
+            .checked_sub(1)
+            .and_then(|p| self.stream.get(p))
+        {
+            Some(t) => t.span,
+            None => self.curr_span(),
+        }
+    }
+
+    // Span from `start` up to the end of the most recently consumed token
+    pub fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span())
+    }
+
+    fn eof_span(&self) -> Span {
+        match self.stream.last() {
+            Some(t) => Span::new(
+                t.span.end,
+                t.span.end,
+                t.span.line,
+                t.span.col + (t.span.end - t.span.start),
+            ),
+            None => Span::new(0, 0, 1, 1),
+        }
     }
 
     pub fn seek_if(&mut self, expected: Token) -> Result<(), Errors> {
+        let span = self.curr_span();
         match self.peek_curr() {
             Some(token) => {
                 if *token == expected {
                     self.position += 1;
                     Ok(())
                 } else {
-                    Err(Errors::UnexpectedToken(token.clone()))
+                    Err(Errors::ExpectedToken(expected, token.clone(), span))
                 }
             }
-            None => Err(Errors::UnexpectedEOF),
+            None => Err(Errors::UnexpectedEOF(span)),
         }
     }
 
     pub fn consume(&mut self) -> Result<&Token, Errors> {
+        let span = self.curr_span();
         let token = self
             .stream
             .get(self.position)
-            .ok_or(Errors::UnexpectedEOF)?;
+            .ok_or(Errors::UnexpectedEOF(span))?;
         self.position += 1;
-        Ok(token)
+        Ok(&token.token)
+    }
+
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    // Panic-mode recovery: skip tokens after a syntax error until parsing can resume.
+    // Stops just past a ';' or past a '{ ... }' group that was skipped whole,
+    // or right before a '}' that closes the enclosing block or an 'fn' keyword.
+    pub fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        while let Some(token) = self.peek_curr() {
+            match token {
+                Token::T_FUNCTION => return,
+                Token::T_SEMICOLON if depth == 0 => {
+                    self.position += 1;
+                    return;
+                }
+                Token::T_CURLY_BRACKET_OPEN => depth += 1,
+                Token::T_CURLY_BRACKET_CLOSE => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        self.position += 1;
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.position += 1;
+        }
+    }
+
+    // The doc comment right above the current token. Only roots ask for it, a doc
+    // comment anywhere else is read like any other comment
+    pub fn doc_comment(&self) -> Option<String> {
+        self.docs.get(&self.position).cloned()
     }
 
     pub fn is_at_end(&self) -> bool {
         self.position >= self.stream.len()
     }
 
-    pub fn new(stream: Vec<Token>) -> Self {
+    // Doc comments are taken out of the stream, consecutive lines become one text.
+    // They only document the token on the line right below them: a blank line in
+    // between drops them, and so does code before them on the same line
+    pub fn new(stream: Vec<SpannedToken>) -> Self {
+        let mut tokens: Vec<SpannedToken> = Vec::new();
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut lines: Vec<String> = Vec::new();
+        let mut last_doc_line = 0;
+        for token in stream {
+            let line = token.span.line;
+            let follows_docs = !lines.is_empty() && last_doc_line + 1 == line;
+            match token.token {
+                Token::T_DOC_COMMENT(text) => {
+                    if !follows_docs {
+                        lines.clear();
+                    }
+                    let after_code = tokens.last().is_some_and(|t| t.span.line == line);
+                    if !after_code {
+                        lines.push(text);
+                        last_doc_line = line;
+                    }
+                }
+                _ => {
+                    if follows_docs {
+                        docs.insert(tokens.len(), lines.join("\n"));
+                    }
+                    lines.clear();
+                    tokens.push(token);
+                }
+            }
+        }
+
         TokenIterator {
             position: 0,
-            stream,
+            stream: tokens,
+            docs,
+            diagnostics: Vec::new(),
         }
     }
 }
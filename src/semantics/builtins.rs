@@ -0,0 +1,12 @@
+// Functions every program can call without declaring them.
+// A user defined function with the same name hides the builtin.
+
+// print(a, b, ...) writes its arguments separated by spaces and a newline, any types allowed
+pub const PRINT: &str = "print";
+
+// len(array) is the number of elements in an array, as an int
+pub const LEN: &str = "len";
+
+pub fn is_builtin(name: &str) -> bool {
+    name == PRINT || name == LEN
+}
@@ -0,0 +1,382 @@
+use crate::lexer::span::Span;
+use crate::lexer::tokens::Token;
+use crate::parser::enums::Constants;
+use crate::semantics::typed_ast::*;
+use std::collections::HashMap;
+
+// Control flow graph of one function body, built from the typed tree so every
+// node still points back at its source. Blocks hold what runs in order, edges
+// are the ways control can go next:
+//
+//     while (c) { a; }        [entry] -> [cond: c] -> [a] -+
+//     b;                                  |    ^-----------+
+//                                         v
+//                                        [b] -> [end] -> [exit]
+//
+// Conditions that are constant only get the edge that can actually be taken.
+
+pub type BlockId = usize;
+
+// Something that runs inside a block
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    VarDecl(&'a TypedVarDecl),
+    Expr(&'a TypedExpr), // expression statements and for loop updates
+    Cond(&'a TypedExpr), // a branch condition, the block ends right after
+    Return(Option<&'a TypedExpr>, Span),
+}
+
+#[derive(Debug, Default)]
+pub struct Block<'a> {
+    pub nodes: Vec<Node<'a>>,
+    pub succs: Vec<BlockId>,
+}
+
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub blocks: Vec<Block<'a>>,
+    pub entry: BlockId,
+    pub end: BlockId,  // where control falls off the end of the body
+    pub exit: BlockId, // every return and the end lead here
+    starts: HashMap<*const TypedStmt, BlockId>, // block each statement starts in
+}
+
+impl<'a> Cfg<'a> {
+    pub fn build(func: &'a TypedFunction) -> Self {
+        let mut builder = Builder {
+            cfg: Cfg {
+                blocks: Vec::new(),
+                entry: 0,
+                end: 0,
+                exit: 0,
+                starts: HashMap::new(),
+            },
+            current: 0,
+            loops: Vec::new(),
+        };
+        builder.cfg.entry = builder.new_block();
+        builder.cfg.exit = builder.new_block();
+        builder.current = builder.cfg.entry;
+
+        builder.block(&func.body);
+
+        let end = builder.current;
+        builder.edge(end, builder.cfg.exit);
+        builder.cfg.end = end;
+        builder.cfg
+    }
+
+    pub fn start_of(&self, stmt: &TypedStmt) -> Option<BlockId> {
+        self.starts.get(&(stmt as *const TypedStmt)).copied()
+    }
+
+    // Blocks control can get to from the entry
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut pending = vec![self.entry];
+        while let Some(block) = pending.pop() {
+            if seen[block] {
+                continue;
+            }
+            seen[block] = true;
+            pending.extend(&self.blocks[block].succs);
+        }
+        seen
+    }
+}
+
+struct Builder<'a> {
+    cfg: Cfg<'a>,
+    current: BlockId,
+    loops: Vec<(BlockId, BlockId)>, // (continue_target, break_target)
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> BlockId {
+        self.cfg.blocks.push(Block::default());
+        self.cfg.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId) {
+        if !self.cfg.blocks[from].succs.contains(&to) {
+            self.cfg.blocks[from].succs.push(to);
+        }
+    }
+
+    fn push(&mut self, node: Node<'a>) {
+        self.cfg.blocks[self.current].nodes.push(node);
+    }
+
+    // After a jump nothing falls through, whatever follows starts in a block
+    // nobody points to
+    fn jump(&mut self, to: BlockId) {
+        self.edge(self.current, to);
+        self.current = self.new_block();
+    }
+
+    fn block(&mut self, block: &'a TypedBlock) {
+        for stmt in &block.statements {
+            self.cfg
+                .starts
+                .insert(stmt as *const TypedStmt, self.current);
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a TypedStmt) {
+        match &stmt.kind {
+            TypedStmtKind::VarDecl(var) => self.push(Node::VarDecl(var)),
+            TypedStmtKind::Expr(expr) => self.push(Node::Expr(expr)),
+            TypedStmtKind::Return(expr) => {
+                self.push(Node::Return(expr.as_ref(), stmt.span));
+                self.jump(self.cfg.exit);
+            }
+            // semantics reports these outside loops, there they don't change the flow
+            TypedStmtKind::Break => {
+                if let Some(&(_, break_target)) = self.loops.last() {
+                    self.jump(break_target);
+                }
+            }
+            TypedStmtKind::Continue => {
+                if let Some(&(continue_target, _)) = self.loops.last() {
+                    self.jump(continue_target);
+                }
+            }
+            TypedStmtKind::If(if_stmt) => self.if_statement(if_stmt),
+            TypedStmtKind::While(while_stmt) => self.while_statement(while_stmt),
+            TypedStmtKind::For(for_stmt) => self.for_statement(for_stmt),
+        }
+    }
+
+    // Ends the current block with `condition` and returns the block taken when
+    // it is true. self.current becomes the block taken when it is false.
+    fn branch(&mut self, condition: &'a TypedExpr) -> BlockId {
+        self.push(Node::Cond(condition));
+        let cond_block = self.current;
+        let (on_true, on_false) = (self.new_block(), self.new_block());
+        let value = const_bool(condition);
+        if value != Some(false) {
+            self.edge(cond_block, on_true);
+        }
+        if value != Some(true) {
+            self.edge(cond_block, on_false);
+        }
+        self.current = on_false;
+        on_true
+    }
+
+    fn if_statement(&mut self, if_stmt: &'a TypedIf) {
+        let after = self.new_block();
+
+        let branches = std::iter::once((&if_stmt.condition, &if_stmt.block)).chain(
+            if_stmt
+                .elif_blocks
+                .iter()
+                .map(|(condition, block)| (condition, block)),
+        );
+        for (condition, block) in branches {
+            let on_true = self.branch(condition);
+            let on_false = self.current;
+            self.current = on_true;
+            self.block(block);
+            self.edge(self.current, after);
+            self.current = on_false;
+        }
+
+        if let Some(block) = &if_stmt.else_block {
+            self.block(block);
+        }
+        self.edge(self.current, after);
+        self.current = after;
+    }
+
+    fn while_statement(&mut self, while_stmt: &'a TypedWhile) {
+        let cond_block = self.new_block();
+        self.edge(self.current, cond_block);
+        self.current = cond_block;
+
+        let body = self.branch(&while_stmt.condition);
+        let after = self.current;
+
+        self.loops.push((cond_block, after));
+        self.current = body;
+        self.block(&while_stmt.block);
+        self.edge(self.current, cond_block);
+        self.loops.pop();
+
+        self.current = after;
+    }
+
+    fn for_statement(&mut self, for_stmt: &'a TypedFor) {
+        if let Some(init) = &for_stmt.init_var {
+            self.push(Node::VarDecl(init));
+        }
+
+        let cond_block = self.new_block();
+        self.edge(self.current, cond_block);
+        self.current = cond_block;
+
+        // no condition loops forever
+        let (body, after) = match &for_stmt.condition {
+            Some(condition) => {
+                let body = self.branch(condition);
+                (body, self.current)
+            }
+            None => {
+                let body = self.new_block();
+                self.edge(cond_block, body);
+                (body, self.new_block())
+            }
+        };
+
+        let update = self.new_block();
+        self.loops.push((update, after));
+        self.current = body;
+        self.block(&for_stmt.block);
+        self.edge(self.current, update);
+        self.loops.pop();
+
+        self.current = update;
+        if let Some(expr) = &for_stmt.update {
+            self.push(Node::Expr(expr));
+        }
+        self.edge(update, cond_block);
+
+        self.current = after;
+    }
+}
+
+// Value of a condition that doesn't depend on anything at runtime
+pub fn const_bool(expr: &TypedExpr) -> Option<bool> {
+    match &expr.kind {
+        TypedExprKind::Literal(Constants::Bool(b)) => Some(*b),
+        TypedExprKind::Unary {
+            operator: Token::T_NOT,
+            operand,
+        } => const_bool(operand).map(|b| !b),
+        TypedExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let (l, r) = (const_bool(left), const_bool(right));
+            match operator {
+                // the side that decides is enough, `false && f()` never calls f
+                Token::T_AND_OPR => match (l, r) {
+                    (Some(false), _) => Some(false),
+                    (Some(true), r) => r,
+                    _ => None,
+                },
+                Token::T_OR_OPR => match (l, r) {
+                    (Some(true), _) => Some(true),
+                    (Some(false), r) => r,
+                    _ => None,
+                },
+                Token::T_EQUALS_OPR => Some(l? == r?),
+                Token::T_NOT_EQUALS_OPR => Some(l? != r?),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// What a node does to variables, in the order it happens
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    Read(SymbolId, Span),
+    // `certain` is false for writes that might not happen, like the right side of &&
+    Write {
+        var: SymbolId,
+        span: Span,
+        certain: bool,
+    },
+    Declare(SymbolId), // a declaration without an initializer, the variable holds nothing yet
+}
+
+pub fn node_events(node: &Node, events: &mut Vec<Event>) {
+    match node {
+        Node::VarDecl(var) => match &var.value {
+            Some(value) => {
+                expr_events(value, true, events);
+                events.push(Event::Write {
+                    var: var.symbol,
+                    span: var.span,
+                    certain: true,
+                });
+            }
+            None => events.push(Event::Declare(var.symbol)),
+        },
+        Node::Expr(expr) | Node::Cond(expr) | Node::Return(Some(expr), _) => {
+            expr_events(expr, true, events)
+        }
+        Node::Return(None, _) => {}
+    }
+}
+
+fn expr_events(expr: &TypedExpr, certain: bool, events: &mut Vec<Event>) {
+    match &expr.kind {
+        TypedExprKind::Literal(_) | TypedExprKind::Invalid => {}
+        TypedExprKind::Variable(id) => events.push(Event::Read(*id, expr.span)),
+        TypedExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            expr_events(left, certain, events);
+            let short_circuits = matches!(operator, Token::T_AND_OPR | Token::T_OR_OPR);
+            expr_events(right, certain && !short_circuits, events);
+        }
+        TypedExprKind::Unary { operand, .. } => expr_events(operand, certain, events),
+        TypedExprKind::Assignment { target, value } => match &target.kind {
+            TypedExprKind::Variable(var) => {
+                expr_events(value, certain, events);
+                events.push(Event::Write {
+                    var: *var,
+                    span: expr.span,
+                    certain,
+                });
+            }
+            // an element or field changes, the rest of the value is still the one read here
+            _ => {
+                expr_events(target, certain, events);
+                expr_events(value, certain, events);
+            }
+        },
+        // the old value is read before the new one is written
+        TypedExprKind::CompoundAssignment { target, value, .. } => {
+            expr_events(target, certain, events);
+            expr_events(value, certain, events);
+            if let TypedExprKind::Variable(var) = target.kind {
+                events.push(Event::Write {
+                    var,
+                    span: expr.span,
+                    certain,
+                });
+            }
+        }
+        TypedExprKind::Index { array, index } => {
+            expr_events(array, certain, events);
+            expr_events(index, certain, events);
+        }
+        TypedExprKind::ArrayLiteral(elements) => {
+            for element in elements {
+                expr_events(element, certain, events);
+            }
+        }
+        TypedExprKind::Field { object, .. } => expr_events(object, certain, events),
+        TypedExprKind::StructLiteral(fields) => {
+            for (_, value) in fields {
+                expr_events(value, certain, events);
+            }
+        }
+        TypedExprKind::Call { args, .. } => {
+            for arg in args {
+                expr_events(arg, certain, events);
+            }
+        }
+        TypedExprKind::IntToFloat(inner) | TypedExprKind::FloatToInt(inner) => {
+            expr_events(inner, certain, events)
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
+use crate::semantics::cfg::{Cfg, Event, Node, node_events};
+use crate::semantics::semantic_analysis::{Symbol, Type};
+use crate::semantics::typed_ast::*;
+use std::collections::HashSet;
+
+// Checks that need to know where control can go: a non-void function must not be
+// able to reach the end of its body, a variable declared without a value must be
+// assigned on every path before it is read, and code nothing can reach gets a warning.
+
+pub fn check_function(func: &TypedFunction, symbols: &[Symbol]) -> Vec<Diagnostic> {
+    let cfg = Cfg::build(func);
+    let reachable = cfg.reachable();
+    let mut diagnostics = Vec::new();
+    let name = &symbols[func.symbol].name;
+
+    if !matches!(func.return_type, Type::Void | Type::Unknown) && reachable[cfg.end] {
+        diagnostics.push(
+            Diagnostic::error(
+                codes::MISSING_RETURN,
+                format!(
+                    "Not all paths in function '{}' return a value of type '{}'",
+                    name, func.return_type
+                ),
+                func.span,
+            )
+            .with_label("can reach the end of its body without returning")
+            .with_note("every path through the body must end in a 'return'"),
+        );
+    }
+
+    for (var, span) in unassigned_reads(&cfg, &reachable, symbols) {
+        let symbol = &symbols[var];
+        diagnostics.push(
+            Diagnostic::error(
+                codes::USED_BEFORE_ASSIGNED,
+                format!("Variable '{}' is used before it is assigned", symbol.name),
+                span,
+            )
+            .with_label("not assigned on every path that gets here")
+            .with_secondary(symbol.span, "declared here without a value"),
+        );
+    }
+
+    unreachable_in(&func.body, &cfg, &reachable, &mut diagnostics);
+    diagnostics
+}
+
+// Forward over the cfg, the set of variables assigned on every path into each
+// block. A block's set is what all its predecessors agree on, blocks not visited
+// yet don't take part. Returns the first bad read of each variable.
+fn unassigned_reads(cfg: &Cfg, reachable: &[bool], symbols: &[Symbol]) -> Vec<(SymbolId, Span)> {
+    let events: Vec<Vec<Event>> = cfg
+        .blocks
+        .iter()
+        .map(|block| {
+            let mut events = Vec::new();
+            for node in &block.nodes {
+                node_events(node, &mut events);
+            }
+            events
+        })
+        .collect();
+
+    // only `int x;` style declarations can be read before they hold anything,
+    // arrays declared like that start out zeroed
+    let tracked: HashSet<SymbolId> = cfg
+        .blocks
+        .iter()
+        .flat_map(|block| &block.nodes)
+        .filter_map(|node| match node {
+            Node::VarDecl(var) if var.value.is_none() => Some(var.symbol),
+            _ => None,
+        })
+        .filter(|var| !symbols[*var].value_type().is_compound())
+        .collect();
+    if tracked.is_empty() {
+        return Vec::new();
+    }
+
+    let mut preds = vec![Vec::new(); cfg.blocks.len()];
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        for succ in &block.succs {
+            preds[*succ].push(id);
+        }
+    }
+
+    let assigned_in = |id: usize, assigned_out: &[Option<HashSet<SymbolId>>]| {
+        if id == cfg.entry {
+            return Some(HashSet::new());
+        }
+        let mut sets = preds[id]
+            .iter()
+            .filter_map(|pred| assigned_out[*pred].as_ref());
+        let first = sets.next()?.clone();
+        Some(sets.fold(first, |acc, set| &acc & set))
+    };
+
+    // None until some path gets there, the sets only shrink after that
+    let mut assigned_out: Vec<Option<HashSet<SymbolId>>> = vec![None; cfg.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..cfg.blocks.len() {
+            let Some(mut assigned) = assigned_in(id, &assigned_out) else {
+                continue;
+            };
+            for event in &events[id] {
+                transfer(event, &mut assigned);
+            }
+            if assigned_out[id].as_ref() != Some(&assigned) {
+                assigned_out[id] = Some(assigned);
+                changed = true;
+            }
+        }
+    }
+
+    let mut reads = Vec::new();
+    for id in 0..cfg.blocks.len() {
+        if !reachable[id] {
+            continue;
+        }
+        let Some(mut assigned) = assigned_in(id, &assigned_out) else {
+            continue;
+        };
+        for event in &events[id] {
+            if let Event::Read(var, span) = event
+                && tracked.contains(var)
+                && !assigned.contains(var)
+            {
+                reads.push((*var, *span));
+            }
+            transfer(event, &mut assigned);
+        }
+    }
+
+    reads.sort_by_key(|(_, span)| span.start);
+    let mut reported = HashSet::new();
+    reads.retain(|(var, _)| reported.insert(*var));
+    reads
+}
+
+fn transfer(event: &Event, assigned: &mut HashSet<SymbolId>) {
+    match event {
+        Event::Write {
+            var, certain: true, ..
+        } => {
+            assigned.insert(*var);
+        }
+        // declared again on the next loop iteration, the old value is gone
+        Event::Declare(var) => {
+            assigned.remove(var);
+        }
+        Event::Read(..) | Event::Write { .. } => {}
+    }
+}
+
+// Reports the first statement of a list nothing can reach, together with the rest
+// of the list since that can't be reached either. Reachable statements are
+// searched for dead code nested inside them.
+fn unreachable_in(
+    block: &TypedBlock,
+    cfg: &Cfg,
+    reachable: &[bool],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (i, stmt) in block.statements.iter().enumerate() {
+        let live = cfg.start_of(stmt).is_none_or(|start| reachable[start]);
+        if !live {
+            let last = block.statements.last().map_or(stmt.span, |last| last.span);
+            let mut warning = Diagnostic::warning(
+                codes::UNREACHABLE_CODE,
+                "Unreachable code".to_string(),
+                stmt.span.to(last),
+            )
+            .with_label(if i + 1 == block.statements.len() {
+                "this statement can never run"
+            } else {
+                "these statements can never run"
+            });
+            if i > 0 {
+                let previous = block.statements[i - 1].span;
+                warning = warning.with_secondary(previous, "control never gets past this");
+            }
+            diagnostics.push(warning);
+            return;
+        }
+
+        match &stmt.kind {
+            TypedStmtKind::If(if_stmt) => {
+                unreachable_in(&if_stmt.block, cfg, reachable, diagnostics);
+                for (_, block) in &if_stmt.elif_blocks {
+                    unreachable_in(block, cfg, reachable, diagnostics);
+                }
+                if let Some(block) = &if_stmt.else_block {
+                    unreachable_in(block, cfg, reachable, diagnostics);
+                }
+            }
+            TypedStmtKind::While(while_stmt) => {
+                unreachable_in(&while_stmt.block, cfg, reachable, diagnostics);
+            }
+            TypedStmtKind::For(for_stmt) => {
+                unreachable_in(&for_stmt.block, cfg, reachable, diagnostics);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
+use crate::semantics::typed_ast::{
+    Callee, SymbolId, TypedBlock, TypedExpr, TypedExprKind, TypedProgram, TypedStmtKind,
+};
+use std::collections::{HashMap, HashSet};
+
+// Ordering rules for global variables:
+//
+//  - globals are initialized once, top to bottom, before main runs
+//  - function bodies can use every global, wherever it is declared
+//  - an initializer can only use globals declared above it, and that includes
+//    globals used by any function it calls (directly or through other functions)
+//
+// Names are already resolved to symbols, this only looks at the order.
+
+// Variables a piece of code touches and functions it calls
+#[derive(Default)]
+struct Uses {
+    variables: Vec<(SymbolId, Span)>,
+    calls: Vec<(SymbolId, Span)>,
+}
+
+impl Uses {
+    fn block(&mut self, block: &TypedBlock) {
+        for statement in &block.statements {
+            match &statement.kind {
+                TypedStmtKind::VarDecl(var) => {
+                    if let Some(value) = &var.value {
+                        self.expression(value);
+                    }
+                }
+                TypedStmtKind::Expr(expr) | TypedStmtKind::Return(Some(expr)) => {
+                    self.expression(expr)
+                }
+                TypedStmtKind::Return(None) | TypedStmtKind::Break | TypedStmtKind::Continue => {}
+                TypedStmtKind::If(if_stmt) => {
+                    self.expression(&if_stmt.condition);
+                    self.block(&if_stmt.block);
+                    for (condition, block) in &if_stmt.elif_blocks {
+                        self.expression(condition);
+                        self.block(block);
+                    }
+                    if let Some(block) = &if_stmt.else_block {
+                        self.block(block);
+                    }
+                }
+                TypedStmtKind::While(while_stmt) => {
+                    self.expression(&while_stmt.condition);
+                    self.block(&while_stmt.block);
+                }
+                TypedStmtKind::For(for_stmt) => {
+                    if let Some(init) = &for_stmt.init_var
+                        && let Some(value) = &init.value
+                    {
+                        self.expression(value);
+                    }
+                    if let Some(condition) = &for_stmt.condition {
+                        self.expression(condition);
+                    }
+                    if let Some(update) = &for_stmt.update {
+                        self.expression(update);
+                    }
+                    self.block(&for_stmt.block);
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &TypedExpr) {
+        match &expr.kind {
+            TypedExprKind::Literal(_) | TypedExprKind::Invalid => {}
+            TypedExprKind::Variable(id) => self.variables.push((*id, expr.span)),
+            TypedExprKind::Binary { left, right, .. }
+            | TypedExprKind::Assignment {
+                target: left,
+                value: right,
+            }
+            | TypedExprKind::CompoundAssignment {
+                target: left,
+                value: right,
+                ..
+            }
+            | TypedExprKind::Index {
+                array: left,
+                index: right,
+            } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            TypedExprKind::Unary { operand: inner, .. }
+            | TypedExprKind::Field { object: inner, .. }
+            | TypedExprKind::IntToFloat(inner)
+            | TypedExprKind::FloatToInt(inner) => self.expression(inner),
+            TypedExprKind::Call { callee, args } => {
+                if let Callee::Function(id) = callee {
+                    self.calls.push((*id, expr.span));
+                }
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            TypedExprKind::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            TypedExprKind::StructLiteral(fields) => {
+                for (_, value) in fields {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+}
+
+pub fn check_initialization_order(program: &TypedProgram) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+
+    // position of every global in initialization order, locals have none
+    let positions: HashMap<SymbolId, usize> = program
+        .globals
+        .iter()
+        .enumerate()
+        .map(|(i, var)| (var.symbol, i))
+        .collect();
+    let functions: HashMap<SymbolId, Uses> = program
+        .functions
+        .iter()
+        .map(|func| {
+            let mut uses = Uses::default();
+            uses.block(&func.body);
+            (func.symbol, uses)
+        })
+        .collect();
+    let name = |id: SymbolId| program.symbol(id).name.as_str();
+
+    for (i, var) in program.globals.iter().enumerate() {
+        let mut uses = Uses::default();
+        if let Some(value) = &var.value {
+            uses.expression(value);
+        }
+
+        let not_ready = |id: SymbolId| match positions.get(&id) {
+            Some(position) if *position >= i => Some(program.symbol(id).span),
+            _ => None,
+        };
+
+        // used right in the initializer
+        for (id, span) in &uses.variables {
+            if *id == var.symbol {
+                errors.push(
+                    Diagnostic::error(
+                        codes::GLOBAL_USED_BEFORE_INIT,
+                        format!("global '{}' is used in its own initializer", name(*id)),
+                        *span,
+                    )
+                    .with_label("not initialized yet"),
+                );
+            } else if let Some(declared_at) = not_ready(*id) {
+                errors.push(
+                    used_before_init(name(*id), *span, declared_at)
+                        .with_label("not initialized yet"),
+                );
+            }
+        }
+
+        // used by a function the initializer calls, reported once per global at the call
+        let mut reported = HashSet::new();
+        for (callee, call_span) in &uses.calls {
+            let mut visited = HashSet::new();
+            let mut pending = vec![*callee];
+            while let Some(func) = pending.pop() {
+                if !visited.insert(func) {
+                    continue;
+                }
+                let Some(func_uses) = functions.get(&func) else {
+                    continue;
+                };
+                for (id, span) in &func_uses.variables {
+                    if let Some(declared_at) = not_ready(*id)
+                        && reported.insert(*id)
+                    {
+                        let global = name(*id);
+                        errors.push(
+                            used_before_init(global, *call_span, declared_at)
+                                .with_label(format!("'{}' uses '{}'", name(func), global))
+                                .with_secondary(*span, format!("'{}' is used here", global))
+                                .with_note(format!(
+                                    "'{}' runs while '{}' is being initialized",
+                                    name(*callee),
+                                    name(var.symbol)
+                                )),
+                        );
+                    }
+                }
+                pending.extend(func_uses.calls.iter().map(|(id, _)| *id));
+            }
+        }
+    }
+
+    errors
+}
+
+fn used_before_init(name: &str, span: Span, declared_at: Span) -> Diagnostic {
+    Diagnostic::error(
+        codes::GLOBAL_USED_BEFORE_INIT,
+        format!("global '{}' is used before it is initialized", name),
+        span,
+    )
+    .with_secondary(declared_at, format!("'{}' is declared here", name))
+    .with_note("globals are initialized from top to bottom")
+}
@@ -0,0 +1,463 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::{Diagnostic, Severity};
+use crate::lexer::span::Span;
+use crate::lexer::tokens::Token;
+use crate::parser::enums::Constants;
+use crate::semantics::cfg::{Cfg, Event, node_events};
+use crate::semantics::typed_ast::*;
+use std::collections::{HashMap, HashSet};
+
+// Warnings about programs that are valid but probably not what was meant. Every
+// lint can be allowed, left a warning, or denied (turned into an error).
+// Names starting with '_' are never reported as unused.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedParameter,
+    UnusedFunction,
+    Shadowing,
+    DeadAssignment,
+    BoolComparison,
+    UnreachableCode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 7] = [
+        Lint::UnusedVariable,
+        Lint::UnusedParameter,
+        Lint::UnusedFunction,
+        Lint::Shadowing,
+        Lint::DeadAssignment,
+        Lint::BoolComparison,
+        Lint::UnreachableCode,
+    ];
+
+    // What the lint is called on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedParameter => "unused-parameter",
+            Lint::UnusedFunction => "unused-function",
+            Lint::Shadowing => "shadowing",
+            Lint::DeadAssignment => "dead-assignment",
+            Lint::BoolComparison => "bool-comparison",
+            Lint::UnreachableCode => "unreachable-code",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    pub fn warning(&self, message: String, span: Span) -> Diagnostic {
+        Diagnostic::warning(self.code(), message, span)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => codes::UNUSED_VARIABLE,
+            Lint::UnusedParameter => codes::UNUSED_PARAMETER,
+            Lint::UnusedFunction => codes::UNUSED_FUNCTION,
+            Lint::Shadowing => codes::SHADOWED_VARIABLE,
+            Lint::DeadAssignment => codes::DEAD_ASSIGNMENT,
+            Lint::BoolComparison => codes::BOOL_COMPARISON,
+            Lint::UnreachableCode => codes::UNREACHABLE_CODE,
+        }
+    }
+}
+
+// Level of every lint, all of them warn unless told otherwise
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<Lint, Level>,
+}
+
+impl LintConfig {
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn set_all(&mut self, level: Level) {
+        for lint in Lint::ALL {
+            self.set(lint, level);
+        }
+    }
+
+    // Puts a warning from `lint` at the configured level, None when it is allowed
+    pub fn apply(&self, lint: Lint, warning: Diagnostic) -> Option<Diagnostic> {
+        match self.level(lint) {
+            Level::Allow => None,
+            Level::Warn => Some(warning),
+            Level::Deny => Some(
+                Diagnostic {
+                    severity: Severity::Error,
+                    ..warning
+                }
+                .with_note(format!("'{}' is denied", lint.name())),
+            ),
+        }
+    }
+}
+
+fn is_ignored(name: &str) -> bool {
+    name.starts_with('_')
+}
+
+// Runs every lint that looks at the whole checked program
+pub fn check_program(program: &TypedProgram, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        program,
+        config,
+        reads: HashSet::new(),
+        calls: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+
+    for value in program.globals.iter().filter_map(|var| var.value.as_ref()) {
+        linter.expression(value, None);
+    }
+    for func in &program.functions {
+        linter.block(&func.body, func.symbol);
+    }
+
+    linter.unused_functions();
+    for func in &program.functions {
+        linter.unused_locals(func);
+        linter.dead_assignments(func);
+    }
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    program: &'a TypedProgram,
+    config: &'a LintConfig,
+    reads: HashSet<SymbolId>, // every variable read somewhere
+    calls: HashSet<SymbolId>, // every function called by something other than itself
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn emit(&mut self, lint: Lint, warning: Diagnostic) {
+        self.diagnostics.extend(self.config.apply(lint, warning));
+    }
+
+    fn name(&self, id: SymbolId) -> &'a str {
+        &self.program.symbol(id).name
+    }
+
+    // Collects reads and calls, and looks for comparisons with bool literals on the way
+    fn block(&mut self, block: &TypedBlock, func: SymbolId) {
+        for stmt in &block.statements {
+            match &stmt.kind {
+                TypedStmtKind::VarDecl(TypedVarDecl {
+                    value: Some(value), ..
+                }) => self.expression(value, Some(func)),
+                TypedStmtKind::Expr(expr) | TypedStmtKind::Return(Some(expr)) => {
+                    self.expression(expr, Some(func))
+                }
+                TypedStmtKind::VarDecl(_)
+                | TypedStmtKind::Return(None)
+                | TypedStmtKind::Break
+                | TypedStmtKind::Continue => {}
+                TypedStmtKind::If(if_stmt) => {
+                    self.expression(&if_stmt.condition, Some(func));
+                    self.block(&if_stmt.block, func);
+                    for (condition, block) in &if_stmt.elif_blocks {
+                        self.expression(condition, Some(func));
+                        self.block(block, func);
+                    }
+                    if let Some(block) = &if_stmt.else_block {
+                        self.block(block, func);
+                    }
+                }
+                TypedStmtKind::While(while_stmt) => {
+                    self.expression(&while_stmt.condition, Some(func));
+                    self.block(&while_stmt.block, func);
+                }
+                TypedStmtKind::For(for_stmt) => {
+                    if let Some(init) = &for_stmt.init_var
+                        && let Some(value) = &init.value
+                    {
+                        self.expression(value, Some(func));
+                    }
+                    if let Some(condition) = &for_stmt.condition {
+                        self.expression(condition, Some(func));
+                    }
+                    if let Some(update) = &for_stmt.update {
+                        self.expression(update, Some(func));
+                    }
+                    self.block(&for_stmt.block, func);
+                }
+            }
+        }
+    }
+
+    // `func` is the function the expression is in, None for global initializers
+    fn expression(&mut self, expr: &TypedExpr, func: Option<SymbolId>) {
+        match &expr.kind {
+            TypedExprKind::Literal(_) | TypedExprKind::Invalid => {}
+            TypedExprKind::Variable(id) => {
+                self.reads.insert(*id);
+            }
+            TypedExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                if matches!(operator, Token::T_EQUALS_OPR | Token::T_NOT_EQUALS_OPR) {
+                    self.bool_comparison(left, operator, right, expr.span);
+                }
+                self.expression(left, func);
+                self.expression(right, func);
+            }
+            TypedExprKind::Unary { operand, .. } => self.expression(operand, func),
+            TypedExprKind::Assignment { target, value } => {
+                // storing into an element or field needs the whole value, storing into a
+                // variable doesn't read it
+                if !matches!(target.kind, TypedExprKind::Variable(_)) {
+                    self.expression(target, func);
+                }
+                self.expression(value, func)
+            }
+            TypedExprKind::CompoundAssignment { target, value, .. } => {
+                self.expression(target, func);
+                self.expression(value, func)
+            }
+            TypedExprKind::Index { array, index } => {
+                self.expression(array, func);
+                self.expression(index, func);
+            }
+            TypedExprKind::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.expression(element, func);
+                }
+            }
+            TypedExprKind::Field { object, .. } => self.expression(object, func),
+            TypedExprKind::StructLiteral(fields) => {
+                for (_, value) in fields {
+                    self.expression(value, func);
+                }
+            }
+            TypedExprKind::Call { callee, args } => {
+                if let Callee::Function(id) = callee
+                    && Some(*id) != func
+                {
+                    self.calls.insert(*id);
+                }
+                for arg in args {
+                    self.expression(arg, func);
+                }
+            }
+            TypedExprKind::IntToFloat(inner) | TypedExprKind::FloatToInt(inner) => {
+                self.expression(inner, func)
+            }
+        }
+    }
+
+    // `x == true` is just `x`, `x == false` is `!x`
+    fn bool_comparison(
+        &mut self,
+        left: &TypedExpr,
+        operator: &Token,
+        right: &TypedExpr,
+        span: Span,
+    ) {
+        let (literal, other) = match (&left.kind, &right.kind) {
+            (TypedExprKind::Literal(Constants::Bool(b)), _) => (*b, right),
+            (_, TypedExprKind::Literal(Constants::Bool(b))) => (*b, left),
+            _ => return,
+        };
+        if matches!(other.kind, TypedExprKind::Literal(_)) {
+            return; // `true == false` is a constant, not this mistake
+        }
+        let negate = literal == (*operator == Token::T_NOT_EQUALS_OPR);
+        let suggestion = if negate {
+            "negate the value with '!' instead"
+        } else {
+            "use the value directly"
+        };
+        let lint = Lint::BoolComparison;
+        let message = format!("Comparison of a bool with '{}'", literal);
+        self.emit(lint, lint.warning(message, span).with_label(suggestion));
+    }
+
+    fn unused_functions(&mut self) {
+        let program = self.program;
+        for func in &program.functions {
+            let name = self.name(func.symbol);
+            if name == "main" || is_ignored(name) || self.calls.contains(&func.symbol) {
+                continue;
+            }
+            let lint = Lint::UnusedFunction;
+            let message = format!("Function '{}' is never called", name);
+            self.emit(lint, lint.warning(message, func.span));
+        }
+    }
+
+    fn unused_locals(&mut self, func: &TypedFunction) {
+        for param in &func.params {
+            let name = self.name(*param);
+            if !is_ignored(name) && !self.reads.contains(param) {
+                let lint = Lint::UnusedParameter;
+                let message = format!("Parameter '{}' is never used", name);
+                let warning = lint
+                    .warning(message, self.program.symbol(*param).span)
+                    .with_note(format!("name it '_{}' if it is unused on purpose", name));
+                self.emit(lint, warning);
+            }
+        }
+
+        for local in locals(&func.body) {
+            let name = self.name(local);
+            if !is_ignored(name) && !self.reads.contains(&local) {
+                let lint = Lint::UnusedVariable;
+                let message = format!("Variable '{}' is never read", name);
+                let warning = lint
+                    .warning(message, self.program.symbol(local).span)
+                    .with_note(format!("name it '_{}' if it is unused on purpose", name));
+                self.emit(lint, warning);
+            }
+        }
+    }
+
+    // Liveness over the function's control flow graph: a value written to a local
+    // that no path reads before it is overwritten or the function returns
+    fn dead_assignments(&mut self, func: &TypedFunction) {
+        // variables that are never read at all already got unused-variable
+        let tracked: HashSet<SymbolId> = func
+            .params
+            .iter()
+            .copied()
+            .chain(locals(&func.body))
+            .filter(|id| self.reads.contains(id))
+            .collect();
+        if tracked.is_empty() {
+            return;
+        }
+
+        let cfg = Cfg::build(func);
+        let reachable = cfg.reachable();
+        let events: Vec<Vec<Event>> = cfg
+            .blocks
+            .iter()
+            .map(|block| {
+                let mut events = Vec::new();
+                for node in &block.nodes {
+                    node_events(node, &mut events);
+                }
+                events
+            })
+            .collect();
+
+        // live_in of every block, iterated until nothing changes
+        let mut live_in: Vec<HashSet<SymbolId>> = vec![HashSet::new(); cfg.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in (0..cfg.blocks.len()).rev() {
+                let mut live = live_out(&cfg, &live_in, id);
+                for event in events[id].iter().rev() {
+                    transfer(event, &mut live);
+                }
+                if live != live_in[id] {
+                    live_in[id] = live;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut dead = Vec::new();
+        for id in 0..cfg.blocks.len() {
+            if !reachable[id] {
+                continue; // already warned about as unreachable
+            }
+            let mut live = live_out(&cfg, &live_in, id);
+            for event in events[id].iter().rev() {
+                if let Event::Write { var, span, .. } = event
+                    && tracked.contains(var)
+                    && !live.contains(var)
+                {
+                    dead.push((*var, *span));
+                }
+                transfer(event, &mut live);
+            }
+        }
+
+        dead.sort_by_key(|(_, span)| span.start);
+        for (var, span) in dead {
+            let lint = Lint::DeadAssignment;
+            let message = format!("Value assigned to '{}' is never read", self.name(var));
+            let warning = lint
+                .warning(message, span)
+                .with_note("it is overwritten or goes out of scope before any read");
+            self.emit(lint, warning);
+        }
+    }
+}
+
+// Every local variable a function body declares, for loop variables included
+fn locals(block: &TypedBlock) -> Vec<SymbolId> {
+    let mut found = Vec::new();
+    for stmt in &block.statements {
+        match &stmt.kind {
+            TypedStmtKind::VarDecl(var) => found.push(var.symbol),
+            TypedStmtKind::If(if_stmt) => {
+                found.extend(locals(&if_stmt.block));
+                for (_, block) in &if_stmt.elif_blocks {
+                    found.extend(locals(block));
+                }
+                if let Some(block) = &if_stmt.else_block {
+                    found.extend(locals(block));
+                }
+            }
+            TypedStmtKind::While(while_stmt) => found.extend(locals(&while_stmt.block)),
+            TypedStmtKind::For(for_stmt) => {
+                if let Some(init) = &for_stmt.init_var {
+                    found.push(init.symbol);
+                }
+                found.extend(locals(&for_stmt.block));
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn live_out(cfg: &Cfg, live_in: &[HashSet<SymbolId>], block: usize) -> HashSet<SymbolId> {
+    cfg.blocks[block]
+        .succs
+        .iter()
+        .flat_map(|succ| live_in[*succ].iter().copied())
+        .collect()
+}
+
+// Steps liveness backwards over one event
+fn transfer(event: &Event, live: &mut HashSet<SymbolId>) {
+    match event {
+        Event::Read(var, _) => {
+            live.insert(*var);
+        }
+        Event::Write {
+            var, certain: true, ..
+        } => {
+            live.remove(var);
+        }
+        // whatever it held before can't be read through the new declaration
+        Event::Declare(var) => {
+            live.remove(var);
+        }
+        Event::Write { .. } => {}
+    }
+}
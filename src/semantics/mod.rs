@@ -1 +1,7 @@
+pub mod builtins;
+pub mod cfg;
+pub mod flow;
+pub mod globals;
+pub mod lints;
 pub mod semantic_analysis;
+pub mod typed_ast;
@@ -1,6 +1,13 @@
+use crate::diagnostics::codes;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::span::Span;
 use crate::lexer::tokens::Token;
 use crate::parser::enums::*;
-use std::collections::HashMap;
+use crate::semantics::lints::{Lint, LintConfig};
+use crate::semantics::typed_ast::*;
+use crate::semantics::{builtins, flow, globals, lints};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
@@ -9,33 +16,42 @@ pub enum Type {
     Bool,
     String,
     Void,
-    Unknown, // For error recovery
+    Array(Box<Type>, Option<usize>), // element type and length, None takes any length
+    Struct(String),                  // by name, see ScopeAnalyzer::find_struct
+    Unknown,                         // For error recovery
 }
 
-impl Type {
-    fn from_string(s: &str) -> Self {
-        match s {
-            "int" => Type::Int,
-            "float" => Type::Float,
-            "bool" => Type::Bool,
-            "string" => Type::String,
-            "void" => Type::Void,
-            _ => Type::Unknown,
-        }
-    }
-
-    fn to_string(&self) -> String {
-        match self {
-            Type::Int => "int".to_string(),
-            Type::Float => "float".to_string(),
-            Type::Bool => "bool".to_string(),
-            Type::String => "string".to_string(),
-            Type::Void => "void".to_string(),
-            Type::Unknown => "unknown".to_string(),
-        }
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::String => "string",
+            Type::Void => "void",
+            Type::Array(..) => {
+                // sizes outermost first, the way they are written
+                let mut sizes = String::new();
+                let mut ty = self;
+                while let Type::Array(element, size) = ty {
+                    match size {
+                        Some(size) => sizes.push_str(&format!("[{}]", size)),
+                        None => sizes.push_str("[]"),
+                    }
+                    ty = element;
+                }
+                return write!(f, "{}{}", ty, sizes);
+            }
+            Type::Struct(name) => name,
+            Type::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
     }
+}
 
-    // Check if two types are compatible for operations
+impl Type {
+    // Check if two types are compatible for operations. For assignments only int
+    // to float is implicit, see ScopeAnalyzer::check_narrowing
     fn is_compatible(&self, other: &Type) -> bool {
         self == other
             || matches!(
@@ -44,7 +60,57 @@ impl Type {
             )
     }
 
-    // Get the result type of a binary operation
+    // Whether a value of type `other` can be stored as this type as it is, without
+    // converting anything. `int[]` holds an int array of any length.
+    fn holds(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Array(element, size), Type::Array(other_element, other_size)) => {
+                element.holds(other_element) && (size.is_none() || size == other_size)
+            }
+            _ => self == other,
+        }
+    }
+
+    // Arrays and structs: made of other values, no operator works on them whole and
+    // declaring one without a value zeroes every part of it
+    pub fn is_compound(&self) -> bool {
+        matches!(self, Type::Array(..) | Type::Struct(_))
+    }
+
+    // Whether a zero value of this type can be made without knowing more, which
+    // is what an array declared without a value starts out as. The fields of a
+    // struct always have a size.
+    fn is_sized(&self) -> bool {
+        match self {
+            Type::Array(element, Some(_)) => element.is_sized(),
+            Type::Array(_, None) => false,
+            _ => true,
+        }
+    }
+
+    // Whether an arithmetic or comparison operator works on two values of this
+    // type. Strings only join and compare, bools only test for equality, && and ||
+    // are checked on their own.
+    fn supports(&self, operator: &Token) -> bool {
+        match self {
+            Type::Int | Type::Float | Type::Unknown => true,
+            Type::String => matches!(
+                operator,
+                Token::T_PLUS_OPR
+                    | Token::T_EQUALS_OPR
+                    | Token::T_NOT_EQUALS_OPR
+                    | Token::T_LESS_THAN_OPR
+                    | Token::T_GREATER_THAN_OPR
+                    | Token::T_LESS_THAN_EQUAL_TO_OPR
+                    | Token::T_GREATER_THAN_EQUAL_TO_OPR
+            ),
+            Type::Bool => matches!(operator, Token::T_EQUALS_OPR | Token::T_NOT_EQUALS_OPR),
+            _ => false,
+        }
+    }
+
+    // Type both operands of a binary operation are brought to, see supports() for
+    // whether the operator works on it
     fn result_type(&self, other: &Type) -> Type {
         match (self, other) {
             (Type::Float, Type::Int) | (Type::Int, Type::Float) | (Type::Float, Type::Float) => {
@@ -60,10 +126,10 @@ impl Type {
 
 #[derive(Debug, Clone)]
 pub enum SymbolType {
-    Variable(String), //Store type, will be useful for type checking later
+    Variable(Type),
     Function {
-        return_type: String,
-        params: Vec<String>, // parameter types
+        return_type: Type,
+        params: Vec<Type>, // parameter types
     },
 }
 
@@ -71,10 +137,49 @@ pub enum SymbolType {
 pub struct Symbol {
     pub name: String,
     pub symbol_type: SymbolType,
+    pub span: Span, // where it was declared
+}
+
+impl Symbol {
+    // Type of the value a variable holds, or that a function returns
+    pub fn value_type(&self) -> Type {
+        match &self.symbol_type {
+            SymbolType::Variable(ty) => ty.clone(),
+            SymbolType::Function { return_type, .. } => return_type.clone(),
+        }
+    }
+}
+
+// A struct type, its fields are laid out in the order they are declared
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub span: Span, // the struct's name
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+impl StructDef {
+    // Index of a field, which is where it sits inside a value of this struct
+    pub fn field(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    // `x, y` for error notes
+    fn field_names(&self) -> String {
+        let names: Vec<&str> = self.fields.iter().map(|f| f.name.as_str()).collect();
+        names.join(", ")
+    }
 }
 
 pub struct Scope {
-    symbols: HashMap<String, Symbol>, //Map identifiers to its Symbol Information
+    symbols: HashMap<String, SymbolId>, //Map identifiers to its Symbol Information
 }
 
 impl Scope {
@@ -84,61 +189,114 @@ impl Scope {
         }
     }
 
-    fn declare(&mut self, name: String, symbol: Symbol) -> Result<(), String> {
-        // Checking if is redeclaration
-        if let Some(existing) = self.symbols.get(&name) {
-            // Provide specific error based on what's being redeclared
-            match (&existing.symbol_type, &symbol.symbol_type) {
-                (SymbolType::Function { .. }, SymbolType::Function { .. }) => {
-                    return Err(format!(
-                        "Function '{}' is already defined in this scope",
-                        name
-                    ));
-                }
-                (SymbolType::Variable(_), SymbolType::Variable(_)) => {
-                    return Err(format!(
-                        "Variable '{}' is already declared in this scope",
-                        name
-                    ));
-                }
-                (SymbolType::Function { .. }, SymbolType::Variable(_)) => {
-                    return Err(format!(
-                        "'{}' is already defined as a function, cannot redeclare as variable",
-                        name
-                    ));
-                }
-                (SymbolType::Variable(_), SymbolType::Function { .. }) => {
-                    return Err(format!(
-                        "'{}' is already declared as a variable, cannot redefine as function",
-                        name
-                    ));
-                }
-            }
+    fn lookup(&self, name: &str) -> Option<SymbolId> {
+        self.symbols.get(name).copied()
+    }
+}
+
+// Provide specific error based on what's being redeclared
+fn redeclaration_message(existing: &Symbol, symbol: &Symbol) -> String {
+    let name = &symbol.name;
+    match (&existing.symbol_type, &symbol.symbol_type) {
+        (SymbolType::Function { .. }, SymbolType::Function { .. }) => {
+            format!("Function '{}' is already defined in this scope", name)
+        }
+        (SymbolType::Variable(_), SymbolType::Variable(_)) => {
+            format!("Variable '{}' is already declared in this scope", name)
+        }
+        (SymbolType::Function { .. }, SymbolType::Variable(_)) => {
+            format!(
+                "'{}' is already defined as a function, cannot redeclare as variable",
+                name
+            )
+        }
+        (SymbolType::Variable(_), SymbolType::Function { .. }) => {
+            format!(
+                "'{}' is already declared as a variable, cannot redefine as function",
+                name
+            )
         }
-        // is a new declaration so adding it to current scope
-        self.symbols.insert(name, symbol);
-        Ok(())
     }
+}
 
-    fn lookup(&self, name: &str) -> Option<&Symbol> {
-        self.symbols.get(name)
+// `'a', 'b'` for messages
+fn quoted(names: &[&str]) -> String {
+    let names: Vec<String> = names.iter().map(|name| format!("'{}'", name)).collect();
+    names.join(", ")
+}
+
+// Adds the conversion node needed to use `expr` where a `to` is expected. The
+// elements of an array literal are converted one by one.
+fn convert(expr: TypedExpr, to: &Type) -> TypedExpr {
+    let kind = match (&expr.ty, to) {
+        (Type::Int, Type::Float) => TypedExprKind::IntToFloat,
+        (Type::Float, Type::Int) => TypedExprKind::FloatToInt,
+        (Type::Array(..), Type::Array(element, _)) => {
+            let TypedExprKind::ArrayLiteral(elements) = expr.kind else {
+                return expr;
+            };
+            let elements: Vec<TypedExpr> =
+                elements.into_iter().map(|e| convert(e, element)).collect();
+            return TypedExpr {
+                ty: Type::Array(element.clone(), Some(elements.len())),
+                kind: TypedExprKind::ArrayLiteral(elements),
+                span: expr.span,
+            };
+        }
+        _ => return expr,
+    };
+    let span = expr.span;
+    TypedExpr {
+        kind: kind(Box::new(expr)),
+        ty: to.clone(),
+        span,
+    }
+}
+
+// Whether `value` can be used where a `to` is expected once convert() is done
+// with it. int and float go both ways here, check_narrowing rejects float to int.
+fn fits(value: &TypedExpr, to: &Type) -> bool {
+    match (&value.kind, to) {
+        // already reported when the literal was checked
+        _ if value.ty == Type::Unknown => true,
+        (TypedExprKind::ArrayLiteral(elements), Type::Array(element, size)) => {
+            size.is_none_or(|size| size == elements.len())
+                && elements.iter().all(|e| fits(e, element))
+        }
+        _ => to.holds(&value.ty) || to.is_compatible(&value.ty),
     }
 }
 
 pub struct ScopeAnalyzer {
-    scopes: Vec<Scope>, //Is Spaghetti stack of scopes
-    errors: Vec<String>,
+    scopes: Vec<Scope>,   //Is Spaghetti stack of scopes
+    symbols: Vec<Symbol>, // every declaration ever made, scopes point into this
+    structs: Vec<StructDef>,
+    errors: Vec<Diagnostic>,
     current_function_return_type: Option<Type>, // Track current function's return type
     loop_depth: usize,                          // Track if we're inside a loop
+    lints: LintConfig,
+}
+
+impl Default for ScopeAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ScopeAnalyzer {
     pub fn new() -> Self {
+        Self::with_lints(LintConfig::default())
+    }
+
+    pub fn with_lints(lints: LintConfig) -> Self {
         ScopeAnalyzer {
             scopes: vec![Scope::new()], // 0th index is Global Scope
+            symbols: Vec::new(),
+            structs: Vec::new(),
             errors: Vec::new(),
             current_function_return_type: None,
             loop_depth: 0,
+            lints,
         }
     }
 
@@ -152,667 +310,1597 @@ impl ScopeAnalyzer {
         self.scopes.pop();
     }
 
-    // Helper to declare function in current scope which is the innermost scope
-    fn declare_symbol(&mut self, name: String, symbol: Symbol) {
-        if let Some(current_scope) = self.scopes.last_mut() {
-            if let Err(e) = current_scope.declare(name, symbol) {
-                self.errors.push(e);
-            }
+    // Helper to declare a symbol in the innermost scope. A redeclared symbol still
+    // gets an id so the tree can be built, it just can't be found by name.
+    fn declare_symbol(&mut self, symbol: Symbol) -> SymbolId {
+        let id = self.symbols.len();
+        let existing = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.lookup(&symbol.name));
+
+        if let Some(existing) = existing {
+            let previous = &self.symbols[existing];
+            let message = redeclaration_message(previous, &symbol);
+            let previous_span = previous.span;
+            self.report(
+                Diagnostic::error(codes::REDECLARATION, message, symbol.span)
+                    .with_label("redeclared here")
+                    .with_secondary(previous_span, "previous declaration here"),
+            );
+        } else if let Some(current_scope) = self.scopes.last_mut() {
+            current_scope.symbols.insert(symbol.name.clone(), id);
+        }
+
+        self.symbols.push(symbol);
+        id
+    }
+
+    fn declare_variable(&mut self, name: &str, ty: Type, span: Span) -> SymbolId {
+        // Allowed, but the outer one can't be reached any more from in here
+        let depth = self.scopes.len();
+        let outer = self.scopes[..depth - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.lookup(name));
+        if depth > 1
+            && self.scopes[depth - 1].lookup(name).is_none()
+            && let Some(outer) = outer
+        {
+            let outer = &self.symbols[outer];
+            let what = match outer.symbol_type {
+                SymbolType::Variable(_) if self.scopes[0].lookup(name).is_some() => "global",
+                SymbolType::Variable(_) => "variable",
+                SymbolType::Function { .. } => "function",
+            };
+            let warning = Lint::Shadowing
+                .warning(
+                    format!("'{}' shadows a {} from an outer scope", name, what),
+                    span,
+                )
+                .with_secondary(
+                    outer.span,
+                    format!("the shadowed {} is declared here", what),
+                );
+            self.lint(Lint::Shadowing, warning);
         }
+
+        self.declare_symbol(Symbol {
+            name: name.to_string(),
+            symbol_type: SymbolType::Variable(ty),
+            span,
+        })
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.errors.push(diagnostic);
+    }
+
+    // Reports a lint's warning at the level it is configured to
+    fn lint(&mut self, lint: Lint, warning: Diagnostic) {
+        self.errors.extend(self.lints.apply(lint, warning));
+    }
+
+    fn error(&mut self, code: &'static str, span: Span, message: String) {
+        self.report(Diagnostic::error(code, message, span));
     }
 
     // Lookup helper function to iteratively search for symbol from current to all the way to outer global scope
-    fn lookup_symbol(&self, name: &str) -> Option<&Symbol> {
+    fn lookup_symbol(&self, name: &str) -> Option<SymbolId> {
         for scope in self.scopes.iter().rev() {
-            if let Some(symbol) = scope.lookup(name) {
-                return Some(symbol);
+            if let Some(id) = scope.lookup(name) {
+                return Some(id);
             }
         }
         None
     }
 
-    // Helper function to convert Token to string type
-    fn token_to_string(&self, token: &Token) -> String {
-        match token {
-            Token::T_INT => "int".to_string(),
-            Token::T_FLOAT => "float".to_string(),
-            Token::T_BOOL => "bool".to_string(),
-            Token::T_STRING => "string".to_string(),
-            Token::T_VOID => "void".to_string(),
-            _ => format!("{:?}", token),
-        }
+    fn find_struct(&self, name: &str) -> Option<&StructDef> {
+        self.structs.iter().find(|def| def.name == name)
     }
 
-    // Helper function to convert Token to Type enum
-    fn token_to_type(&self, token: &Token) -> Type {
-        match token {
-            Token::T_INT => Type::Int,
-            Token::T_FLOAT => Type::Float,
-            Token::T_BOOL => Type::Bool,
-            Token::T_STRING => Type::String,
-            Token::T_VOID => Type::Void,
-            _ => Type::Unknown,
+    // Helper function to convert a written type to Type enum
+    fn resolve_type(&mut self, name: &TypeName) -> Type {
+        match name {
+            TypeName::Base(Token::T_INT) => Type::Int,
+            TypeName::Base(Token::T_FLOAT) => Type::Float,
+            TypeName::Base(Token::T_BOOL) => Type::Bool,
+            TypeName::Base(Token::T_STRING) => Type::String,
+            TypeName::Base(Token::T_VOID) => Type::Void,
+            TypeName::Base(_) => Type::Unknown,
+            TypeName::Struct(name, span) => {
+                if self.find_struct(name).is_some() {
+                    return Type::Struct(name.clone());
+                }
+                self.report(
+                    Diagnostic::error(
+                        codes::UNKNOWN_TYPE,
+                        format!("Unknown type '{}'", name),
+                        *span,
+                    )
+                    .with_label("no struct with this name"),
+                );
+                Type::Unknown
+            }
+            TypeName::Array { element, size } => {
+                Type::Array(Box::new(self.resolve_type(element)), *size)
+            }
         }
     }
 
-    // Type inference for expressions
-    fn infer_expression_type(&mut self, expr: &Expression) -> Type {
-        match expr {
-            Expression::Literal(constant) => match constant {
-                Constants::Int(_) => Type::Int,
-                Constants::Float(_) => Type::Float,
-                Constants::Str(_) => Type::String,
-                Constants::Bool(_) => Type::Bool,
-            },
-            Expression::Identifier(name) => {
-                if let Some(symbol) = self.lookup_symbol(name) {
-                    match &symbol.symbol_type {
-                        SymbolType::Variable(type_str) => Type::from_string(type_str),
-                        SymbolType::Function { .. } => {
-                            // Already reported as error in analyze_expression
-                            Type::Unknown
-                        }
-                    }
-                } else {
-                    // Already reported as error in analyze_expression
-                    Type::Unknown
-                }
+    // Type checks an expression once and builds its typed node. Anything already
+    // reported comes back as Unknown, which never causes a second error.
+    fn check_expression(&mut self, expr: &Expression) -> TypedExpr {
+        let span = expr.span;
+        let typed = |kind, ty| TypedExpr { kind, ty, span };
+
+        match &expr.kind {
+            ExpressionKind::Literal(constant) => {
+                let ty = match constant {
+                    Constants::Int(_) => Type::Int,
+                    Constants::Float(_) => Type::Float,
+                    Constants::Str(_) => Type::String,
+                    Constants::Bool(_) => Type::Bool,
+                };
+                typed(TypedExprKind::Literal(constant.clone()), ty)
             }
-            Expression::BinaryOperation {
+            ExpressionKind::Identifier(name) => match self.resolve_variable(name, span) {
+                Some(id) => typed(TypedExprKind::Variable(id), self.symbols[id].value_type()),
+                None => typed(TypedExprKind::Invalid, Type::Unknown),
+            },
+            ExpressionKind::BinaryOperation {
                 left,
                 operator,
                 right,
             } => {
-                let left_type = self.infer_expression_type(left);
-                let right_type = self.infer_expression_type(right);
-
-                match operator {
-                    // Arithmetic operators: +, -, *, /, ^
-                    Token::T_PLUS_OPR
-                    | Token::T_MINUS_OPR
-                    | Token::T_MULTIPLY_OPR
-                    | Token::T_DIVIDE_OPR
-                    | Token::T_EXPONENT_OPR => {
-                        if left_type.is_compatible(&right_type) {
-                            left_type.result_type(&right_type)
+                let left = self.check_value(left);
+                let right = self.check_value(right);
+                self.check_binary(left, operator, right, span)
+            }
+            ExpressionKind::UnaryOperation {
+                operator,
+                expression,
+            } => {
+                let operand = self.check_value(expression);
+                let ty = match operator {
+                    Token::T_MINUS_OPR => {
+                        if matches!(operand.ty, Type::Int | Type::Float | Type::Unknown) {
+                            operand.ty.clone()
                         } else {
-                            self.errors.push(format!(
-                                "Type mismatch in arithmetic operation: cannot apply operator to '{}' and '{}'",
-                                left_type.to_string(), right_type.to_string()
-                            ));
+                            self.error(
+                                codes::INVALID_OPERAND,
+                                span,
+                                format!("Unary minus requires numeric type, got '{}'", operand.ty),
+                            );
                             Type::Unknown
                         }
                     }
-                    // Comparison operators: ==, !=, <, >, <=, >=
-                    Token::T_EQUALS_OPR
-                    | Token::T_NOT_EQUALS_OPR
-                    | Token::T_LESS_THAN_OPR
-                    | Token::T_GREATER_THAN_OPR
-                    | Token::T_LESS_THAN_EQUAL_TO_OPR
-                    | Token::T_GREATER_THAN_EQUAL_TO_OPR => {
-                        if left_type.is_compatible(&right_type) {
-                            Type::Bool
-                        } else {
-                            self.errors.push(format!(
-                                "Type mismatch in comparison: cannot compare '{}' and '{}'",
-                                left_type.to_string(),
-                                right_type.to_string()
-                            ));
-                            Type::Bool // Return bool for error recovery
-                        }
-                    }
-                    // Logical operators: &&, ||
-                    Token::T_AND_OPR | Token::T_OR_OPR => {
-                        if left_type != Type::Bool {
-                            self.errors.push(format!(
-                                "Logical operator requires boolean operands, got '{}' on left side",
-                                left_type.to_string()
-                            ));
-                        }
-                        if right_type != Type::Bool {
-                            self.errors.push(format!(
-                                "Logical operator requires boolean operands, got '{}' on right side",
-                                right_type.to_string()
-                            ));
+                    Token::T_NOT => {
+                        if !matches!(operand.ty, Type::Bool | Type::Unknown) {
+                            self.error(
+                                codes::INVALID_OPERAND,
+                                span,
+                                format!("Logical NOT requires boolean type, got '{}'", operand.ty),
+                            );
                         }
                         Type::Bool
                     }
-                    // Bitwise operators: &, |, <<, >>
-                    Token::T_LEFT_SHIFT_OPR | Token::T_RIGHT_SHIFT_OPR => {
-                        if left_type != Type::Int {
-                            self.errors.push(format!(
-                                "Bitwise shift operator requires integer operands, got '{}'",
-                                left_type.to_string()
-                            ));
-                        }
-                        if right_type != Type::Int {
-                            self.errors.push(format!(
-                                "Bitwise shift operator requires integer operands, got '{}'",
-                                right_type.to_string()
-                            ));
+                    Token::T_BIT_NOT => {
+                        if !matches!(operand.ty, Type::Int | Type::Unknown) {
+                            self.error(
+                                codes::INVALID_OPERAND,
+                                span,
+                                format!("Bitwise NOT requires integer type, got '{}'", operand.ty),
+                            );
                         }
                         Type::Int
                     }
                     _ => Type::Unknown,
+                };
+                typed(
+                    TypedExprKind::Unary {
+                        operator: operator.clone(),
+                        operand: Box::new(operand),
+                    },
+                    ty,
+                )
+            }
+            ExpressionKind::Assignment { left, right } => {
+                // the parser only allows variables and elements of them on the left,
+                // their indices are evaluated before the value
+                let target = self.check_expression(left);
+                let value = self.check_value(right);
+                if matches!(target.kind, TypedExprKind::Invalid) {
+                    return typed(TypedExprKind::Invalid, Type::Unknown);
+                }
+
+                let target_type = target.ty.clone();
+                self.check_narrowing(&value, &target_type);
+                if !fits(&value, &target_type) && target_type != Type::Unknown {
+                    self.error(
+                        codes::MISMATCHED_TYPES,
+                        span,
+                        format!(
+                            "Type mismatch in assignment: cannot assign '{}' to '{}'",
+                            value.ty, target_type
+                        ),
+                    );
                 }
+                typed(
+                    TypedExprKind::Assignment {
+                        target: Box::new(target),
+                        value: Box::new(convert(value, &target_type)),
+                    },
+                    target_type,
+                )
             }
-            Expression::UnaryOperation {
+            ExpressionKind::CompoundAssignment {
+                left,
                 operator,
-                expression,
+                right,
             } => {
-                let expr_type = self.infer_expression_type(expression);
-                match operator {
-                    Token::T_MINUS_OPR => {
-                        if expr_type == Type::Int || expr_type == Type::Float {
-                            expr_type
-                        } else {
-                            self.errors.push(format!(
-                                "Unary minus requires numeric type, got '{}'",
-                                expr_type.to_string()
-                            ));
-                            Type::Unknown
-                        }
-                    }
-                    Token::T_NOT => {
-                        if expr_type != Type::Bool {
-                            self.errors.push(format!(
-                                "Logical NOT requires boolean type, got '{}'",
-                                expr_type.to_string()
-                            ));
-                        }
-                        Type::Bool
-                    }
-                    _ => Type::Unknown,
+                let target = self.check_expression(left);
+                let value = self.check_value(right);
+                self.check_compound(target, operator, value, false, span)
+            }
+            ExpressionKind::Increment {
+                target,
+                operator,
+                postfix,
+            } => {
+                let target = self.check_expression(target);
+                let one = typed(TypedExprKind::Literal(Constants::Int(1)), Type::Int);
+                self.check_compound(target, operator, one, *postfix, span)
+            }
+            ExpressionKind::FunctionCall(func_call) => self.check_function_call(func_call, span),
+            ExpressionKind::Index { array, index } => {
+                let array = self.check_value(array);
+                let index = self.check_value(index);
+                self.check_index(array, index, span)
+            }
+            ExpressionKind::ArrayLiteral(elements) => self.check_array_literal(elements, span),
+            ExpressionKind::FieldAccess {
+                object,
+                field,
+                field_span,
+            } => {
+                let object = self.check_value(object);
+                self.check_field(object, field, *field_span, span)
+            }
+            ExpressionKind::StructLiteral {
+                identifier,
+                fields,
+                identifier_span,
+            } => self.check_struct_literal(identifier, fields, *identifier_span, span),
+        }
+    }
+
+    // `x += v` and `x++`: the operator has to work on the target's type and the value
+    // has to fit in it, the result is stored back without changing the type
+    fn check_compound(
+        &mut self,
+        target: TypedExpr,
+        operator: &Token,
+        value: TypedExpr,
+        postfix: bool,
+        span: Span,
+    ) -> TypedExpr {
+        let Some(binary) = operator.compound_operator() else {
+            return TypedExpr {
+                kind: TypedExprKind::Invalid,
+                ty: Type::Unknown,
+                span,
+            };
+        };
+        if matches!(target.kind, TypedExprKind::Invalid) {
+            return TypedExpr {
+                kind: TypedExprKind::Invalid,
+                ty: Type::Unknown,
+                span,
+            };
+        }
+
+        let ty = target.ty.clone();
+        let allowed = match operator {
+            Token::T_PLUS_ASSIGN_OPR => matches!(ty, Type::Int | Type::Float | Type::String),
+            Token::T_MODULO_ASSIGN_OPR
+            | Token::T_LEFT_SHIFT_ASSIGN_OPR
+            | Token::T_RIGHT_SHIFT_ASSIGN_OPR => ty == Type::Int,
+            _ => matches!(ty, Type::Int | Type::Float),
+        };
+        if ty == Type::Unknown {
+            // already reported
+        } else if !allowed {
+            self.report(
+                Diagnostic::error(
+                    codes::INVALID_OPERAND,
+                    format!("Operator {} cannot be applied to '{}'", operator, ty),
+                    target.span,
+                )
+                .with_label(format!("this has type '{}'", ty)),
+            );
+        } else {
+            self.check_narrowing(&value, &ty);
+            if !fits(&value, &ty) {
+                self.error(
+                    codes::MISMATCHED_TYPES,
+                    span,
+                    format!(
+                        "Type mismatch in assignment: cannot apply {} to '{}' and '{}'",
+                        operator, ty, value.ty
+                    ),
+                );
+            }
+        }
+
+        TypedExpr {
+            kind: TypedExprKind::CompoundAssignment {
+                target: Box::new(target),
+                operator: binary,
+                value: Box::new(convert(value, &ty)),
+                postfix,
+            },
+            ty,
+            span,
+        }
+    }
+
+    fn check_field(
+        &mut self,
+        object: TypedExpr,
+        field: &str,
+        field_span: Span,
+        span: Span,
+    ) -> TypedExpr {
+        let invalid = TypedExpr {
+            kind: TypedExprKind::Invalid,
+            ty: Type::Unknown,
+            span,
+        };
+        let def = match &object.ty {
+            Type::Struct(name) => match self.find_struct(name) {
+                Some(def) => def,
+                None => return invalid,
+            },
+            Type::Unknown => return invalid,
+            other => {
+                self.report(
+                    Diagnostic::error(
+                        codes::NOT_A_STRUCT,
+                        format!(
+                            "Cannot access field '{}' on a value of type '{}'",
+                            field, other
+                        ),
+                        object.span,
+                    )
+                    .with_label("not a struct"),
+                );
+                return invalid;
+            }
+        };
+
+        let Some(index) = def.field(field) else {
+            let diagnostic = Diagnostic::error(
+                codes::UNKNOWN_FIELD,
+                format!("Struct '{}' has no field '{}'", def.name, field),
+                field_span,
+            )
+            .with_label("unknown field")
+            .with_note(format!(
+                "the fields of '{}' are: {}",
+                def.name,
+                def.field_names()
+            ));
+            self.report(diagnostic);
+            return invalid;
+        };
+        let ty = def.fields[index].ty.clone();
+        TypedExpr {
+            kind: TypedExprKind::Field {
+                object: Box::new(object),
+                field: index,
+            },
+            ty,
+            span,
+        }
+    }
+
+    // Every field needs exactly one value, in any order
+    fn check_struct_literal(
+        &mut self,
+        identifier: &str,
+        fields: &[FieldInit],
+        identifier_span: Span,
+        span: Span,
+    ) -> TypedExpr {
+        let values: Vec<TypedExpr> = fields.iter().map(|f| self.check_value(&f.value)).collect();
+        let Some(def) = self.find_struct(identifier).cloned() else {
+            self.report(
+                Diagnostic::error(
+                    codes::UNKNOWN_TYPE,
+                    format!("Unknown type '{}'", identifier),
+                    identifier_span,
+                )
+                .with_label("no struct with this name"),
+            );
+            return TypedExpr {
+                kind: TypedExprKind::Invalid,
+                ty: Type::Unknown,
+                span,
+            };
+        };
+
+        let mut given: Vec<(usize, TypedExpr)> = Vec::new();
+        let mut given_at: Vec<Span> = Vec::new();
+        for (init, value) in fields.iter().zip(values) {
+            let Some(index) = def.field(&init.identifier) else {
+                self.report(
+                    Diagnostic::error(
+                        codes::UNKNOWN_FIELD,
+                        format!("Struct '{}' has no field '{}'", def.name, init.identifier),
+                        init.span,
+                    )
+                    .with_label("unknown field")
+                    .with_note(format!(
+                        "the fields of '{}' are: {}",
+                        def.name,
+                        def.field_names()
+                    )),
+                );
+                continue;
+            };
+            if let Some(previous) = given.iter().position(|(i, _)| *i == index) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_FIELD,
+                        format!("Field '{}' is given more than once", init.identifier),
+                        init.span,
+                    )
+                    .with_label("given again here")
+                    .with_secondary(given_at[previous], "first given here"),
+                );
+                continue;
+            }
+
+            let field_type = &def.fields[index].ty;
+            self.check_narrowing(&value, field_type);
+            if !fits(&value, field_type) {
+                self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "Type mismatch in field '{}' of '{}': expected '{}', got '{}'",
+                            init.identifier, def.name, field_type, value.ty
+                        ),
+                        value.span,
+                    )
+                    .with_label(format!("expected '{}'", field_type)),
+                );
+            }
+            given.push((index, convert(value, field_type)));
+            given_at.push(init.span);
+        }
+
+        let missing: Vec<&str> = def
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !given.iter().any(|(given, _)| given == i))
+            .map(|(_, field)| field.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            let message = match missing.as_slice() {
+                [field] => format!("Missing field '{}' in '{}'", field, def.name),
+                fields => format!("Missing fields {} in '{}'", quoted(fields), def.name),
+            };
+            self.report(
+                Diagnostic::error(codes::MISSING_FIELD, message, identifier_span)
+                    .with_label("every field needs a value")
+                    .with_secondary(def.span, format!("'{}' is declared here", def.name)),
+            );
+        }
+
+        TypedExpr {
+            kind: TypedExprKind::StructLiteral(given),
+            ty: Type::Struct(def.name),
+            span,
+        }
+    }
+
+    fn check_index(&mut self, array: TypedExpr, index: TypedExpr, span: Span) -> TypedExpr {
+        if !matches!(index.ty, Type::Int | Type::Unknown) {
+            self.report(
+                Diagnostic::error(
+                    codes::NON_INT_INDEX,
+                    format!("Array index must be 'int', got '{}'", index.ty),
+                    index.span,
+                )
+                .with_label("expected 'int'"),
+            );
+        }
+
+        let ty = match &array.ty {
+            Type::Array(element, size) => {
+                // a constant index can be checked right away
+                if let (TypedExprKind::Literal(Constants::Int(i)), Some(size)) = (&index.kind, size)
+                    && *i as u64 >= *size as u64
+                {
+                    let label = match size {
+                        0 => "the array has no elements".to_string(),
+                        _ => format!("valid indices are 0 to {}", size - 1),
+                    };
+                    self.report(
+                        Diagnostic::error(
+                            codes::CONSTANT_INDEX_OUT_OF_BOUNDS,
+                            format!("Index {} is out of bounds for '{}'", i, array.ty),
+                            index.span,
+                        )
+                        .with_label(label),
+                    );
                 }
+                (**element).clone()
+            }
+            Type::Unknown => Type::Unknown,
+            other => {
+                self.report(
+                    Diagnostic::error(
+                        codes::NOT_AN_ARRAY,
+                        format!("Cannot index into a value of type '{}'", other),
+                        array.span,
+                    )
+                    .with_label("not an array"),
+                );
+                Type::Unknown
+            }
+        };
+
+        TypedExpr {
+            kind: TypedExprKind::Index {
+                array: Box::new(array),
+                index: Box::new(index),
+            },
+            ty,
+            span,
+        }
+    }
+
+    // The elements decide the type: int and float mix into float, anything else
+    // has to agree
+    fn check_array_literal(&mut self, elements: &[Expression], span: Span) -> TypedExpr {
+        let elements: Vec<TypedExpr> = elements.iter().map(|e| self.check_value(e)).collect();
+        let Some(first) = elements.first() else {
+            self.report(
+                Diagnostic::error(
+                    codes::EMPTY_ARRAY_LITERAL,
+                    "Cannot infer the type of an empty array literal".to_string(),
+                    span,
+                )
+                .with_note("declare it with a size instead, like `int[0] a;`"),
+            );
+            return TypedExpr {
+                kind: TypedExprKind::ArrayLiteral(elements),
+                ty: Type::Unknown,
+                span,
+            };
+        };
+
+        let mut element_type = first.ty.clone();
+        for element in &elements[1..] {
+            if element_type == Type::Unknown || element.ty == Type::Unknown {
+                element_type = Type::Unknown;
+            } else if element_type.holds(&element.ty) {
+                // already the wider one
+            } else if element.ty.holds(&element_type) {
+                element_type = element.ty.clone();
+            } else if element_type.is_compatible(&element.ty) {
+                element_type = element_type.result_type(&element.ty);
+            } else {
+                self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "Array elements must have the same type, found '{}' and '{}'",
+                            element_type, element.ty
+                        ),
+                        element.span,
+                    )
+                    .with_label(format!("expected '{}'", element_type)),
+                );
+                element_type = Type::Unknown;
+            }
+        }
+
+        let ty = match element_type {
+            Type::Unknown => Type::Unknown,
+            element_type => Type::Array(Box::new(element_type), Some(elements.len())),
+        };
+        let elements = match &ty {
+            Type::Array(element_type, _) => elements
+                .into_iter()
+                .map(|e| convert(e, element_type))
+                .collect(),
+            _ => elements,
+        };
+        TypedExpr {
+            kind: TypedExprKind::ArrayLiteral(elements),
+            ty,
+            span,
+        }
+    }
+
+    // A float going into an int would silently lose its fraction, so that one
+    // conversion is never implicit
+    fn check_narrowing(&mut self, value: &TypedExpr, to: &Type) {
+        if let (TypedExprKind::ArrayLiteral(elements), Type::Array(element, _)) = (&value.kind, to)
+        {
+            for e in elements {
+                self.check_narrowing(e, element);
             }
-            Expression::Assignment { left, right } => {
-                let left_type = self.infer_expression_type(left);
-                let right_type = self.infer_expression_type(right);
+            return;
+        }
+        if value.ty != Type::Float || *to != Type::Int {
+            return;
+        }
+        self.report(
+            Diagnostic::error(
+                codes::NARROWING_CONVERSION,
+                "Cannot implicitly convert 'float' to 'int'".to_string(),
+                value.span,
+            )
+            .with_label("expected 'int', found 'float'")
+            .with_note("only int to float converts implicitly, the fractional part would be lost"),
+        );
+    }
+
+    // check_expression for the places that need a value, which a void call doesn't have
+    fn check_value(&mut self, expr: &Expression) -> TypedExpr {
+        let mut typed = self.check_expression(expr);
+        if typed.ty == Type::Void {
+            let name = match &expr.kind {
+                ExpressionKind::FunctionCall(call) => call.identifier.as_str(),
+                _ => "expression",
+            };
+            self.report(
+                Diagnostic::error(
+                    codes::VOID_VALUE_USED,
+                    format!(
+                        "'{}' returns nothing, its result cannot be used as a value",
+                        name
+                    ),
+                    expr.span,
+                )
+                .with_label("this call has type 'void'"),
+            );
+            typed.ty = Type::Unknown;
+        }
+        typed
+    }
+
+    // Finds the variable a name refers to, reporting it when there is none
+    fn resolve_variable(&mut self, name: &str, span: Span) -> Option<SymbolId> {
+        let Some(id) = self.lookup_symbol(name) else {
+            self.report(
+                Diagnostic::error(
+                    codes::UNDEFINED_VARIABLE,
+                    format!("Variable '{}' is not defined", name),
+                    span,
+                )
+                .with_label("not found in this scope"),
+            );
+            return None;
+        };
+
+        // Checking if is identifier and not a function
+        if matches!(self.symbols[id].symbol_type, SymbolType::Function { .. }) {
+            self.error(
+                codes::NOT_A_VARIABLE,
+                span,
+                format!("'{}' is a function, not a variable", name),
+            );
+            return None;
+        }
+        Some(id)
+    }
 
-                if !left_type.is_compatible(&right_type) {
-                    self.errors.push(format!(
-                        "Type mismatch in assignment: cannot assign '{}' to '{}'",
-                        right_type.to_string(),
-                        left_type.to_string()
+    fn check_binary(
+        &mut self,
+        left: TypedExpr,
+        operator: &Token,
+        right: TypedExpr,
+        span: Span,
+    ) -> TypedExpr {
+        let (left_type, right_type) = (left.ty.clone(), right.ty.clone());
+        let unknown = left_type == Type::Unknown || right_type == Type::Unknown;
+
+        // no operator works on whole arrays or structs
+        if let Some(compound) = [&left, &right].into_iter().find(|e| e.ty.is_compound()) {
+            let label = match compound.ty {
+                Type::Struct(_) => "a struct",
+                _ => "an array",
+            };
+            self.report(
+                Diagnostic::error(
+                    codes::INVALID_OPERAND,
+                    format!(
+                        "Operator {} cannot be applied to '{}'",
+                        operator, compound.ty
+                    ),
+                    compound.span,
+                )
+                .with_label(label),
+            );
+            return TypedExpr {
+                kind: TypedExprKind::Invalid,
+                ty: Type::Unknown,
+                span,
+            };
+        }
+
+        // operand type both sides are brought to, and the type of the result
+        let (operand_type, ty) = match operator {
+            // Arithmetic operators: +, -, *, /, **
+            Token::T_PLUS_OPR
+            | Token::T_MINUS_OPR
+            | Token::T_MULTIPLY_OPR
+            | Token::T_DIVIDE_OPR
+            | Token::T_EXPONENT_OPR => {
+                if unknown {
+                    (None, Type::Unknown)
+                } else if left_type.is_compatible(&right_type) {
+                    let ty = left_type.result_type(&right_type);
+                    if ty.supports(operator) {
+                        (Some(ty.clone()), ty)
+                    } else {
+                        self.unsupported(operator, &left, &ty);
+                        (None, Type::Unknown)
+                    }
+                } else {
+                    self.error(codes::INVALID_OPERAND, span, format!(
+                        "Type mismatch in arithmetic operation: cannot apply operator to '{}' and '{}'",
+                        left_type, right_type
                     ));
+                    (None, Type::Unknown)
                 }
-                left_type
             }
-            Expression::FunctionCall(func_call) => {
-                if let Some(symbol) = self.lookup_symbol(&func_call.identifier) {
-                    match &symbol.symbol_type {
-                        SymbolType::Function { return_type, .. } => Type::from_string(return_type),
-                        _ => Type::Unknown,
+            // Comparison operators: ==, !=, <, >, <=, >=
+            Token::T_EQUALS_OPR
+            | Token::T_NOT_EQUALS_OPR
+            | Token::T_LESS_THAN_OPR
+            | Token::T_GREATER_THAN_OPR
+            | Token::T_LESS_THAN_EQUAL_TO_OPR
+            | Token::T_GREATER_THAN_EQUAL_TO_OPR => {
+                if unknown {
+                    (None, Type::Bool)
+                } else if left_type.is_compatible(&right_type) {
+                    let operand_type = left_type.result_type(&right_type);
+                    if operand_type.supports(operator) {
+                        (Some(operand_type), Type::Bool)
+                    } else {
+                        self.unsupported(operator, &left, &operand_type);
+                        (None, Type::Bool)
                     }
                 } else {
-                    Type::Unknown
+                    self.error(
+                        codes::INVALID_OPERAND,
+                        span,
+                        format!(
+                            "Type mismatch in comparison: cannot compare '{}' and '{}'",
+                            left_type, right_type
+                        ),
+                    );
+                    (None, Type::Bool) // Return bool for error recovery
+                }
+            }
+            // Logical operators: &&, ||
+            Token::T_AND_OPR | Token::T_OR_OPR => {
+                if !matches!(left_type, Type::Bool | Type::Unknown) {
+                    self.error(
+                        codes::INVALID_OPERAND,
+                        left.span,
+                        format!(
+                            "Logical operator requires boolean operands, got '{}' on left side",
+                            left_type
+                        ),
+                    );
                 }
+                if !matches!(right_type, Type::Bool | Type::Unknown) {
+                    self.error(
+                        codes::INVALID_OPERAND,
+                        right.span,
+                        format!(
+                            "Logical operator requires boolean operands, got '{}' on right side",
+                            right_type
+                        ),
+                    );
+                }
+                (None, Type::Bool)
             }
+            // Int only operators: %, <<, >>, &, |, ^
+            Token::T_MODULO_OPR
+            | Token::T_LEFT_SHIFT_OPR
+            | Token::T_RIGHT_SHIFT_OPR
+            | Token::T_BIT_AND_OPR
+            | Token::T_BIT_OR_OPR
+            | Token::T_BIT_XOR_OPR => {
+                let what = match operator {
+                    Token::T_MODULO_OPR => "Remainder operator",
+                    Token::T_LEFT_SHIFT_OPR | Token::T_RIGHT_SHIFT_OPR => "Bitwise shift operator",
+                    _ => "Bitwise operator",
+                };
+                let mut ty = Type::Int;
+                for operand in [&left, &right] {
+                    if matches!(operand.ty, Type::Int | Type::Unknown) {
+                        continue;
+                    }
+                    let mut diagnostic = Diagnostic::error(
+                        codes::INVALID_OPERAND,
+                        format!("{} requires integer operands, got '{}'", what, operand.ty),
+                        operand.span,
+                    );
+                    if let TypedExprKind::Binary {
+                        operator: inner, ..
+                    } = &operand.kind
+                        && operand.ty == Type::Bool
+                        && !matches!(inner, Token::T_AND_OPR | Token::T_OR_OPR)
+                    {
+                        // `a & 1 == 0` is `a & (1 == 0)`, same as in C
+                        diagnostic = diagnostic.with_note(format!(
+                            "{} is applied before {}, wrap the {} operation in parentheses",
+                            inner, operator, operator
+                        ));
+                    } else if *operator == Token::T_BIT_XOR_OPR && operand.ty == Type::Float {
+                        // it used to be the power operator
+                        diagnostic =
+                            diagnostic.with_note("'^' is xor, powers are written with '**'");
+                    } else if operand.ty == Type::Bool
+                        && matches!(operator, Token::T_BIT_AND_OPR | Token::T_BIT_OR_OPR)
+                    {
+                        let logical = match operator {
+                            Token::T_BIT_AND_OPR => Token::T_AND_OPR,
+                            _ => Token::T_OR_OPR,
+                        };
+                        diagnostic = diagnostic.with_note(format!(
+                            "{} is bitwise, use {} on bools",
+                            operator, logical
+                        ));
+                    }
+                    self.report(diagnostic);
+                    ty = Type::Unknown;
+                }
+                (None, ty)
+            }
+            _ => (None, Type::Unknown),
+        };
+
+        // int + float: the int side becomes a float
+        let (left, right) = match operand_type {
+            Some(to) => (convert(left, &to), convert(right, &to)),
+            None => (left, right),
+        };
+        TypedExpr {
+            kind: TypedExprKind::Binary {
+                left: Box::new(left),
+                operator: operator.clone(),
+                right: Box::new(right),
+            },
+            ty,
+            span,
         }
     }
 
+    // Both operands have type `ty` and the operator does not work on it
+    fn unsupported(&mut self, operator: &Token, left: &TypedExpr, ty: &Type) {
+        let mut diagnostic = Diagnostic::error(
+            codes::INVALID_OPERAND,
+            format!("Operator {} cannot be applied to '{}'", operator, ty),
+            left.span,
+        )
+        .with_label(format!("this has type '{}'", ty));
+        if *ty == Type::Bool && matches!(operator, Token::T_PLUS_OPR | Token::T_MULTIPLY_OPR) {
+            let logical = match operator {
+                Token::T_PLUS_OPR => Token::T_OR_OPR,
+                _ => Token::T_AND_OPR,
+            };
+            diagnostic = diagnostic.with_note(format!("use {} on bools", logical));
+        }
+        self.report(diagnostic);
+    }
+
     // AST parsing functions
 
     // Main function starting from root list
-    pub fn analyze(&mut self, root_list: &RootList) {
-        for root in root_list {
-            self.analyze_root(root);
+    pub fn analyze(&mut self, root_list: &RootList) -> TypedProgram {
+        // Everything at the top level is declared before any body is checked,
+        // so functions can call functions defined further down and each other.
+        // Structs go first, any type written anywhere can name one of them.
+        self.declare_structs(root_list);
+        let ids: Vec<Option<SymbolId>> = root_list
+            .iter()
+            .map(|root| self.declare_root(root))
+            .collect();
+
+        let mut globals = Vec::new();
+        let mut functions = Vec::new();
+        for (root, id) in root_list.iter().zip(ids) {
+            match (root, id) {
+                (Root::Var(var_decl), Some(id)) => globals.push(self.check_var_decl(var_decl, id)),
+                (Root::Func(func), Some(id)) => functions.push(self.analyze_function(func, id)),
+                _ => {}
+            }
         }
+
+        let program = TypedProgram {
+            symbols: std::mem::take(&mut self.symbols),
+            structs: std::mem::take(&mut self.structs),
+            globals,
+            functions,
+        };
+
+        // Globals are initialized top to bottom, see globals.rs
+        for diagnostic in globals::check_initialization_order(&program) {
+            self.report(diagnostic);
+        }
+
+        // Lints on a broken program mostly repeat its errors
+        if self.is_valid() {
+            let warnings = lints::check_program(&program, &self.lints);
+            self.errors.extend(warnings);
+        }
+        program
     }
 
-    fn analyze_root(&mut self, root: &Root) {
-        match root {
-            Root::Var(var_decl) => {
-                // Checking initializing expression
-                self.analyze_expression(&var_decl.expression);
+    // Names of all structs first so fields can use structs declared further down,
+    // then the fields, then whether any struct ends up containing itself
+    fn declare_structs(&mut self, root_list: &RootList) {
+        let decls: Vec<&StructDeclaration> = root_list
+            .iter()
+            .filter_map(|root| match root {
+                Root::Struct(decl) => Some(decl),
+                _ => None,
+            })
+            .collect();
+
+        let mut declared = Vec::new();
+        for decl in &decls {
+            if let Some(previous) = self.find_struct(&decl.identifier) {
+                let previous_span = previous.span;
+                self.report(
+                    Diagnostic::error(
+                        codes::REDECLARATION,
+                        format!("Struct '{}' is already defined", decl.identifier),
+                        decl.identifier_span,
+                    )
+                    .with_label("redefined here")
+                    .with_secondary(previous_span, "previous definition here"),
+                );
+                continue;
+            }
+            self.structs.push(StructDef {
+                name: decl.identifier.clone(),
+                fields: Vec::new(),
+                span: decl.identifier_span,
+            });
+            declared.push(decl);
+        }
 
-                // Type checking: check if expression type matches variable type
-                let declared_type = self.token_to_type(&var_decl.type_token);
-                let expr_type = self.infer_expression_type(&var_decl.expression);
+        for (index, decl) in declared.into_iter().enumerate() {
+            let mut fields: Vec<Field> = Vec::new();
+            for field in &decl.fields {
+                let ty = self.resolve_type(&field.field_type);
+                if let Some(previous) = fields.iter().find(|f| f.name == field.identifier) {
+                    let previous_span = previous.span;
+                    self.report(
+                        Diagnostic::error(
+                            codes::DUPLICATE_FIELD,
+                            format!(
+                                "Field '{}' is already declared in '{}'",
+                                field.identifier, decl.identifier
+                            ),
+                            field.span,
+                        )
+                        .with_label("declared again here")
+                        .with_secondary(previous_span, "previous declaration here"),
+                    );
+                    continue;
+                }
+                if !ty.is_sized() {
+                    self.report(
+                        Diagnostic::error(
+                            codes::UNSIZED_ARRAY,
+                            format!("Array field '{}' needs a size", field.identifier),
+                            field.span,
+                        )
+                        .with_label(format!("'{}' has no length", ty))
+                        .with_note("write the length in the type, like `int[10]`"),
+                    );
+                }
+                fields.push(Field {
+                    name: field.identifier.clone(),
+                    ty,
+                    span: field.span,
+                });
+            }
+            self.structs[index].fields = fields;
+        }
 
-                if !declared_type.is_compatible(&expr_type) {
-                    self.errors.push(format!(
-                        "Type mismatch in variable declaration '{}': expected '{}', got '{}'",
-                        var_decl.identifier,
-                        declared_type.to_string(),
-                        expr_type.to_string()
-                    ));
+        // A struct that contains itself, directly or through other structs or arrays,
+        // has no finite size, so a value of it could never be built. The field that
+        // closes the loop is dropped to Unknown so the rest of the cycle isn't
+        // reported again
+        for index in 0..self.structs.len() {
+            let name = self.structs[index].name.clone();
+            for f in 0..self.structs[index].fields.len() {
+                let field = &self.structs[index].fields[f];
+                if !self.contains_struct(&field.ty, &name, &mut HashSet::new()) {
+                    continue;
                 }
+                let diagnostic = Diagnostic::error(
+                    codes::RECURSIVE_STRUCT,
+                    format!("Struct '{}' contains itself", name),
+                    field.span,
+                )
+                .with_label(format!("this field leads back to '{}'", name))
+                .with_note("a value of it would never end, structs are stored whole");
+                self.report(diagnostic);
+                self.structs[index].fields[f].ty = Type::Unknown;
+            }
+        }
+    }
 
-                // Add variable to scope , will handle redeclaration automatically
-                let type_str = self.token_to_string(&var_decl.type_token);
-                self.declare_symbol(
-                    var_decl.identifier.clone(),
-                    Symbol {
-                        name: var_decl.identifier.clone(),
-                        symbol_type: SymbolType::Variable(type_str),
-                    },
-                );
+    // Whether a value of type `ty` has a `name` somewhere inside it
+    fn contains_struct(&self, ty: &Type, name: &str, seen: &mut HashSet<String>) -> bool {
+        match ty {
+            Type::Array(element, _) => self.contains_struct(element, name, seen),
+            Type::Struct(inner) if inner == name => true,
+            Type::Struct(inner) => {
+                seen.insert(inner.clone())
+                    && self.find_struct(inner).is_some_and(|def| {
+                        def.fields
+                            .iter()
+                            .any(|field| self.contains_struct(&field.ty, name, seen))
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    fn declare_root(&mut self, root: &Root) -> Option<SymbolId> {
+        let id = match root {
+            Root::Var(var_decl) => {
+                let ty = self.resolve_type(&var_decl.var_type);
+                self.declare_variable(&var_decl.identifier, ty, var_decl.identifier_span)
             }
             Root::Func(func) => {
-                self.analyze_function(func);
+                let return_type = self.resolve_type(&func.return_type);
+                let params = func
+                    .parameters
+                    .iter()
+                    .map(|param| self.resolve_type(&param.param_type))
+                    .collect();
+
+                self.declare_symbol(Symbol {
+                    name: func.identifier.clone(),
+                    symbol_type: SymbolType::Function {
+                        return_type,
+                        params,
+                    },
+                    span: func.identifier_span,
+                })
             }
+            Root::Struct(_) => return None, // see declare_structs
+        };
+        Some(id)
+    }
+
+    // Checks the initializer of a global already declared as `id`. A global without
+    // one starts out zeroed, any function could read it first so there is no path
+    // definite assignment could follow.
+    fn check_var_decl(&mut self, var_decl: &VariableDeclaration, id: SymbolId) -> TypedVarDecl {
+        let value = var_decl
+            .expression
+            .as_ref()
+            .map(|expr| self.check_value(expr));
+        let declared_type = self.symbols[id].value_type();
+        if value.is_none() {
+            self.check_sized(var_decl, &declared_type);
+        }
+
+        TypedVarDecl {
+            symbol: id,
+            value: value.map(|value| self.check_initializer(var_decl, value, &declared_type)),
+            span: var_decl.span,
         }
     }
 
-    fn analyze_function(&mut self, func: &FunctionStatement) {
-        // Declare the function in the current (global) scope, nested functions not supported by syntax
-        let return_type = self.token_to_string(&func.return_type);
+    // A local declaration: the initializer is checked before the name exists
+    fn analyze_var_decl(&mut self, var_decl: &VariableDeclaration) -> TypedVarDecl {
+        let ty = self.resolve_type(&var_decl.var_type);
+        let value = var_decl
+            .expression
+            .as_ref()
+            .map(|expr| self.check_value(expr));
+        if value.is_none() {
+            self.check_sized(var_decl, &ty);
+        }
+        let id = self.declare_variable(&var_decl.identifier, ty.clone(), var_decl.identifier_span);
 
-        let mut param_types: Vec<String> = Vec::new();
-        for param in &func.parameters {
-            let type_str = self.token_to_string(&param.param_type);
-            param_types.push(type_str);
+        TypedVarDecl {
+            symbol: id,
+            value: value.map(|value| self.check_initializer(var_decl, value, &ty)),
+            span: var_decl.span,
         }
+    }
 
-        //Adding declaration of function to current scope
-        self.declare_symbol(
-            func.identifier.clone(),
-            Symbol {
-                name: func.identifier.clone(),
-                symbol_type: SymbolType::Function {
-                    return_type: return_type.clone(),
-                    params: param_types,
-                },
-            },
+    // Arrays declared without a value start out zeroed, which needs their length
+    fn check_sized(&mut self, var_decl: &VariableDeclaration, ty: &Type) {
+        if ty.is_sized() {
+            return;
+        }
+        self.report(
+            Diagnostic::error(
+                codes::UNSIZED_ARRAY,
+                format!(
+                    "Array '{}' needs a size or an initializer",
+                    var_decl.identifier
+                ),
+                var_decl.identifier_span,
+            )
+            .with_label(format!("'{}' has no length", ty))
+            .with_note("write the length in the type, like `int[10]`, or give it a value"),
         );
+    }
+
+    // Type checking: the initializer has to fit the declared type
+    fn check_initializer(
+        &mut self,
+        var_decl: &VariableDeclaration,
+        value: TypedExpr,
+        declared_type: &Type,
+    ) -> TypedExpr {
+        self.check_narrowing(&value, declared_type);
+        if !fits(&value, declared_type) {
+            self.report(
+                Diagnostic::error(
+                    codes::MISMATCHED_TYPES,
+                    format!(
+                        "Type mismatch in variable declaration '{}': expected '{}', got '{}'",
+                        var_decl.identifier, declared_type, value.ty
+                    ),
+                    value.span,
+                )
+                .with_label(format!("expected '{}'", declared_type))
+                .with_secondary(var_decl.identifier_span, "variable declared here"),
+            );
+        }
+        convert(value, declared_type)
+    }
+
+    fn analyze_function(&mut self, func: &FunctionStatement, id: SymbolId) -> TypedFunction {
+        // The signature was already declared by declare_root
 
         // Set current function return type for return statement checking
-        let func_return_type = self.token_to_type(&func.return_type);
+        let (func_return_type, param_types) = match &self.symbols[id].symbol_type {
+            SymbolType::Function {
+                return_type,
+                params,
+            } => (return_type.clone(), params.clone()),
+            SymbolType::Variable(_) => (Type::Unknown, Vec::new()),
+        };
         self.current_function_return_type = Some(func_return_type.clone());
 
         // Add function scope and its params
         self.enter_scope();
 
         // Declaring parameters in function scope
-        for param in &func.parameters {
-            let param_type = self.token_to_string(&param.param_type);
-            self.declare_symbol(
-                param.identifier.clone(),
-                Symbol {
-                    name: param.identifier.clone(),
-                    symbol_type: SymbolType::Variable(param_type),
-                },
-            );
-        }
-
-        // Recursively analyze function body and check for return statements
-        let has_return = self.block_has_return(&func.block);
+        let params = func
+            .parameters
+            .iter()
+            .zip(param_types)
+            .map(|(param, ty)| self.declare_variable(&param.identifier, ty, param.span))
+            .collect();
 
-        // Check if non-void function has a return statement
-        if func_return_type != Type::Void && !has_return {
-            self.errors.push(format!(
-                "Function '{}' with return type '{}' must have a return statement",
-                func.identifier,
-                func_return_type.to_string()
-            ));
-        }
+        // Recursively analyze function body
+        let body = self.analyze_block(&func.block);
 
         // Pop function scope
         self.exit_scope();
 
         // Clear current function return type
         self.current_function_return_type = None;
-    }
 
-    fn analyze_block(&mut self, block: &Block) {
-        for statement in &block.statements {
-            self.analyze_statement(statement);
-        }
-    }
-
-    // Helper to check if a block contains a return statement
-    fn block_has_return(&mut self, block: &Block) -> bool {
-        self.analyze_block(block);
-        block
-            .statements
-            .iter()
-            .any(|stmt| self.statement_has_return(stmt))
-    }
-
-    // Helper to check if a statement contains a return
-    fn statement_has_return(&self, statement: &Statement) -> bool {
-        match statement {
-            Statement::Return(_) => true,
-            Statement::If(if_stmt) => {
-                // An if statement guarantees a return only if all branches return
-                let if_returns = if_stmt
-                    .block
-                    .statements
-                    .iter()
-                    .any(|s| self.statement_has_return(s));
-
-                let all_elif_return = if_stmt.elif_blocks.iter().all(|elif| {
-                    elif.block
-                        .statements
-                        .iter()
-                        .any(|s| self.statement_has_return(s))
-                });
-
-                let else_returns = if let Some(else_block) = &if_stmt.else_block {
-                    else_block
-                        .statements
-                        .iter()
-                        .any(|s| self.statement_has_return(s))
-                } else {
-                    false
-                };
+        let func = TypedFunction {
+            symbol: id,
+            params,
+            return_type: func_return_type,
+            body,
+            span: func.identifier_span,
+        };
 
-                // Only if we have if, all elifs, and else all returning
-                if_returns && all_elif_return && else_returns && if_stmt.else_block.is_some()
+        // Missing returns, reads before assignment and unreachable code, see flow.rs
+        for diagnostic in flow::check_function(&func, &self.symbols) {
+            if diagnostic.is_error() {
+                self.report(diagnostic);
+            } else {
+                self.lint(Lint::UnreachableCode, diagnostic);
             }
-            _ => false, // while and for loops do not guarantee return (never run), so error on them
         }
+        func
     }
 
-    fn analyze_statement(&mut self, statement: &Statement) {
-        match statement {
-            Statement::VarDecl(var_decl) => {
-                self.analyze_expression(&var_decl.expression);
-
-                // Type checking: check if expression type matches variable type
-                let declared_type = self.token_to_type(&var_decl.type_token);
-                let expr_type = self.infer_expression_type(&var_decl.expression);
+    fn analyze_block(&mut self, block: &Block) -> TypedBlock {
+        TypedBlock {
+            statements: block
+                .statements
+                .iter()
+                .map(|statement| self.analyze_statement(statement))
+                .collect(),
+            span: block.span,
+        }
+    }
 
-                if !declared_type.is_compatible(&expr_type) {
-                    self.errors.push(format!(
-                        "Type mismatch in variable declaration '{}': expected '{}', got '{}'",
-                        var_decl.identifier,
-                        declared_type.to_string(),
-                        expr_type.to_string()
-                    ));
-                }
+    // Same as analyze_block but in a scope of its own
+    fn analyze_scoped_block(&mut self, block: &Block) -> TypedBlock {
+        self.enter_scope();
+        let block = self.analyze_block(block);
+        self.exit_scope();
+        block
+    }
 
-                let type_str = self.token_to_string(&var_decl.type_token);
-                self.declare_symbol(
-                    var_decl.identifier.clone(),
-                    Symbol {
-                        name: var_decl.identifier.clone(),
-                        symbol_type: SymbolType::Variable(type_str),
-                    },
-                );
-            }
-            Statement::Expr(expr) => {
-                self.analyze_expression(expr);
+    fn analyze_statement(&mut self, statement: &Statement) -> TypedStmt {
+        let kind = match &statement.kind {
+            StatementKind::VarDecl(var_decl) => {
+                TypedStmtKind::VarDecl(self.analyze_var_decl(var_decl))
             }
-            Statement::Return(expr) => {
-                self.analyze_expression(expr);
-
-                // Type check return statement
-                let return_type = self.infer_expression_type(expr);
-                if let Some(expected_type) = &self.current_function_return_type {
-                    if !expected_type.is_compatible(&return_type) {
-                        self.errors.push(format!(
-                            "Type mismatch in return statement: expected '{}', got '{}'",
-                            expected_type.to_string(),
-                            return_type.to_string()
-                        ));
-                    }
-                }
+            StatementKind::Expr(expr) => TypedStmtKind::Expr(self.check_expression(expr)),
+            StatementKind::Return(expr) => {
+                TypedStmtKind::Return(self.check_return(expr, statement.span))
             }
-            Statement::Break => {
+            StatementKind::Break => {
                 if self.loop_depth == 0 {
-                    self.errors
-                        .push("'break' statement must be inside a loop".to_string());
+                    self.error(
+                        codes::LOOP_CONTROL_OUTSIDE_LOOP,
+                        statement.span,
+                        "'break' statement must be inside a loop".to_string(),
+                    );
                 }
+                TypedStmtKind::Break
             }
-            Statement::Continue => {
+            StatementKind::Continue => {
                 if self.loop_depth == 0 {
-                    self.errors
-                        .push("'continue' statement must be inside a loop".to_string());
+                    self.error(
+                        codes::LOOP_CONTROL_OUTSIDE_LOOP,
+                        statement.span,
+                        "'continue' statement must be inside a loop".to_string(),
+                    );
                 }
+                TypedStmtKind::Continue
             }
-            Statement::If(if_stmt) => {
-                self.analyze_if_statement(if_stmt);
-            }
-            Statement::While(while_stmt) => {
-                self.analyze_while_statement(while_stmt);
+            StatementKind::If(if_stmt) => TypedStmtKind::If(self.analyze_if_statement(if_stmt)),
+            StatementKind::While(while_stmt) => {
+                TypedStmtKind::While(self.analyze_while_statement(while_stmt))
             }
-            Statement::For(for_stmt) => {
-                self.analyze_for_statement(for_stmt);
+            StatementKind::For(for_stmt) => {
+                TypedStmtKind::For(self.analyze_for_statement(for_stmt))
             }
+        };
+        TypedStmt {
+            kind,
+            span: statement.span,
         }
     }
 
-    fn analyze_expression(&mut self, expr: &Expression) {
-        match expr {
-            Expression::Literal(_) => {
-                // No need to check scope for literals
-                //TODO: Type checking to be added here later
-            }
-            Expression::Identifier(name) => {
-                if let Some(symbol) = self.lookup_symbol(name) {
-                    // Checking if is identifier and not a function
-                    if matches!(symbol.symbol_type, SymbolType::Function { .. }) {
-                        self.errors
-                            .push(format!("'{}' is a function, not a variable", name));
-                    }
-                } else {
-                    self.errors
-                        .push(format!("Variable '{}' is not defined", name));
-                }
-            }
-            Expression::BinaryOperation { left, right, .. } => {
-                self.analyze_expression(left);
-                self.analyze_expression(right);
+    fn check_return(&mut self, expr: &Option<Expression>, span: Span) -> Option<TypedExpr> {
+        let expected_type = self
+            .current_function_return_type
+            .clone()
+            .unwrap_or(Type::Unknown);
+
+        // `return;` only in void functions
+        let Some(expr) = expr else {
+            if !matches!(expected_type, Type::Void | Type::Unknown) {
+                self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "Missing value in return statement: expected '{}'",
+                            expected_type
+                        ),
+                        span,
+                    )
+                    .with_label(format!("expected '{}'", expected_type)),
+                );
             }
-            Expression::UnaryOperation { expression, .. } => {
-                self.analyze_expression(expression);
+            return None;
+        };
+
+        let value = self.check_value(expr);
+        if expected_type == Type::Void {
+            self.report(
+                Diagnostic::error(
+                    codes::MISMATCHED_TYPES,
+                    "Void function cannot return a value".to_string(),
+                    expr.span,
+                )
+                .with_label("remove this value")
+                .with_note("use 'return;' to leave a void function early"),
+            );
+            return Some(value);
+        }
+
+        // Type check return statement
+        self.check_narrowing(&value, &expected_type);
+        if !fits(&value, &expected_type) && expected_type != Type::Unknown {
+            self.error(
+                codes::MISMATCHED_TYPES,
+                expr.span,
+                format!(
+                    "Type mismatch in return statement: expected '{}', got '{}'",
+                    expected_type, value.ty
+                ),
+            );
+        }
+        Some(convert(value, &expected_type))
+    }
+
+    fn check_function_call(&mut self, func_call: &FunctionCallStatement, span: Span) -> TypedExpr {
+        // Checking arguments
+        let args: Vec<TypedExpr> = func_call
+            .args
+            .iter()
+            .map(|arg| self.check_value(arg))
+            .collect();
+
+        let invalid = |args| TypedExpr {
+            kind: TypedExprKind::Call {
+                callee: Callee::Builtin(String::new()),
+                args,
+            },
+            ty: Type::Unknown,
+            span,
+        };
+
+        // Check declaration and get function signature
+        let Some(id) = self.lookup_symbol(&func_call.identifier) else {
+            if builtins::is_builtin(&func_call.identifier) {
+                return self.check_builtin_call(func_call, args, span);
             }
-            Expression::Assignment { left, right } => {
-                self.analyze_expression(left);
-                self.analyze_expression(right);
+            self.report(
+                Diagnostic::error(
+                    codes::UNDEFINED_FUNCTION,
+                    format!("Function '{}' is not defined", func_call.identifier),
+                    func_call.identifier_span,
+                )
+                .with_label("not found in this scope"),
+            );
+            return invalid(args);
+        };
+
+        // Check symbol type
+        let symbol = &self.symbols[id];
+        let declared_at = symbol.span;
+        let (return_type, params) = match &symbol.symbol_type {
+            SymbolType::Variable(_) => {
+                self.error(
+                    codes::NOT_A_FUNCTION,
+                    func_call.identifier_span,
+                    format!("'{}' is a variable, not a function", func_call.identifier),
+                );
+                return invalid(args);
             }
-            Expression::FunctionCall(func_call) => {
-                self.analyze_function_call(func_call);
+            SymbolType::Function {
+                return_type,
+                params,
+            } => (return_type.clone(), params.clone()),
+        };
+
+        // Type check function arguments
+        if args.len() != params.len() {
+            self.report(
+                Diagnostic::error(
+                    codes::WRONG_ARGUMENT_COUNT,
+                    format!(
+                        "Function '{}' expects {} argument(s), got {}",
+                        func_call.identifier,
+                        params.len(),
+                        args.len()
+                    ),
+                    func_call.identifier_span,
+                )
+                .with_secondary(declared_at, "function defined here"),
+            );
+            return TypedExpr {
+                ty: return_type,
+                ..invalid(args)
+            };
+        }
+
+        // Check each argument type
+        let mut converted = Vec::new();
+        for (i, (arg, expected_type)) in args.into_iter().zip(params).enumerate() {
+            self.check_narrowing(&arg, &expected_type);
+            if !fits(&arg, &expected_type) {
+                self.error(
+                    codes::MISMATCHED_TYPES,
+                    arg.span,
+                    format!(
+                        "Type mismatch in argument {} of function '{}': expected '{}', got '{}'",
+                        i + 1,
+                        func_call.identifier,
+                        expected_type,
+                        arg.ty
+                    ),
+                );
             }
+            converted.push(convert(arg, &expected_type));
+        }
+
+        TypedExpr {
+            kind: TypedExprKind::Call {
+                callee: Callee::Function(id),
+                args: converted,
+            },
+            ty: return_type,
+            span,
         }
     }
 
-    fn analyze_function_call(&mut self, func_call: &FunctionCallStatement) {
-        // Check declaration and get function signature
-        let function_info = if let Some(symbol) = self.lookup_symbol(&func_call.identifier) {
-            // Check symbol type
-            match &symbol.symbol_type {
-                SymbolType::Variable(_) => {
-                    self.errors.push(format!(
-                        "'{}' is a variable, not a function",
-                        func_call.identifier
-                    ));
-                    None
-                }
-                SymbolType::Function { params, .. } => Some(params.clone()),
+    // print takes anything, len exactly one array
+    fn check_builtin_call(
+        &mut self,
+        func_call: &FunctionCallStatement,
+        args: Vec<TypedExpr>,
+        span: Span,
+    ) -> TypedExpr {
+        let name = func_call.identifier.as_str();
+        let ty = if name == builtins::LEN {
+            if args.len() != 1 {
+                self.error(
+                    codes::WRONG_ARGUMENT_COUNT,
+                    func_call.identifier_span,
+                    format!("Function 'len' expects 1 argument(s), got {}", args.len()),
+                );
+            } else if !matches!(args[0].ty, Type::Array(..) | Type::Unknown) {
+                self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "Type mismatch in argument 1 of function 'len': expected an array, got '{}'",
+                            args[0].ty
+                        ),
+                        args[0].span,
+                    )
+                    .with_label("not an array"),
+                );
             }
+            Type::Int
         } else {
-            self.errors.push(format!(
-                "Function '{}' is not defined",
-                func_call.identifier
-            ));
-            None
+            Type::Void
         };
 
-        // Checking arguments
-        for arg in &func_call.args {
-            self.analyze_expression(arg);
-        }
-
-        // Type check function arguments if we have function info
-        if let Some(params) = function_info {
-            if func_call.args.len() != params.len() {
-                self.errors.push(format!(
-                    "Function '{}' expects {} argument(s), got {}",
-                    func_call.identifier,
-                    params.len(),
-                    func_call.args.len()
-                ));
-            } else {
-                // Check each argument type
-                for (i, (arg, expected_type_str)) in
-                    func_call.args.iter().zip(params.iter()).enumerate()
-                {
-                    let arg_type = self.infer_expression_type(arg);
-                    let expected_type = Type::from_string(expected_type_str);
-
-                    if !expected_type.is_compatible(&arg_type) {
-                        self.errors.push(format!(
-                            "Type mismatch in argument {} of function '{}': expected '{}', got '{}'",
-                            i + 1,
-                            func_call.identifier,
-                            expected_type.to_string(),
-                            arg_type.to_string()
-                        ));
-                    }
-                }
-            }
+        TypedExpr {
+            kind: TypedExprKind::Call {
+                callee: Callee::Builtin(name.to_string()),
+                args,
+            },
+            ty,
+            span,
         }
     }
 
-    fn analyze_if_statement(&mut self, if_stmt: &IfStatement) {
-        // Condition expression check if (....)
-        self.analyze_expression(&if_stmt.condition);
+    // Checks a condition of if/elif/while/for, `what` names the statement in errors
+    fn check_condition(&mut self, condition: &Expression, what: &str) -> TypedExpr {
+        let typed = self.check_value(condition);
 
         // Type check: condition must be boolean
-        let condition_type = self.infer_expression_type(&if_stmt.condition);
-        if condition_type != Type::Bool && condition_type != Type::Unknown {
-            self.errors.push(format!(
-                "If statement condition must be boolean, got '{}'",
-                condition_type.to_string()
-            ));
+        if typed.ty != Type::Bool && typed.ty != Type::Unknown {
+            self.error(
+                codes::NON_BOOL_CONDITION,
+                condition.span,
+                format!("{} condition must be boolean, got '{}'", what, typed.ty),
+            );
         }
+        typed
+    }
+
+    fn analyze_if_statement(&mut self, if_stmt: &IfStatement) -> TypedIf {
+        // Condition expression check if (....)
+        let condition = self.check_condition(&if_stmt.condition, "If statement");
 
         //checking if block {}
-        self.enter_scope();
-        self.analyze_block(&if_stmt.block);
-        self.exit_scope();
+        let block = self.analyze_scoped_block(&if_stmt.block);
 
         // elifBlock, can be none or many
-        for elif in &if_stmt.elif_blocks {
-            self.analyze_expression(&elif.condition);
-
-            // Type check: elif condition must be boolean
-            let elif_condition_type = self.infer_expression_type(&elif.condition);
-            if elif_condition_type != Type::Bool && elif_condition_type != Type::Unknown {
-                self.errors.push(format!(
-                    "Elif statement condition must be boolean, got '{}'",
-                    elif_condition_type.to_string()
-                ));
-            }
-
-            self.enter_scope();
-            self.analyze_block(&elif.block);
-            self.exit_scope();
-        }
+        let elif_blocks = if_stmt
+            .elif_blocks
+            .iter()
+            .map(|elif| {
+                let condition = self.check_condition(&elif.condition, "Elif statement");
+                (condition, self.analyze_scoped_block(&elif.block))
+            })
+            .collect();
 
         // else block
-        if let Some(else_block) = &if_stmt.else_block {
-            self.enter_scope();
-            self.analyze_block(else_block);
-            self.exit_scope();
+        let else_block = if_stmt
+            .else_block
+            .as_ref()
+            .map(|block| self.analyze_scoped_block(block));
+
+        TypedIf {
+            condition,
+            block,
+            elif_blocks,
+            else_block,
         }
     }
 
-    fn analyze_while_statement(&mut self, while_stmt: &WhileStatement) {
-        self.analyze_expression(&while_stmt.condition);
-
-        // Type check: while condition must be boolean
-        let condition_type = self.infer_expression_type(&while_stmt.condition);
-        if condition_type != Type::Bool && condition_type != Type::Unknown {
-            self.errors.push(format!(
-                "While statement condition must be boolean, got '{}'",
-                condition_type.to_string()
-            ));
-        }
+    fn analyze_while_statement(&mut self, while_stmt: &WhileStatement) -> TypedWhile {
+        let condition = self.check_condition(&while_stmt.condition, "While statement");
 
-        self.enter_scope();
         self.loop_depth += 1;
-        self.analyze_block(&while_stmt.block);
+        let block = self.analyze_scoped_block(&while_stmt.block);
         self.loop_depth -= 1;
-        self.exit_scope();
+
+        TypedWhile { condition, block }
     }
 
-    fn analyze_for_statement(&mut self, for_stmt: &ForStatement) {
+    fn analyze_for_statement(&mut self, for_stmt: &ForStatement) -> TypedFor {
         // makes its own scope variables in parenthesis
         self.enter_scope();
 
         // checking initialization if exists, for (int i= 0)
-        if let Some(init_var) = &for_stmt.init_var {
-            self.analyze_expression(&init_var.expression);
-
-            // Type check initialization
-            let declared_type = self.token_to_type(&init_var.type_token);
-            let expr_type = self.infer_expression_type(&init_var.expression);
-
-            if !declared_type.is_compatible(&expr_type) {
-                self.errors.push(format!(
-                    "Type mismatch in for loop initialization '{}': expected '{}', got '{}'",
-                    init_var.identifier,
-                    declared_type.to_string(),
-                    expr_type.to_string()
-                ));
-            }
-
-            let type_str = self.token_to_string(&init_var.type_token);
-            self.declare_symbol(
-                init_var.identifier.clone(),
-                Symbol {
-                    name: init_var.identifier.clone(),
-                    symbol_type: SymbolType::Variable(type_str),
-                },
-            );
-        }
+        let init_var = for_stmt
+            .init_var
+            .as_ref()
+            .map(|init_var| self.analyze_var_decl(init_var));
 
         // Checking condition if exists
-        if let Some(condition) = &for_stmt.condition {
-            self.analyze_expression(condition);
-
-            // Type check: for condition must be boolean
-            let condition_type = self.infer_expression_type(condition);
-            if condition_type != Type::Bool && condition_type != Type::Unknown {
-                self.errors.push(format!(
-                    "For loop condition must be boolean, got '{}'",
-                    condition_type.to_string()
-                ));
-            }
-        }
+        let condition = for_stmt
+            .condition
+            .as_ref()
+            .map(|condition| self.check_condition(condition, "For loop"));
 
         // Checking update if exists
-        if let Some(update) = &for_stmt.update {
-            self.analyze_expression(update);
-        }
+        let update = for_stmt
+            .update
+            .as_ref()
+            .map(|update| self.check_expression(update));
 
-        // Analyze block
+        // the body is a scope inside that one, a variable in it can shadow `i`
         self.loop_depth += 1;
-        self.analyze_block(&for_stmt.block);
+        let block = self.analyze_scoped_block(&for_stmt.block);
         self.loop_depth -= 1;
 
         self.exit_scope();
+
+        TypedFor {
+            init_var,
+            condition,
+            update,
+            block,
+        }
     }
 
-    pub fn get_errors(&self) -> &[String] {
+    pub fn get_errors(&self) -> &[Diagnostic] {
         &self.errors
     }
 
+    // Warnings alone don't stop compilation
     pub fn is_valid(&self) -> bool {
-        self.errors.is_empty()
+        !self.errors.iter().any(|diagnostic| diagnostic.is_error())
     }
 }
 
-// Main
-pub fn semantic_analysis(ast: &RootList) -> Result<(), Vec<String>> {
-    let mut analyzer = ScopeAnalyzer::new();
-    analyzer.analyze(ast);
+// Main, a checked program comes back with the warnings found on the way
+pub fn semantic_analysis(
+    ast: &RootList,
+    lints: &LintConfig,
+) -> Result<(TypedProgram, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut analyzer = ScopeAnalyzer::with_lints(lints.clone());
+    let program = analyzer.analyze(ast);
 
     if analyzer.is_valid() {
-        println!("Scope and type analysis passed!");
-        Ok(())
+        Ok((program, analyzer.errors))
     } else {
-        println!(
-            "Scope and type analysis failed with {} error(s):",
-            analyzer.get_errors().len()
-        );
-        for (i, error) in analyzer.get_errors().iter().enumerate() {
-            println!("  {}. {}", i + 1, error);
-        }
         Err(analyzer.errors)
     }
 }
@@ -0,0 +1,181 @@
+use crate::lexer::span::Span;
+use crate::lexer::tokens::Token;
+use crate::parser::enums::Constants;
+use crate::semantics::semantic_analysis::{StructDef, Symbol, Type};
+
+// The syntax tree after semantic analysis: every expression knows its type, every
+// name is resolved to the symbol it refers to, and int/float conversions that the
+// source leaves implicit are spelled out as nodes.
+
+// Index into TypedProgram::symbols, one per declaration so shadowed names stay apart
+pub type SymbolId = usize;
+
+#[derive(Debug)]
+pub struct TypedProgram {
+    pub symbols: Vec<Symbol>,
+    pub structs: Vec<StructDef>,
+    pub globals: Vec<TypedVarDecl>, // in initialization order
+    pub functions: Vec<TypedFunction>,
+}
+
+impl TypedProgram {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    // Every Type::Struct in a checked program names one of these
+    pub fn struct_def(&self, name: &str) -> Option<&StructDef> {
+        self.structs.iter().find(|def| def.name == name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&TypedFunction> {
+        self.functions
+            .iter()
+            .find(|func| self.symbols[func.symbol].name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct TypedFunction {
+    pub symbol: SymbolId,
+    pub params: Vec<SymbolId>,
+    pub return_type: Type,
+    pub body: TypedBlock,
+    pub span: Span, // the function's name
+}
+
+#[derive(Debug)]
+pub struct TypedVarDecl {
+    pub symbol: SymbolId,
+    pub value: Option<TypedExpr>, // already converted to the declared type, None for `int x;`
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct TypedBlock {
+    pub statements: Vec<TypedStmt>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct TypedStmt {
+    pub kind: TypedStmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum TypedStmtKind {
+    VarDecl(TypedVarDecl),
+    Expr(TypedExpr),
+    Return(Option<TypedExpr>),
+    Break,
+    Continue,
+    If(TypedIf),
+    While(TypedWhile),
+    For(TypedFor),
+}
+
+#[derive(Debug)]
+pub struct TypedIf {
+    pub condition: TypedExpr,
+    pub block: TypedBlock,
+    pub elif_blocks: Vec<(TypedExpr, TypedBlock)>,
+    pub else_block: Option<TypedBlock>,
+}
+
+#[derive(Debug)]
+pub struct TypedWhile {
+    pub condition: TypedExpr,
+    pub block: TypedBlock,
+}
+
+#[derive(Debug)]
+pub struct TypedFor {
+    pub init_var: Option<TypedVarDecl>,
+    pub condition: Option<TypedExpr>,
+    pub update: Option<TypedExpr>,
+    pub block: TypedBlock,
+}
+
+#[derive(Debug)]
+pub struct TypedExpr {
+    pub kind: TypedExprKind,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum TypedExprKind {
+    Literal(Constants),
+    Variable(SymbolId),
+    Binary {
+        // both operands already have the same type, except for && || << >>
+        left: Box<TypedExpr>,
+        operator: Token,
+        right: Box<TypedExpr>,
+    },
+    Unary {
+        operator: Token,
+        operand: Box<TypedExpr>,
+    },
+    Assignment {
+        target: Box<TypedExpr>, // a Variable, or an Index or Field of one
+        value: Box<TypedExpr>,  // already converted to the target's type
+    },
+    // `x += v`, `++x` and `x++`: the target is evaluated once, read, changed and stored.
+    // `++`/`--` add or subtract a literal 1
+    CompoundAssignment {
+        target: Box<TypedExpr>,
+        operator: Token,       // the binary operator, `+` for `+=`
+        value: Box<TypedExpr>, // already converted to the target's type
+        postfix: bool,         // `x++` gives the value from before the change
+    },
+    Call {
+        callee: Callee,
+        args: Vec<TypedExpr>, // already converted to the parameter types
+    },
+    Index {
+        array: Box<TypedExpr>,
+        index: Box<TypedExpr>,
+    },
+    ArrayLiteral(Vec<TypedExpr>), // elements already converted to the element type
+    Field {
+        object: Box<TypedExpr>,
+        field: usize, // index into the struct's fields, which is also its offset
+    },
+    StructLiteral(Vec<(usize, TypedExpr)>), // (field, value) in source order, values converted
+    IntToFloat(Box<TypedExpr>),
+    FloatToInt(Box<TypedExpr>), // truncates toward zero
+    Invalid,                    // something that failed to check, only in programs with errors
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    Function(SymbolId),
+    Builtin(String),
+}
+
+// One step from a variable into a part of it
+pub enum Step<'a> {
+    Index(&'a TypedExpr),
+    Field(usize),
+}
+
+// `a[i].x` as the variable `a` and the steps `[i]`, `.x`, None for values that
+// aren't stored anywhere
+pub fn place(expr: &TypedExpr) -> Option<(SymbolId, Vec<Step<'_>>)> {
+    match &expr.kind {
+        TypedExprKind::Variable(id) => Some((*id, Vec::new())),
+        TypedExprKind::Index { array, index } => {
+            let (id, mut steps) = place(array)?;
+            steps.push(Step::Index(index));
+            Some((id, steps))
+        }
+        TypedExprKind::Field { object, field } => {
+            let (id, mut steps) = place(object)?;
+            steps.push(Step::Field(*field));
+            Some((id, steps))
+        }
+        _ => None,
+    }
+}
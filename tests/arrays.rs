@@ -0,0 +1,81 @@
+mod common;
+
+use common::{errors, run_both, runtime_error_at};
+use relipmoc::Phase;
+use relipmoc::diagnostics::codes;
+
+fn body(statements: &str) -> String {
+    format!("fn int main() {{ {} return 0; }}", statements)
+}
+
+#[test]
+fn arrays_are_read_written_and_passed_around() {
+    let code = "
+        fn void bump(int[3] a) { a[0] = 99; }
+        fn int[3] make() { return [1, 2, 3]; }
+        fn int sum(int[3] a) { int total = 0; for (int i = 0; i < len(a); i++) { total += a[i]; } return total; }
+        fn int main() {
+            int[3] a = make();
+            bump(a);
+            int[3] b = a;
+            b[1] = 7;
+            float[] c = [1.0, 2];
+            int[2][2] m;
+            m[1][0] = 5;
+            print(a, b, c, m, len(c), len(m[0]), sum(b));
+            return 0;
+        }
+    ";
+    // arrays are values: bump() and b changed their own copies
+    assert_eq!(
+        run_both(code),
+        Ok("[1, 2, 3] [1, 7, 3] [1.0, 2.0] [[0, 0], [5, 0]] 2 2 11\n".to_string())
+    );
+}
+
+#[test]
+fn declared_arrays_start_zeroed() {
+    let code = body("int[2] i; float[1] f; bool[1] b; string[1] s; print(i, f, b, s);");
+    assert_eq!(run_both(&code), Ok("[0, 0] [0.0] [false] []\n".to_string()));
+}
+
+#[test]
+fn indices_are_checked_at_run_time() {
+    for index in ["i", "-i", "i + 1"] {
+        let code = body(&format!("int[2] a; int i = 2; print(a[{}]);", index));
+        assert_eq!(
+            run_both(&code),
+            Err((Phase::Runtime, vec![codes::INDEX_OUT_OF_BOUNDS])),
+            "{}",
+            index
+        );
+    }
+    // a store fails on the whole assignment, a load on the index expression
+    let at = |statements: &str| {
+        let (walker, vm) = runtime_error_at(&body(statements));
+        assert_eq!(walker, vm);
+        walker
+    };
+    assert_eq!(at("int[2] a; int i = 2; a[i] = 1;"), "a[i] = 1");
+    assert_eq!(at("int[2] a; int i = 2; print(a[i] + 1);"), "a[i]");
+}
+
+#[test]
+fn array_types_are_checked() {
+    let check = |statements: &str| errors(&body(statements));
+    assert_eq!(check("int x = 1; print(x[0]);"), [codes::NOT_AN_ARRAY]);
+    assert_eq!(check("int[2] a; print(a[1.5]);"), [codes::NON_INT_INDEX]);
+    assert_eq!(check("int[] a;"), [codes::UNSIZED_ARRAY]);
+    assert_eq!(check("int[2] a = [];"), [codes::EMPTY_ARRAY_LITERAL]);
+    assert_eq!(
+        check("int[2] a; print(a[2]);"),
+        [codes::CONSTANT_INDEX_OUT_OF_BOUNDS]
+    );
+    assert_eq!(
+        check("int[2] a; print(a[-1]);"),
+        [codes::CONSTANT_INDEX_OUT_OF_BOUNDS]
+    );
+    assert_eq!(check("int[2] a = [1, 2, 3];"), [codes::MISMATCHED_TYPES]);
+    assert_eq!(check("int[2] a; a[0] = \"s\";"), [codes::MISMATCHED_TYPES]);
+    assert_eq!(check("int[2] a = [1, true];"), [codes::MISMATCHED_TYPES]);
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const OK: &str = "fn int main() { int x = 2; print(x); return 3; }";
+
+struct Output {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+// Runs the binary with `args`, feeding it `stdin`
+fn relipmoc(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_relipmoc"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the binary starts");
+    // it may exit before reading any of it
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    Output {
+        code: output.status.code().expect("exited"),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+// A file only this test uses, written with `code`
+fn source(name: &str, code: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("relipmoc-{}-{}", std::process::id(), name));
+    fs::write(&path, code).unwrap();
+    path
+}
+
+#[test]
+fn emits_ir_by_default() {
+    let path = source("default.rlp", OK);
+    let output = relipmoc(&[path.to_str().unwrap()], "");
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert!(
+        output.stdout.starts_with("main:\nBeginFunc\n"),
+        "{}",
+        output.stdout
+    );
+    assert!(output.stdout.contains("Return 3\n"));
+    assert_eq!(output.stderr, "");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn emits_the_stages_asked_for() {
+    let tokens = relipmoc(&["--emit=tokens", "-"], OK);
+    assert_eq!(tokens.code, 0);
+    assert!(tokens.stdout.starts_with("1:1\tT_FUNCTION\n1:4\tT_INT\n"));
+    assert!(!tokens.stdout.contains("BeginFunc"));
+
+    let both = relipmoc(&["--emit=ast,ir", "-"], OK);
+    assert!(both.stdout.contains("FunctionStatement"));
+    assert!(both.stdout.contains("BeginFunc"));
+    assert!(!both.stdout.contains("T_FUNCTION"));
+
+    let check = relipmoc(&["--emit=check", "-"], OK);
+    assert_eq!((check.code, check.stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn reads_stdin_and_writes_the_output_file() {
+    let out = std::env::temp_dir().join(format!("relipmoc-{}-out.ir", std::process::id()));
+    let output = relipmoc(&["-", "-o", out.to_str().unwrap()], OK);
+    assert_eq!((output.code, output.stdout.as_str()), (0, ""));
+    let written = fs::read_to_string(&out).unwrap();
+    assert_eq!(written, relipmoc(&["-"], OK).stdout);
+    fs::remove_file(out).unwrap();
+}
+
+#[test]
+fn exit_code_tells_the_failing_phase() {
+    let failing = [
+        ("int x = \"open", 2),
+        ("fn int main() { int x = ; return 0; }", 3),
+        ("fn int main() { return y; }", 4),
+    ];
+    for (code, exit) in failing {
+        let output = relipmoc(&["--emit=check", "-"], code);
+        assert_eq!(output.code, exit, "{}", code);
+        assert_eq!(output.stdout, "");
+        assert!(output.stderr.contains("error["), "{}", output.stderr);
+        assert!(
+            output.stderr.contains("--> <stdin>:1:"),
+            "{}",
+            output.stderr
+        );
+    }
+}
+
+#[test]
+fn usage_and_io_errors_exit_with_one() {
+    for args in [
+        &["--emit=bogus", "-"][..],
+        &["--nope", "-"],
+        &[],
+        &["-o"],
+        &["/no/such/file.rlp"],
+    ] {
+        let output = relipmoc(args, OK);
+        assert_eq!(output.code, 1, "{:?}", args);
+        assert!(output.stderr.starts_with("error: "), "{}", output.stderr);
+    }
+    let help = relipmoc(&["--help"], "");
+    assert_eq!(help.code, 0);
+    assert!(help.stdout.starts_with("Usage: relipmoc"));
+}
+
+#[test]
+fn run_exits_with_what_main_returns() {
+    let output = relipmoc(&["run", "-"], OK);
+    assert_eq!((output.code, output.stdout.as_str()), (3, "2\n"));
+
+    let code = "fn int main() { int zero = 0; print(1); return 1 / zero; }";
+    let output = relipmoc(&["run", "-"], code);
+    assert_eq!((output.code, output.stdout.as_str()), (6, "1\n"));
+    assert!(output.stderr.contains("error[E0601]"), "{}", output.stderr);
+}
@@ -0,0 +1,99 @@
+// Helpers shared by the integration tests: run a program through the library the
+// way the binary does and look at what came out
+#![allow(dead_code)] // every test file uses a different part of this
+
+use relipmoc::diagnostics::diagnostic::{Diagnostic, Severity};
+use relipmoc::semantics::lints::LintConfig;
+use relipmoc::{CompileError, Phase, Session};
+
+// What a program printed, or the phase it stopped in and the codes it stopped with
+pub type Outcome = Result<String, (Phase, Vec<&'static str>)>;
+
+fn failed(error: CompileError) -> (Phase, Vec<&'static str>) {
+    let codes = error.diagnostics.iter().map(|d| d.code).collect();
+    (error.phase, codes)
+}
+
+fn walk(code: &str) -> Result<String, CompileError> {
+    let mut session = Session::new("test.rlp", code);
+    let program = session.check_source()?;
+    let mut out = Vec::new();
+    session.run(&program, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn execute(code: &str) -> Result<String, CompileError> {
+    let mut session = Session::new("test.rlp", code);
+    let module = session.compile()?;
+    let mut out = Vec::new();
+    session.run_ir(&module, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+// Runs main() on the tree walker
+pub fn run(code: &str) -> Outcome {
+    walk(code).map_err(failed)
+}
+
+// Runs main() on the VM, from the generated IR
+pub fn run_vm(code: &str) -> Outcome {
+    execute(code).map_err(failed)
+}
+
+// Runs on both and checks they agree, then returns what they did
+pub fn run_both(code: &str) -> Outcome {
+    let walker = run(code);
+    let vm = run_vm(code);
+    assert_eq!(walker, vm, "tree walker and VM disagree on:\n{}", code);
+    walker
+}
+
+// The source text the runtime error points at, on the tree walker and on the VM
+pub fn runtime_error_at(code: &str) -> (String, String) {
+    let at = |result: Result<String, CompileError>| {
+        let error = result.expect_err("the program ran without an error");
+        assert_eq!(error.phase, Phase::Runtime, "{:?}", error.diagnostics);
+        let span = error.diagnostics[0].primary.span;
+        code[span.start..span.end].to_string()
+    };
+    (at(walk(code)), at(execute(code)))
+}
+
+// Every diagnostic up to and including semantic analysis, errors and warnings
+pub fn check(code: &str) -> Vec<Diagnostic> {
+    check_with(code, LintConfig::default())
+}
+
+pub fn check_with(code: &str, lints: LintConfig) -> Vec<Diagnostic> {
+    let mut session = Session::new("test.rlp", code);
+    session.lints = lints;
+    match session.check_source() {
+        Ok(_) => session.diagnostics().to_vec(),
+        Err(error) => {
+            let mut diagnostics = session.diagnostics().to_vec();
+            diagnostics.extend(error.diagnostics);
+            diagnostics
+        }
+    }
+}
+
+pub fn errors(code: &str) -> Vec<&'static str> {
+    codes(code, Severity::Error)
+}
+
+pub fn warnings(code: &str) -> Vec<&'static str> {
+    codes(code, Severity::Warning)
+}
+
+// How many times one diagnostic code was reported, errors and warnings alike
+pub fn count(code: &str, id: &str) -> usize {
+    check(code).iter().filter(|d| d.code == id).count()
+}
+
+fn codes(code: &str, severity: Severity) -> Vec<&'static str> {
+    check(code)
+        .iter()
+        .filter(|d| d.severity == severity)
+        .map(|d| d.code)
+        .collect()
+}
@@ -0,0 +1,108 @@
+use relipmoc::diagnostics::codes;
+use relipmoc::diagnostics::diagnostic::Diagnostic;
+use relipmoc::diagnostics::renderer::Renderer;
+use relipmoc::lexer::span::{LineMap, Span};
+
+// The span of the `nth` time `text` shows up in `code`
+fn find(code: &str, text: &str, nth: usize) -> Span {
+    let (start, _) = code.match_indices(text).nth(nth).expect("in the code");
+    LineMap::new(code).span(code, start, start + text.len())
+}
+
+#[test]
+fn primary_span_is_underlined_with_its_label() {
+    let code = "fn int main() {\n    x = 5;\n    return 0;\n}";
+    let diagnostic = Diagnostic::error(
+        codes::UNDEFINED_VARIABLE,
+        "Variable 'x' is not defined".to_string(),
+        find(code, "x", 0),
+    )
+    .with_label("not found in this scope");
+    assert_eq!(
+        Renderer::new("main.rlp", code).render(&diagnostic),
+        "\
+error[E0202]: Variable 'x' is not defined
+ --> main.rlp:2:5
+  |
+2 |     x = 5;
+  |     ^ not found in this scope
+"
+    );
+}
+
+#[test]
+fn secondary_labels_and_notes() {
+    let code = "int x = 1;\nint y = 2;\n\n\n\n\n\n\nint x = 3;";
+    let diagnostic = Diagnostic::warning(
+        codes::SHADOWED_VARIABLE,
+        "shadowed".to_string(),
+        find(code, "int x = 3", 0),
+    )
+    .with_secondary(find(code, "x", 0), "first here")
+    .with_secondary(find(code, "y", 0), "")
+    .with_note("one note")
+    .with_note("another");
+    assert_eq!(
+        Renderer::new("a.rlp", code).render(&diagnostic),
+        "\
+warning[W0704]: shadowed
+ --> a.rlp:9:1
+  |
+1 | int x = 1;
+  |     - first here
+2 | int y = 2;
+  |     -
+...
+9 | int x = 3;
+  | ^^^^^^^^^
+  |
+  = note: one note
+  = note: another
+"
+    );
+}
+
+#[test]
+fn underlines_follow_tabs_and_stop_at_the_end_of_the_line() {
+    let code = "\t\tint x = \"é\n\";";
+    let diagnostic = Diagnostic::error(
+        codes::UNCLOSED_STRING,
+        "unclosed".to_string(),
+        find(code, "x = \"é\n\"", 0),
+    );
+    assert_eq!(
+        Renderer::new("t.rlp", code).render(&diagnostic),
+        "error[E0001]: unclosed\n --> t.rlp:1:7\n  |\n1 | \t\tint x = \"é\n  | \t\t    ^^^^^^\n"
+    );
+}
+
+#[test]
+fn gutter_is_as_wide_as_the_largest_line_number() {
+    let code = format!("{}int x;", "\n".repeat(99));
+    let span = find(&code, "x", 0);
+    let diagnostic = Diagnostic::error(codes::UNEXPECTED_CHARACTER, "bad".to_string(), span);
+    assert_eq!(
+        Renderer::new("t.rlp", &code).render(&diagnostic),
+        "error[E0003]: bad\n   --> t.rlp:100:5\n    |\n100 | int x;\n    |     ^\n"
+    );
+}
+
+#[test]
+fn diagnostics_are_separated_by_a_blank_line() {
+    let code = "a b";
+    let renderer = Renderer::new("t.rlp", code);
+    let first = Diagnostic::error(
+        codes::UNEXPECTED_CHARACTER,
+        "a".to_string(),
+        find(code, "a", 0),
+    );
+    let second = Diagnostic::error(
+        codes::UNEXPECTED_CHARACTER,
+        "b".to_string(),
+        find(code, "b", 0),
+    );
+    assert_eq!(
+        renderer.render_all(&[first.clone(), second.clone()]),
+        format!("{}\n{}", renderer.render(&first), renderer.render(&second))
+    );
+}
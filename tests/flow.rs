@@ -0,0 +1,159 @@
+mod common;
+
+use common::{count, errors, run_both};
+use relipmoc::diagnostics::codes;
+
+#[test]
+fn globals_without_a_value_start_zeroed() {
+    let code = r#"
+        int count;
+        float ratio;
+        bool seen;
+        string name;
+        fn void bump() { count += 1; }
+        fn int main() { bump(); bump(); print(count, ratio, seen, name + "!"); return 0; }
+    "#;
+    assert_eq!(errors(code), Vec::<&str>::new());
+    assert_eq!(run_both(code), Ok("2 0.0 false !\n".to_string()));
+}
+
+#[test]
+fn zeroed_globals_still_initialize_in_order() {
+    assert_eq!(
+        errors("int h = g; int g; fn int main() { return h; }"),
+        [codes::GLOBAL_USED_BEFORE_INIT]
+    );
+    assert_eq!(
+        errors("int g; int h = g; fn int main() { return h; }"),
+        Vec::<&str>::new()
+    );
+}
+
+#[test]
+fn initializers_only_use_globals_declared_above() {
+    let used_before = |code: &str| errors(&format!("{} fn int main() {{ return 0; }}", code));
+    let none = Vec::<&str>::new();
+
+    assert_eq!(
+        used_before("int a = b; int b = 1;"),
+        [codes::GLOBAL_USED_BEFORE_INIT]
+    );
+    assert_eq!(
+        used_before("int a = a + 1;"),
+        [codes::GLOBAL_USED_BEFORE_INIT]
+    );
+    assert_eq!(used_before("int b = 1; int a = b;"), none);
+
+    // through the functions an initializer calls
+    let code = "int a = f(); int b = 2; fn int f() { return g(); } fn int g() { return b; }";
+    assert_eq!(used_before(code), [codes::GLOBAL_USED_BEFORE_INIT]);
+    let code = "int b = 2; int a = f(); fn int f() { return b; }";
+    assert_eq!(used_before(code), none);
+
+    // a local with the name of a later global is not that global
+    let code = "int a = f(1); int b = 2; fn int f(int b) { for (int i = 0; i < 1; i++) { int b = i; } return b; }";
+    assert_eq!(used_before(code), none);
+    let code = "int a = f(); int b = 2; fn int f() { if (true) { int b = 1; } return b; }";
+    assert_eq!(used_before(code), [codes::GLOBAL_USED_BEFORE_INIT]);
+}
+
+// a function taking `b`, with `body` and whatever it returns
+fn func(ret: &str, body: &str) -> String {
+    format!(
+        "fn {} f(bool b) {{ {} }} fn int main() {{ f(true); return 0; }}",
+        ret, body
+    )
+}
+
+#[test]
+fn missing_return_follows_every_path() {
+    let missing = |body: &str| count(&func("int", body), codes::MISSING_RETURN);
+
+    assert_eq!(missing("if (b) { return 1; }"), 1);
+    assert_eq!(missing("if (b) { return 1; } elif (!b) { return 2; }"), 1);
+    assert_eq!(missing("while (b) { return 1; }"), 1);
+    assert_eq!(missing("while (false) { return 1; }"), 1);
+    assert_eq!(missing("while (true) { break; }"), 1);
+    assert_eq!(missing("for (int i = 0; i < 3; i++) { return i; }"), 1);
+
+    assert_eq!(missing("return 1;"), 0);
+    assert_eq!(missing("if (b) { return 1; } else { return 2; }"), 0);
+    assert_eq!(
+        missing("if (b) { return 1; } elif (!b) { return 2; } else { return 3; }"),
+        0
+    );
+    assert_eq!(missing("if (true) { return 1; }"), 0);
+    assert_eq!(missing("while (true) { return 1; }"), 0);
+    assert_eq!(missing("while (true) { if (b) { return 1; } }"), 0);
+    assert_eq!(missing("for (;;) { }"), 0);
+    assert_eq!(
+        count(&func("void", "if (b) { return; }"), codes::MISSING_RETURN),
+        0
+    );
+}
+
+#[test]
+fn code_after_a_jump_is_unreachable() {
+    let unreachable = |body: &str| count(&func("void", body), codes::UNREACHABLE_CODE);
+
+    assert_eq!(unreachable("return; print(1);"), 1);
+    assert_eq!(unreachable("while (b) { break; print(1); }"), 1);
+    assert_eq!(unreachable("while (b) { continue; print(1); }"), 1);
+    assert_eq!(
+        unreachable("if (b) { return; } else { return; } print(1);"),
+        1
+    );
+    assert_eq!(unreachable("while (true) { } print(1);"), 1);
+    assert_eq!(unreachable("if (false) { print(1); }"), 1);
+    // one warning for all of the dead statements in a row
+    assert_eq!(unreachable("return; print(1); print(2);"), 1);
+
+    assert_eq!(unreachable("if (b) { return; } print(1);"), 0);
+    assert_eq!(
+        unreachable("while (true) { if (b) { break; } } print(1);"),
+        0
+    );
+    assert_eq!(
+        unreachable("while (b) { if (b) { continue; } print(1); }"),
+        0
+    );
+}
+
+#[test]
+fn variables_are_assigned_before_every_read() {
+    let unassigned = |body: &str| count(&func("void", body), codes::USED_BEFORE_ASSIGNED);
+
+    assert_eq!(unassigned("int x; print(x);"), 1);
+    assert_eq!(unassigned("int x; x += 1;"), 1);
+    assert_eq!(unassigned("int x; x = x + 1;"), 1);
+    assert_eq!(unassigned("int x; if (b) { x = 1; } print(x);"), 1);
+    assert_eq!(
+        unassigned("int x; if (b) { x = 1; } elif (!b) { x = 2; } print(x);"),
+        1
+    );
+    assert_eq!(unassigned("int x; while (b) { x = 1; } print(x);"), 1);
+    assert_eq!(
+        unassigned("int x; for (int i = 0; i < 3; i++) { x = i; } print(x);"),
+        1
+    );
+
+    assert_eq!(unassigned("int x; x = 1; print(x);"), 0);
+    assert_eq!(
+        unassigned("int x; if (b) { x = 1; } else { x = 2; } print(x);"),
+        0
+    );
+    assert_eq!(
+        unassigned("int x; if (b) { x = 1; } elif (!b) { x = 2; } else { x = 3; } print(x);"),
+        0
+    );
+    assert_eq!(
+        unassigned("int x; if (b) { return; } else { x = 1; } print(x);"),
+        0
+    );
+    assert_eq!(
+        unassigned("int x; while (true) { x = 1; break; } print(x);"),
+        0
+    );
+    // arrays and structs start out zeroed
+    assert_eq!(unassigned("int[3] a; print(a[0]);"), 0);
+}
@@ -0,0 +1,335 @@
+mod common;
+
+use common::{errors, run_vm};
+use relipmoc::Session;
+use relipmoc::diagnostics::codes;
+use relipmoc::interpreter::errors::RuntimeError;
+use relipmoc::interpreter::value::Value;
+use relipmoc::interpreter::{ops, vm};
+use relipmoc::ir::tac::{
+    BinaryOp, Constant, Function, Instr, IrType, Label, Module, Operand, UnaryOp,
+};
+use relipmoc::lexer::span::Span;
+
+const BINARY: [BinaryOp; 19] = [
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Mod,
+    BinaryOp::Pow,
+    BinaryOp::Eq,
+    BinaryOp::Ne,
+    BinaryOp::Lt,
+    BinaryOp::Gt,
+    BinaryOp::Le,
+    BinaryOp::Ge,
+    BinaryOp::And,
+    BinaryOp::Or,
+    BinaryOp::Shl,
+    BinaryOp::Shr,
+    BinaryOp::BitAnd,
+    BinaryOp::BitOr,
+    BinaryOp::BitXor,
+];
+
+// a value of each type no operator fails on at run time
+fn sample(ty: IrType) -> Value {
+    match ty {
+        IrType::Int => Value::Int(6),
+        IrType::Float => Value::Float(6.0),
+        IrType::Bool => Value::Bool(true),
+        IrType::Str => Value::Str("s".to_string()),
+    }
+}
+
+const TYPES: [IrType; 4] = [IrType::Int, IrType::Float, IrType::Bool, IrType::Str];
+
+// the generator's table of opcodes and what the runtimes can execute agree
+#[test]
+fn supported_opcodes_are_the_ones_that_run() {
+    for op in BINARY {
+        for ty in TYPES {
+            let ran = ops::binary(op, sample(ty), sample(ty), Span::default()).is_ok();
+            assert_eq!(op.supports(ty), ran, "{}{}", op, ty);
+        }
+    }
+    for op in [UnaryOp::Neg, UnaryOp::Not, UnaryOp::BitNot] {
+        for ty in TYPES {
+            let ran = ops::unary(op, sample(ty), Span::default()).is_ok();
+            assert_eq!(op.supports(ty), ran, "{}{}", op, ty);
+        }
+    }
+}
+
+#[test]
+fn every_checked_operator_has_an_opcode() {
+    let code = r#"
+        fn int main() {
+            int i = 7; float f = 2.5; string s = "a"; bool b = true;
+            print(i + 1, i - 1, i * 2, i / 2, i % 2, i ** 2, i << 1, i >> 1, i & 3, i | 8, i ^ 1, ~i, -i);
+            print(f + 1, f - 1, f * 2, f / 2, f ** 2, -f, f < 3, f >= i);
+            print(s + "b", s < "b", s == "a", b == false, b != true, !b, b && i > 2, b || false);
+            i += 1; f *= 2; s += "c";
+            print(i, f, s);
+            return 0;
+        }
+    "#;
+    assert!(run_vm(code).is_ok(), "{:?}", run_vm(code));
+}
+
+#[test]
+fn calling_an_unknown_function_is_an_error() {
+    let span = Span::new(4, 13, 1, 5);
+    let code = vec![
+        Instr::Call {
+            dest: Operand::Temp(0),
+            func: "missing".to_string(),
+            argc: 0,
+            span,
+        },
+        Instr::Return(None),
+    ];
+    let module = Module {
+        globals: Vec::new(),
+        functions: vec![Function::new("main".to_string(), Vec::new(), code, span)],
+    };
+    let error = vm::execute(&module, &mut Vec::new()).unwrap_err();
+    assert_eq!(
+        error,
+        RuntimeError::UnknownFunction("missing".to_string(), span)
+    );
+}
+
+fn ir(code: &str) -> Module {
+    Session::new("test.rlp", code).compile().unwrap()
+}
+
+fn returns(func: &Function) -> Vec<&Instr> {
+    func.instrs()
+        .filter(|instr| matches!(instr, Instr::Return(_)))
+        .collect()
+}
+
+#[test]
+fn only_void_functions_get_an_implicit_return() {
+    let module = ir("
+        fn int pick(bool b) { if (b) { return 1; } else { return 2; } }
+        fn void log(bool b) { if (b) { print(1); } }
+        fn void early(bool b) { if (b) { return; } print(1); }
+        fn int main() { print(pick(true)); log(true); early(false); return 0; }
+    ");
+    let pick = module.function("pick").unwrap();
+    assert!(
+        returns(pick)
+            .iter()
+            .all(|instr| **instr != Instr::Return(None))
+    );
+    assert!(!matches!(pick.instrs().last(), Some(Instr::Return(_))));
+
+    let log = module.function("log").unwrap();
+    assert_eq!(log.instrs().last(), Some(&Instr::Return(None)));
+    let early = module.function("early").unwrap();
+    assert_eq!(returns(early), [&Instr::Return(None); 2]);
+
+    assert_eq!(
+        run_vm(
+            "fn int pick(bool b) { if (b) { return 1; } else { return 2; } } fn int main() { print(pick(true), pick(false)); return 0; }"
+        ),
+        Ok("1 2\n".to_string())
+    );
+}
+
+#[test]
+fn modules_print_as_three_address_code() {
+    let module = ir(r#"
+        int g = 2;
+        fn int main() {
+            int n = 0;
+            while (n < g) { n = n + 1; }
+            float f = n * 1.5;
+            print(-f, "a\tb");
+            return n;
+        }
+    "#);
+    assert_eq!(
+        module.to_string(),
+        r#"g = 2
+main:
+BeginFunc
+n.2 = 0
+L0:
+t0 = n.2 <i g
+IfZ t0 Goto L1
+t1 = n.2 +i 1
+n.2 = t1
+Goto L0
+L1:
+t2 = IntToFloat n.2
+t3 = t2 *f 1.5
+f.3 = t3
+t4 = -f f.3
+Param t4
+Param "a\tb"
+t5 = Call print, 2
+Return n.2
+EndFunc"#
+    );
+}
+
+#[test]
+fn parameters_are_popped_on_entry() {
+    let module =
+        ir("fn int add(int a, int b) { return a + b; } fn int main() { return add(1, 2); }");
+    let add = module.function("add").unwrap();
+    assert_eq!(add.params, ["a.2", "b.3"]);
+    assert!(
+        add.to_string()
+            .starts_with("add:\nBeginFunc\nPopParam a.2\nPopParam b.3\n")
+    );
+}
+
+#[test]
+fn functions_split_into_basic_blocks() {
+    let assign = |n: usize| Instr::Assign {
+        dest: Operand::Temp(n),
+        src: Operand::Const(Constant::Int(n as i64)),
+    };
+    let code = vec![
+        assign(0),
+        Instr::IfZ {
+            cond: Operand::Temp(0),
+            target: Label(0),
+        },
+        assign(1),
+        Instr::Goto(Label(1)),
+        Instr::Label(Label(0)),
+        assign(2),
+        Instr::Label(Label(1)),
+        Instr::Return(None),
+    ];
+    let func = Function::new("f".to_string(), Vec::new(), code.clone(), Span::default());
+    let blocks: Vec<_> = func.blocks.iter().map(|b| b.instrs.len()).collect();
+    assert_eq!(blocks, [2, 2, 2, 2]);
+    let labels: Vec<_> = func.blocks.iter().map(|b| b.label()).collect();
+    assert_eq!(labels, [None, None, Some(Label(0)), Some(Label(1))]);
+    assert_eq!(func.instrs().cloned().collect::<Vec<_>>(), code);
+}
+
+#[test]
+fn conditions_jump_without_building_a_bool() {
+    let module = ir("
+        fn void f(bool a, bool b, bool c) { if (a && b || c) { print(1); } while (a || b) { } }
+        fn int main() { f(true, false, true); bool v = true && false; print(v); return 0; }
+    ");
+    let f = module.function("f").unwrap();
+    // only the jumps test a, b and c, none of them is combined or copied
+    assert!(
+        f.instrs()
+            .all(|instr| !matches!(instr, Instr::BinOp { .. } | Instr::Assign { .. }))
+    );
+    let tests = f
+        .instrs()
+        .filter(|instr| matches!(instr, Instr::IfZ { .. }))
+        .count();
+    assert_eq!(tests, 5);
+
+    // as a value it's still jumps, there is no && opcode to run
+    let main = module.function("main").unwrap();
+    assert!(main.instrs().all(|instr| !matches!(
+        instr,
+        Instr::BinOp {
+            op: BinaryOp::And | BinaryOp::Or,
+            ..
+        }
+    )));
+    assert!(
+        main.instrs()
+            .any(|instr| matches!(instr, Instr::IfZ { .. }))
+    );
+}
+
+// Every instruction of `name`, printed
+fn code_of(module: &Module, name: &str) -> Vec<String> {
+    let func = module.function(name).unwrap();
+    func.instrs().map(|instr| instr.to_string()).collect()
+}
+
+#[test]
+fn opcodes_carry_their_operand_type() {
+    let module = ir("
+        fn int main() {
+            int i = 3; float f = 1.5;
+            print(i + i, f + f, i < i, f < f, -i, -f);
+            return 0;
+        }
+    ");
+    let code = code_of(&module, "main");
+    for line in [
+        "t0 = i.1 +i i.1",
+        "t1 = f.2 +f f.2",
+        "t2 = i.1 <i i.1",
+        "t3 = f.2 <f f.2",
+        "t4 = -i i.1",
+        "t5 = -f f.2",
+    ] {
+        assert!(
+            code.iter().any(|l| l == line),
+            "{} not in {:#?}",
+            line,
+            code
+        );
+    }
+}
+
+const WIDENING: &str = "
+    fn float half(float x) { return x / 2; }
+    fn float widen(int n) { return n; }
+    fn int main() {
+        int i = 3; float f = i; f = i * 2; float g = f + i;
+        print(half(i), widen(i), g);
+        return 0;
+    }
+";
+
+#[test]
+fn ints_convert_to_float_where_a_float_is_needed() {
+    let module = ir(WIDENING);
+    let conversions = |name: &str| {
+        code_of(&module, name)
+            .into_iter()
+            .filter(|line| line.contains(" = IntToFloat "))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(conversions("half"), ["t0 = IntToFloat 2"]);
+    assert!(code_of(&module, "half")[1].contains(" /f t0"));
+    assert_eq!(conversions("widen").len(), 1);
+    // f = i, f = i * 2, f + i and the argument to half()
+    assert_eq!(conversions("main").len(), 4);
+    assert_eq!(run_vm(WIDENING), Ok("1.5 3.0 9.0\n".to_string()));
+}
+
+#[test]
+fn floats_never_narrow_to_int_implicitly() {
+    for statements in [
+        "int x = 2.5;",
+        "int x = 1; x = 2.5;",
+        "int x = 1; x += 1.5;",
+        "float f = 1; int x = f;",
+        "int x = 7 / 2.0;",
+    ] {
+        let code = format!("fn int main() {{ {} return 0; }}", statements);
+        assert_eq!(
+            errors(&code),
+            [codes::NARROWING_CONVERSION],
+            "{}",
+            statements
+        );
+    }
+    let code =
+        "fn int f() { return 1.5; } fn void g(int n) { } fn int main() { g(f() * 1.0); return 0; }";
+    assert_eq!(
+        errors(code),
+        [codes::NARROWING_CONVERSION, codes::NARROWING_CONVERSION]
+    );
+}
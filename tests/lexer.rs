@@ -0,0 +1,340 @@
+mod common;
+
+use common::{errors, run_both};
+use relipmoc::diagnostics::codes;
+use relipmoc::lexer::lexer::lex;
+use relipmoc::lexer::tokens::Token;
+
+fn tokens(code: &str) -> Vec<Token> {
+    match lex(code.to_string()) {
+        Ok(tokens) => tokens.into_iter().map(|t| t.token).collect(),
+        Err(errors) => panic!("{:?} doesn't lex: {:?}", code, errors),
+    }
+}
+
+// Each error as its code and the source text it points at
+fn lex_errors(code: &str) -> Vec<(&'static str, String)> {
+    match lex(code.to_string()) {
+        Ok(tokens) => panic!("{:?} lexes: {:?}", code, tokens),
+        Err(errors) => errors
+            .iter()
+            .map(|error| {
+                let span = error.span();
+                (
+                    error.to_diagnostic().code,
+                    code[span.start..span.end].to_string(),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn error(code: &'static str, at: &str) -> (&'static str, String) {
+    (code, at.to_string())
+}
+
+// The text of a string literal after its escapes are decoded
+fn string(literal: &str) -> String {
+    match tokens(literal).as_slice() {
+        [Token::T_STRINGLIT(text)] => text.clone(),
+        other => panic!("{:?} isn't one string: {:?}", literal, other),
+    }
+}
+
+#[test]
+fn escapes_are_decoded() {
+    assert_eq!(string(r#""a\nb\tc\rd\0""#), "a\nb\tc\rd\0");
+    assert_eq!(string(r#""\\ \" \'""#), "\\ \" '");
+    assert_eq!(string(r#""\x41\x7f\x7F""#), "A\x7f\x7f");
+    assert_eq!(
+        string(r#""\u{41}\u{e9}\u{1F600}\u{10FFFF}""#),
+        "Aé😀\u{10FFFF}"
+    );
+    assert_eq!(string(r#""é😀""#), "é😀");
+    assert_eq!(string(r#""""#), "");
+}
+
+#[test]
+fn bad_escapes_are_reported_and_the_rest_is_lexed() {
+    assert_eq!(
+        lex_errors(r#""a\qb""#),
+        [error(codes::INVALID_ESCAPE, r"\q")]
+    );
+    assert_eq!(
+        lex_errors(r#""\x4" "\x80" "\xG1""#),
+        [
+            error(codes::INVALID_ESCAPE, r"\x4"),
+            error(codes::INVALID_ESCAPE, r"\x80"),
+            error(codes::INVALID_ESCAPE, r"\x"),
+        ]
+    );
+    assert_eq!(
+        lex_errors(r#""\u{}" "\u{110000}" "\u{D800}" "\u{1234567}" "\u41" "\u{41""#),
+        [
+            error(codes::INVALID_ESCAPE, r"\u{}"),
+            error(codes::INVALID_ESCAPE, r"\u{110000}"),
+            error(codes::INVALID_ESCAPE, r"\u{D800}"),
+            error(codes::INVALID_ESCAPE, r"\u{1234567}"),
+            error(codes::INVALID_ESCAPE, r"\u"),
+            error(codes::INVALID_ESCAPE, r"\u{41"),
+        ]
+    );
+    // every bad escape in a string, and errors after it
+    assert_eq!(
+        lex_errors(r#""\q\z" 1x"#),
+        [
+            error(codes::INVALID_ESCAPE, r"\q"),
+            error(codes::INVALID_ESCAPE, r"\z"),
+            error(codes::INVALID_IDENTIFIER, "1x"),
+        ]
+    );
+}
+
+#[test]
+fn unclosed_strings_point_at_the_quote() {
+    assert_eq!(
+        lex_errors(r#"int x; "abc"#),
+        [error(codes::UNCLOSED_STRING, "\"")]
+    );
+    assert_eq!(
+        lex_errors(r#""abc\""#),
+        [error(codes::UNCLOSED_STRING, "\"")]
+    );
+    assert_eq!(
+        lex_errors(r#""abc\"#),
+        [error(codes::UNCLOSED_STRING, "\"")]
+    );
+}
+
+#[test]
+fn decoded_strings_print_as_written() {
+    let code = r#"fn int main() { print("a\tb", "\x41\u{e9}", "say \"hi\"" + "\\"); return 0; }"#;
+    assert_eq!(run_both(code), Ok("a\tb Aé say \"hi\"\\\n".to_string()));
+}
+
+#[test]
+fn int_literals_in_every_base() {
+    use Token::T_CONST_INT as int;
+    assert_eq!(
+        tokens("42 0 0xFF 0xff 0b1010 0o17 0x0 007"),
+        [
+            int(42),
+            int(0),
+            int(255),
+            int(255),
+            int(10),
+            int(15),
+            int(0),
+            int(7)
+        ]
+    );
+    assert_eq!(
+        tokens("1_000_000 0xFF_FF 0b1111_0000 0o7_7 1__0 1_"),
+        [
+            int(1_000_000),
+            int(0xFFFF),
+            int(0xF0),
+            int(0o77),
+            int(10),
+            int(1)
+        ]
+    );
+}
+
+#[test]
+fn float_literals_with_exponents() {
+    use Token::T_CONST_FLOAT as float;
+    assert_eq!(
+        tokens("1.5 2e10 1.5e-3 1E3 2e+2 1_000.5 0.000_1"),
+        [
+            float(1.5),
+            float(2e10),
+            float(1.5e-3),
+            float(1e3),
+            float(200.0),
+            float(1000.5),
+            float(0.0001)
+        ]
+    );
+    // the sign only belongs to an exponent, anywhere else it's an operator
+    assert_eq!(
+        tokens("1-2"),
+        [
+            Token::T_CONST_INT(1),
+            Token::T_MINUS_OPR,
+            Token::T_CONST_INT(2)
+        ]
+    );
+    assert_eq!(
+        tokens("0x1e-2"),
+        [
+            Token::T_CONST_INT(0x1e),
+            Token::T_MINUS_OPR,
+            Token::T_CONST_INT(2)
+        ]
+    );
+}
+
+#[test]
+fn malformed_numbers_are_invalid() {
+    for text in [
+        "0x", "0xG1", "0b102", "0o8", "0b_", "1.2.3", "1e", "1e+", "1.5e-",
+    ] {
+        assert_eq!(
+            lex_errors(text),
+            [error(codes::INVALID_NUMBER, text)],
+            "{}",
+            text
+        );
+    }
+    assert_eq!(
+        lex_errors("12abc 1e999"),
+        [
+            error(codes::INVALID_IDENTIFIER, "12abc"),
+            error(codes::FLOAT_TOO_LARGE, "1e999"),
+        ]
+    );
+}
+
+#[test]
+fn ints_go_up_to_two_to_the_sixty_three() {
+    assert_eq!(
+        tokens("9223372036854775807 9223372036854775808 0x8000_0000_0000_0000"),
+        [
+            Token::T_CONST_INT(i64::MAX as u64),
+            Token::T_CONST_INT(1 << 63),
+            Token::T_CONST_INT(1 << 63),
+        ]
+    );
+    for text in [
+        "9223372036854775809",
+        "0xFFFF_FFFF_FFFF_FFFF",
+        "99999999999999999999",
+        "0b1_0000000000000000000000000000000000000000000000000000000000000001",
+    ] {
+        assert_eq!(
+            lex_errors(text),
+            [error(codes::INTEGER_TOO_LARGE, text)],
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn only_i64_min_can_use_two_to_the_sixty_three() {
+    let code = "fn int main() { int x = -9223372036854775808; print(x, -0x7FFF_FFFF_FFFF_FFFF - 1); return 0; }";
+    assert_eq!(
+        run_both(code),
+        Ok("-9223372036854775808 -9223372036854775808\n".to_string())
+    );
+    let code = "fn int main() { int x = 9223372036854775808; return 0; }";
+    assert_eq!(errors(code), [codes::INTEGER_TOO_LARGE]);
+}
+
+#[test]
+fn block_comments_nest() {
+    use Token::T_CONST_INT as int;
+    assert_eq!(tokens("1 /* a */ 2"), [int(1), int(2)]);
+    assert_eq!(tokens("1 /* a /* b */ c */ 2"), [int(1), int(2)]);
+    assert_eq!(
+        tokens("1 /* /* /* */ */ */ 2 /**/ 3"),
+        [int(1), int(2), int(3)]
+    );
+    assert_eq!(tokens("1 /* \"*/ 2"), [int(1), int(2)]);
+    assert_eq!(tokens("1 /* # */ 2 /* \n\n */ 3"), [int(1), int(2), int(3)]);
+}
+
+#[test]
+fn unclosed_block_comments_point_at_the_first_opening() {
+    assert_eq!(
+        lex_errors("int x; /* a"),
+        [error(codes::UNCLOSED_COMMENT, "/*")]
+    );
+    assert_eq!(
+        lex_errors("/* a /* b */ c"),
+        [error(codes::UNCLOSED_COMMENT, "/*")]
+    );
+
+    let Err(errors) = lex("1\n  /* /* /* */".to_string()) else {
+        panic!("lexes");
+    };
+    let diagnostic = errors[0].to_diagnostic();
+    assert_eq!(
+        (diagnostic.primary.span.line, diagnostic.primary.span.col),
+        (2, 3)
+    );
+    assert_eq!(
+        diagnostic.notes,
+        ["block comments nest, 2 of them are still open"]
+    );
+}
+
+#[test]
+fn two_hashes_make_a_doc_comment() {
+    use Token::T_DOC_COMMENT as doc;
+    assert_eq!(
+        tokens("## Adds one\n##\n##  indented  "),
+        [
+            doc("Adds one".to_string()),
+            doc(String::new()),
+            doc(" indented".to_string())
+        ]
+    );
+    // one hash, or three and more, is a plain comment
+    assert_eq!(tokens("# plain\n### banner\n##########\n#"), []);
+    assert_eq!(
+        tokens("1 # 2\n3 ## 4"),
+        [
+            Token::T_CONST_INT(1),
+            Token::T_CONST_INT(3),
+            doc("4".to_string())
+        ]
+    );
+}
+
+#[test]
+fn bad_lexemes_are_errors_and_lexing_goes_on() {
+    assert_eq!(
+        lex_errors("int 9lives = 1; x @ y $ \"open"),
+        [
+            error(codes::INVALID_IDENTIFIER, "9lives"),
+            error(codes::UNEXPECTED_CHARACTER, "@"),
+            error(codes::UNEXPECTED_CHARACTER, "$"),
+            error(codes::UNCLOSED_STRING, "\""),
+        ]
+    );
+}
+
+#[test]
+fn odd_endings_lex() {
+    use Token::{T_IDENTIFIER as ident, T_SEMICOLON as semi};
+    let x = || ident("x".to_string());
+    assert_eq!(tokens(""), []);
+    assert_eq!(tokens("   \n\t  \r\n"), []);
+    assert_eq!(tokens("x;  \n\n"), [x(), semi]);
+    assert_eq!(tokens("x"), [x()]);
+    assert_eq!(tokens("x # no newline"), [x()]);
+    assert_eq!(tokens("1"), [Token::T_CONST_INT(1)]);
+    assert_eq!(tokens(";"), [semi]);
+}
+
+#[test]
+fn bitwise_and_logical_operators_are_different_tokens() {
+    use Token::*;
+    assert_eq!(
+        tokens("& && | || ^ ~ % %= ** *"),
+        [
+            T_BIT_AND_OPR,
+            T_AND_OPR,
+            T_BIT_OR_OPR,
+            T_OR_OPR,
+            T_BIT_XOR_OPR,
+            T_BIT_NOT,
+            T_MODULO_OPR,
+            T_MODULO_ASSIGN_OPR,
+            T_EXPONENT_OPR,
+            T_MULTIPLY_OPR,
+        ]
+    );
+}
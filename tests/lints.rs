@@ -0,0 +1,117 @@
+mod common;
+
+use common::{check_with, count};
+use relipmoc::diagnostics::codes;
+use relipmoc::diagnostics::diagnostic::Severity;
+use relipmoc::semantics::lints::{Level, Lint, LintConfig};
+
+// `items` next to a main that calls f, with `body` as main's body
+fn program(items: &str, body: &str) -> String {
+    format!(
+        "{} fn int main() {{ bool b = true; print(b); {} return 0; }}",
+        items, body
+    )
+}
+
+fn lint(code: &str, id: &str) -> usize {
+    count(code, id)
+}
+
+#[test]
+fn unused_variables() {
+    let unused = |body: &str| lint(&program("", body), codes::UNUSED_VARIABLE);
+    assert_eq!(unused("int x = 1;"), 1);
+    assert_eq!(unused("int x = 1; x = 2;"), 1);
+    assert_eq!(unused("int x = 1; print(x);"), 0);
+    assert_eq!(unused("int _x = 1;"), 0);
+    assert_eq!(unused("for (int i = 0; i < 3; i++) { }"), 0);
+}
+
+#[test]
+fn unused_parameters() {
+    let unused = |items: &str| lint(&program(items, "f(1);"), codes::UNUSED_PARAMETER);
+    assert_eq!(unused("fn void f(int p) { }"), 1);
+    assert_eq!(unused("fn void f(int p) { print(p); }"), 0);
+    assert_eq!(unused("fn void f(int _p) { }"), 0);
+}
+
+#[test]
+fn unused_functions() {
+    let unused = |items: &str, body: &str| lint(&program(items, body), codes::UNUSED_FUNCTION);
+    assert_eq!(unused("fn void f() { }", ""), 1);
+    // calling itself doesn't count
+    assert_eq!(unused("fn void f() { f(); }", ""), 1);
+    assert_eq!(unused("fn void f() { }", "f();"), 0);
+    assert_eq!(unused("fn void f() { g(); } fn void g() { }", "f();"), 0);
+    assert_eq!(unused("fn void _f() { }", ""), 0);
+}
+
+#[test]
+fn shadowed_variables() {
+    let shadowed = |body: &str| lint(&program("", body), codes::SHADOWED_VARIABLE);
+    assert_eq!(
+        shadowed("int x = 1; if (b) { int x = 2; print(x); } print(x);"),
+        1
+    );
+    assert_eq!(shadowed("if (b) { bool b = false; print(b); }"), 1);
+    assert_eq!(
+        shadowed("if (b) { int x = 1; print(x); } else { int x = 2; print(x); }"),
+        0
+    );
+    assert_eq!(
+        lint(
+            &program("int g = 1;", "int g = 2; print(g);"),
+            codes::SHADOWED_VARIABLE
+        ),
+        1
+    );
+}
+
+#[test]
+fn dead_assignments() {
+    let dead = |body: &str| lint(&program("", body), codes::DEAD_ASSIGNMENT);
+    assert_eq!(dead("int x; x = 1; x = 2; print(x);"), 1);
+    assert_eq!(dead("int x = 1; print(x); x = 2;"), 1);
+    assert_eq!(dead("int x; x = 1; print(x); x = 2; print(x);"), 0);
+    assert_eq!(
+        dead("int x; if (b) { x = 1; } else { x = 2; } print(x);"),
+        0
+    );
+    assert_eq!(
+        dead("int x = 0; while (b) { x = x + 1; b = x < 3; } print(x);"),
+        0
+    );
+}
+
+#[test]
+fn bool_comparisons() {
+    let compared = |body: &str| lint(&program("", body), codes::BOOL_COMPARISON);
+    assert_eq!(compared("print(b == true);"), 1);
+    assert_eq!(compared("print(false != b);"), 1);
+    assert_eq!(compared("bool c = false; print(b == c);"), 0);
+    assert_eq!(compared("print(1 == 1);"), 0);
+}
+
+#[test]
+fn lints_can_be_allowed_or_denied() {
+    let code = program("", "int x = 1;");
+
+    let severities = |config: LintConfig| -> Vec<Severity> {
+        check_with(&code, config)
+            .iter()
+            .filter(|d| d.code == codes::UNUSED_VARIABLE)
+            .map(|d| d.severity)
+            .collect()
+    };
+
+    assert_eq!(severities(LintConfig::default()), [Severity::Warning]);
+    let mut config = LintConfig::default();
+    config.set(Lint::UnusedVariable, Level::Allow);
+    assert_eq!(severities(config), []);
+    let mut config = LintConfig::default();
+    config.set(Lint::UnusedVariable, Level::Deny);
+    assert_eq!(severities(config), [Severity::Error]);
+    let mut config = LintConfig::default();
+    config.set_all(Level::Allow);
+    assert!(check_with(&code, config).is_empty());
+}
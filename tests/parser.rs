@@ -0,0 +1,228 @@
+mod common;
+
+use common::{errors, run_both};
+use relipmoc::Session;
+use relipmoc::diagnostics::codes;
+use relipmoc::lexer::tokens::Token;
+use relipmoc::parser::enums::{Constants, Expression, ExpressionKind, Root};
+
+// The expression as the parser grouped it, every operation in parentheses
+fn grouped(expr: &str) -> String {
+    let mut session = Session::new("test.rlp", format!("int x = {};", expr));
+    let tokens = session.lex().expect("lexes");
+    let ast = session.parse(tokens).expect("parses");
+    match ast.first() {
+        Some(Root::Var(var)) => render(var.expression.as_ref().expect("has a value")),
+        _ => unreachable!("a global declaration"),
+    }
+}
+
+fn render(expr: &Expression) -> String {
+    let op = |token: &Token| token.to_string().trim_matches('\'').to_string();
+    match &expr.kind {
+        ExpressionKind::Literal(Constants::Int(i)) => i.to_string(),
+        ExpressionKind::Literal(Constants::Bool(b)) => b.to_string(),
+        ExpressionKind::Identifier(name) => name.clone(),
+        ExpressionKind::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => format!("({} {} {})", render(left), op(operator), render(right)),
+        ExpressionKind::UnaryOperation {
+            operator,
+            expression,
+        } => format!("({}{})", op(operator), render(expression)),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+// Binary operators from loosest to tightest, one level per entry
+const LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+    &["**"],
+];
+
+#[test]
+fn tighter_levels_group_first() {
+    for (i, loose) in LEVELS.iter().enumerate() {
+        for tight in &LEVELS[i + 1..] {
+            for x in *loose {
+                for y in *tight {
+                    let expected = format!("(a {} (b {} c))", x, y);
+                    assert_eq!(grouped(&format!("a {} b {} c", x, y)), expected);
+                    let expected = format!("((a {} b) {} c)", y, x);
+                    assert_eq!(grouped(&format!("a {} b {} c", y, x)), expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn operators_on_one_level_are_left_associative() {
+    for level in &LEVELS[..LEVELS.len() - 1] {
+        for x in *level {
+            for y in *level {
+                let expected = format!("((a {} b) {} c)", x, y);
+                assert_eq!(grouped(&format!("a {} b {} c", x, y)), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn power_is_right_associative() {
+    assert_eq!(grouped("a ** b ** c"), "(a ** (b ** c))");
+    assert_eq!(grouped("a ** b ** c ** d"), "(a ** (b ** (c ** d)))");
+    assert_eq!(grouped("(a ** b) ** c"), "((a ** b) ** c)");
+    assert_eq!(
+        grouped("a * b ** c ** d * e"),
+        "((a * (b ** (c ** d))) * e)"
+    );
+}
+
+#[test]
+fn unary_operators_bind_tighter_than_power() {
+    // `-2` is one literal, `-a` a minus on `a`, both are the base of the power
+    assert_eq!(grouped("-2 ** 2"), "(-2 ** 2)");
+    assert_eq!(grouped("-a ** 2"), "((-a) ** 2)");
+    assert_eq!(grouped("2 ** -a"), "(2 ** (-a))");
+    assert_eq!(grouped("-(2 ** 2)"), "(-(2 ** 2))");
+    assert_eq!(grouped("!a && b"), "((!a) && b)");
+    assert_eq!(grouped("~a & b"), "((~a) & b)");
+    assert_eq!(grouped("- -a"), "(-(-a))");
+
+    let code =
+        "fn int main() { int a = 2; print(-2 ** 2, -a ** 2, -(2 ** 2), 2 ** 3 ** 2); return 0; }";
+    assert_eq!(run_both(code), Ok("4 4 -4 512\n".to_string()));
+}
+
+#[test]
+fn array_sizes_past_the_limit_are_rejected() {
+    let code = "fn int main() { int[16777216] ok; int[16777217] big; return 0; }";
+    assert_eq!(errors(code), [codes::ARRAY_TOO_LARGE]);
+    let code = "fn int main() { int[9223372036854775807] big; return 0; }";
+    assert_eq!(errors(code), [codes::ARRAY_TOO_LARGE]);
+}
+
+// Every global and function with the doc comment the parser gave it
+fn docs(code: &str) -> Vec<(String, Option<String>)> {
+    let mut session = Session::new("test.rlp", code);
+    let tokens = session.lex().expect("lexes");
+    let ast = session.parse(tokens).expect("parses");
+    ast.into_iter()
+        .filter_map(|root| match root {
+            Root::Var(var) => Some((var.identifier, var.doc)),
+            Root::Func(func) => Some((func.identifier, func.doc)),
+            Root::Struct(_) => None,
+        })
+        .collect()
+}
+
+fn doc(name: &str, text: Option<&str>) -> (String, Option<String>) {
+    (name.to_string(), text.map(str::to_string))
+}
+
+#[test]
+fn doc_comments_attach_to_the_item_right_below() {
+    let code = "
+        ## How many there are
+        ## at most
+        int limit = 3;
+        ## Runs first
+        fn int main() { return 0; }
+    ";
+    assert_eq!(
+        docs(code),
+        [
+            doc("limit", Some("How many there are\nat most")),
+            doc("main", Some("Runs first")),
+        ]
+    );
+}
+
+#[test]
+fn separated_doc_comments_do_not_attach() {
+    let code = "
+        ## nothing below this one
+
+        int a = 1; ## a trailing note
+        fn int main() { return a; }
+        ## dropped
+
+        ## kept
+        int b = 2;
+    ";
+    assert_eq!(
+        docs(code),
+        [doc("a", None), doc("main", None), doc("b", Some("kept"))]
+    );
+}
+
+// The syntax errors in code as their code and the line they're on
+fn syntax_errors(code: &str) -> Vec<(&'static str, usize)> {
+    let mut session = Session::new("test.rlp", code);
+    let tokens = session.lex().expect("lexes");
+    match session.parse(tokens) {
+        Ok(_) => Vec::new(),
+        Err(error) => error
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.primary.span.line))
+            .collect(),
+    }
+}
+
+#[test]
+fn every_syntax_error_is_reported_in_one_run() {
+    let code = "
+        fn int main() {
+            int x = ;
+            int y = 2
+            print(y);
+            while (x < ) { x = x + 1; }
+            return 0;
+        }
+        fn int f( { return 1; }
+        fn int g() { return 2 }
+        int z = 3 +;
+    ";
+    assert_eq!(
+        syntax_errors(code),
+        [
+            (codes::UNEXPECTED_TOKEN, 3),
+            (codes::UNEXPECTED_TOKEN, 5),
+            (codes::UNEXPECTED_TOKEN, 6),
+            (codes::EXPECTED_TYPE, 9),
+            (codes::UNEXPECTED_TOKEN, 10),
+            (codes::UNEXPECTED_TOKEN, 11),
+        ]
+    );
+}
+
+#[test]
+fn recovery_skips_to_the_next_statement() {
+    // the bad `{ ... }` is skipped whole, the code after it still parses
+    let code = "fn int main() { int = { 1; 2; } int y = ; return 0; } fn void f() { return; }";
+    assert_eq!(
+        syntax_errors(code),
+        [
+            (codes::EXPECTED_IDENTIFIER, 1),
+            (codes::UNEXPECTED_TOKEN, 1)
+        ]
+    );
+    assert_eq!(
+        syntax_errors("fn int main() { return 0; }\nfn"),
+        [(codes::UNEXPECTED_EOF, 2)]
+    );
+    assert_eq!(syntax_errors("fn int main() { return 0; }"), []);
+}
@@ -0,0 +1,318 @@
+mod common;
+
+use common::{run_both, runtime_error_at};
+use relipmoc::Phase;
+use relipmoc::diagnostics::codes;
+
+// a shadowing local is its own variable on both runtimes, it never writes through
+// to the variable it shadows
+
+#[test]
+fn block_local_shadows_local() {
+    let code = "fn int main() { int x = 1; if (true) { int x = 2; } print(x); return 0; }";
+    assert_eq!(run_both(code), Ok("1\n".to_string()));
+}
+
+#[test]
+fn local_shadows_global() {
+    let code = "
+        int x = 1;
+        fn void f() { int x = 7; print(x); }
+        fn int main() { f(); print(x); return 0; }
+    ";
+    assert_eq!(run_both(code), Ok("7\n1\n".to_string()));
+}
+
+#[test]
+fn callee_sees_global_not_callers_local() {
+    let code = "
+        int x = 1;
+        fn void f() { print(x); }
+        fn int main() { int x = 5; f(); print(x); return 0; }
+    ";
+    assert_eq!(run_both(code), Ok("1\n5\n".to_string()));
+}
+
+// calls happen in source order, and a global read before a call that changes it
+// keeps the value it had
+
+const TRACE: &str = "
+    int g = 1;
+    fn int bump(int n) { g += n; return g; }
+    fn int trace(int n) { print(n); return n; }
+";
+
+#[test]
+fn calls_run_left_to_right() {
+    let code = format!(
+        "{} fn int main() {{ print(trace(1) + trace(2) * trace(3)); print(trace(trace(4) + 1), trace(6)); return 0; }}",
+        TRACE
+    );
+    assert_eq!(
+        run_both(&code),
+        Ok("1\n2\n3\n7\n4\n5\n6\n5 6\n".to_string())
+    );
+}
+
+#[test]
+fn globals_read_before_a_call_keep_their_value() {
+    let printed = |statements: &str| {
+        let code = format!("{} fn int main() {{ {} return 0; }}", TRACE, statements);
+        run_both(&code).unwrap()
+    };
+    assert_eq!(printed("print(g + bump(10), g);"), "12 11\n");
+    assert_eq!(printed("print(bump(10) + g, g);"), "22 11\n");
+    assert_eq!(printed("print(g, bump(1), g);"), "1 2 2\n");
+    assert_eq!(
+        printed("int[2] a = [g, bump(1)]; print(a[0], a[1]);"),
+        "1 2\n"
+    );
+    assert_eq!(printed("int x = g - bump(1) * g; print(x);"), "-3\n");
+}
+
+fn recursion(depth: usize) -> String {
+    format!(
+        "fn int f(int n) {{ if (n == 0) {{ return 0; }} return f(n - 1) + 1; }}
+         fn int main() {{ print(f({})); return 0; }}",
+        depth
+    )
+}
+
+// main is one frame, f(998) down to f(0) are the other 999
+#[test]
+fn recursion_up_to_the_call_limit_runs() {
+    assert_eq!(run_both(&recursion(998)), Ok("998\n".to_string()));
+}
+
+#[test]
+fn recursion_past_the_call_limit_is_a_stack_overflow() {
+    for depth in [999, 100_000] {
+        let outcome = run_both(&recursion(depth));
+        assert_eq!(outcome, Err((Phase::Runtime, vec![codes::STACK_OVERFLOW])));
+    }
+}
+
+#[test]
+fn nested_arrays_over_the_size_limit_fail_to_allocate() {
+    // 4096 * 4096 is the limit, one more column goes over it
+    let code = "fn int main() { int[4096][4097] grid; print(len(grid)); return 0; }";
+    let outcome = run_both(code);
+    assert_eq!(
+        outcome,
+        Err((Phase::Runtime, vec![codes::ALLOCATION_FAILED]))
+    );
+
+    let code = "struct P { int[2] xy; } fn int main() { P[8388609] ps; return 0; }";
+    let outcome = run_both(code);
+    assert_eq!(
+        outcome,
+        Err((Phase::Runtime, vec![codes::ALLOCATION_FAILED]))
+    );
+}
+
+// both runtimes point at the expression that failed, not at the function it is in
+#[test]
+fn runtime_errors_point_at_the_failing_expression() {
+    let at = |statements: &str, expected: &str| {
+        let code = format!(
+            "struct P {{ int[2] xy; }} fn int id(int x) {{ return x; }} fn int main() {{ {} return 0; }}",
+            statements
+        );
+        let (walker, vm) = runtime_error_at(&code);
+        assert_eq!(
+            (walker.as_str(), vm.as_str()),
+            (expected, expected),
+            "{}",
+            statements
+        );
+    };
+
+    at("int z = 0; print(1 + 7 / z);", "7 / z");
+    at("int m = 9223372036854775807; int n = -m - 2;", "-m - 2");
+    at("int m = -9223372036854775807 - 1; print(-m);", "-m");
+    at("int s = 1 << id(64);", "1 << id(64)");
+    at("int[3] a; print(a[id(3)]);", "a[id(3)]");
+    at("int[2][2] g; print(g[0][id(2)] + 1);", "g[0][id(2)]");
+    at("int[2][2] g; g[id(5)][0] = 1;", "g[id(5)][0] = 1");
+    at("int[2] a; a[id(-1)] += 1;", "a[id(-1)] += 1");
+    at("P p; p.xy[id(2)] = 1;", "p.xy[id(2)] = 1");
+    at("int i = 9223372036854775807; i++;", "i++");
+    at("print(id(id(1)) ** -1);", "id(id(1)) ** -1");
+    at("int[4096][4097] big;", "int[4096][4097] big;");
+}
+
+fn body(statements: &str) -> String {
+    format!("fn int main() {{ {} return 0; }}", statements)
+}
+
+// an operand is read when it's evaluated, a ++ or assignment further right in the
+// same expression doesn't change what was already read
+
+#[test]
+fn increments_after_a_read_do_not_change_it() {
+    let printed = |statements: &str| run_both(&body(statements)).unwrap();
+    assert_eq!(printed("int x = 1; print(x + x++, x);"), "2 2\n");
+    assert_eq!(printed("int k = 1; print(k++ + k++, k);"), "3 3\n");
+    assert_eq!(printed("int k = 1; print(++k + k, k);"), "4 2\n");
+    assert_eq!(printed("int x = 1; print(x - --x, x);"), "1 0\n");
+    assert_eq!(
+        printed("int x = 1; int[2] a = [x, x++]; print(a[0], a[1]);"),
+        "1 1\n"
+    );
+}
+
+#[test]
+fn assignments_after_a_read_do_not_change_it() {
+    let printed = |statements: &str| run_both(&body(statements)).unwrap();
+    assert_eq!(printed("int z = 1; print(z + (z = 5), z);"), "6 5\n");
+    assert_eq!(printed("int z = 1; print((z = 5) + z++, z);"), "10 6\n");
+    assert_eq!(printed("int z = 1; print(z * (z += 2), z);"), "3 3\n");
+}
+
+#[test]
+fn indices_are_read_before_the_value_is_evaluated() {
+    let printed = |statements: &str| run_both(&body(statements)).unwrap();
+    let a = "int[3] a = [1, 2, 3]; int i = 0;";
+    assert_eq!(
+        printed(&format!("{} a[i] += i++; print(a[0], a[1], i);", a)),
+        "1 2 1\n"
+    );
+    assert_eq!(
+        printed(&format!("{} a[i] = i++ + 5; print(a[0], a[1], i);", a)),
+        "5 2 1\n"
+    );
+    assert_eq!(printed(&format!("{} print(a[i] + a[i++], i);", a)), "2 1\n");
+    assert_eq!(
+        printed("int[2][2] m = [[1, 2], [3, 4]]; int i = 0; print(m[i][i++], i);"),
+        "1 1\n"
+    );
+}
+
+#[test]
+fn compound_assignments_and_increments_update_in_place() {
+    let printed = |statements: &str| run_both(&body(statements)).unwrap();
+    assert_eq!(
+        printed("int x = 7; x += 3; x -= 1; x *= 4; x /= 6; x %= 4; x <<= 3; x >>= 1; print(x);"),
+        "8\n"
+    );
+    assert_eq!(
+        printed("float f = 1.5; f += 1; f *= 2; f++; string s = \"a\"; s += \"b\"; print(f, s);"),
+        "6.0 ab\n"
+    );
+    // prefix gives the new value, postfix the old one
+    assert_eq!(
+        printed("int i = 1; print(i++, i, ++i, i--, --i, i);"),
+        "1 2 3 3 1 1\n"
+    );
+    assert_eq!(printed("int x = 1; print(x += 2, x);"), "3 3\n");
+}
+
+#[test]
+fn compound_targets_are_evaluated_once() {
+    let code = format!(
+        "{} fn int main() {{ int[3] a; a[trace(1)] += 5; a[trace(2)]++; print(a[1], a[2]); return 0; }}",
+        TRACE
+    );
+    assert_eq!(run_both(&code), Ok("1\n2\n5 1\n".to_string()));
+}
+
+#[test]
+fn values_of_every_type_print() {
+    let code = body(
+        r#"int i = 7 / 2; float f = 7.0 / 2; bool b = i < 4; string s = "a" + "b";
+        print(i, f, b, s, -i, 2.0, 0.1 + 0.2, 1e20, !b);"#,
+    );
+    assert_eq!(
+        run_both(&code),
+        Ok("3 3.5 true ab -3 2.0 0.30000000000000004 1e20 false\n".to_string())
+    );
+}
+
+#[test]
+fn loops_break_and_continue() {
+    let code = body(
+        "int total = 0;
+        for (int i = 0; i < 10; i++) {
+            if (i % 2 == 0) { continue; }
+            if (i > 7) { break; }
+            total += i;
+        }
+        int n = 0;
+        while (true) { n++; if (n == 5) { break; } }
+        print(total, n);",
+    );
+    assert_eq!(run_both(&code), Ok("16 5\n".to_string()));
+}
+
+#[test]
+fn calls_bind_parameters_and_return_early() {
+    let code = "
+        fn int fib(int n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+        fn float half(float x) { return x / 2; }
+        fn string greet(string name, bool loud) {
+            if (loud) { return name + \"!\"; }
+            return name;
+        }
+        fn void count(int n) {
+            for (int i = 0; ; i++) { if (i == n) { return; } print(i); }
+        }
+        fn int main() { print(fib(15), half(5), greet(\"hi\", true), greet(\"hi\", false)); count(2); return 0; }
+    ";
+    assert_eq!(run_both(code), Ok("610 2.5 hi! hi\n0\n1\n".to_string()));
+}
+
+#[test]
+fn division_by_zero_is_a_runtime_error() {
+    for expr in ["1 / zero", "1 % zero"] {
+        let code = format!(
+            "fn int main() {{ int zero = 0; print({}); return 0; }}",
+            expr
+        );
+        assert_eq!(
+            run_both(&code),
+            Err((Phase::Runtime, vec![codes::DIVISION_BY_ZERO]))
+        );
+        assert_eq!(
+            runtime_error_at(&code),
+            (expr.to_string(), expr.to_string())
+        );
+    }
+}
+
+// the right side of && and || only runs when it decides the result
+
+#[test]
+fn logical_operators_short_circuit_in_values() {
+    let code = format!(
+        "{} fn int main() {{
+            bool a = g > 5 && trace(1) > 0;
+            bool b = g < 5 || trace(2) > 0;
+            bool c = g < 5 && trace(3) > 0;
+            bool d = g > 5 || trace(4) > 0 || trace(5) > 0;
+            print(a, b, c, d);
+            return 0;
+        }}",
+        TRACE
+    );
+    assert_eq!(
+        run_both(&code),
+        Ok("3\n4\nfalse true true true\n".to_string())
+    );
+}
+
+#[test]
+fn logical_operators_short_circuit_in_conditions() {
+    let code = format!(
+        "{} fn int main() {{
+            if (g > 5 && trace(1) > 0) {{ print(10); }}
+            if (g < 5 || trace(2) > 0) {{ print(20); }}
+            if ((g > 5 || trace(3) > 0) && (g < 5 || trace(4) > 0)) {{ print(30); }}
+            int n = 0;
+            while (n < 2 && trace(n) >= 0) {{ n++; }}
+            for (int i = 0; i < 1 || trace(100) < 0; i++) {{ }}
+            return 0;
+        }}",
+        TRACE
+    );
+    assert_eq!(run_both(&code), Ok("20\n3\n30\n0\n1\n100\n".to_string()));
+}
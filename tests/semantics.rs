@@ -0,0 +1,325 @@
+mod common;
+
+use common::{errors, run_both, warnings};
+use relipmoc::diagnostics::codes;
+use relipmoc::parser::enums::Constants;
+use relipmoc::semantics::typed_ast::{
+    TypedExpr, TypedExprKind, TypedProgram, TypedStmt, TypedStmtKind,
+};
+use relipmoc::{Phase, Session};
+
+fn body(statements: &str) -> String {
+    format!("fn int main() {{ {} return 0; }}", statements)
+}
+
+#[test]
+fn strings_only_join_and_compare() {
+    for op in ["+", "==", "!=", "<", ">", "<=", ">="] {
+        let code = body(&format!("print(\"a\" {} \"b\");", op));
+        assert_eq!(errors(&code), Vec::<&str>::new(), "{}", op);
+    }
+    for op in ["-", "*", "/", "**"] {
+        let code = body(&format!("print(\"a\" {} \"b\");", op));
+        assert_eq!(errors(&code), [codes::INVALID_OPERAND], "{}", op);
+    }
+    // int only operators point at each operand that isn't one
+    let code = body("print(\"a\" % \"b\");");
+    assert_eq!(errors(&code), [codes::INVALID_OPERAND; 2]);
+}
+
+#[test]
+fn bools_only_test_for_equality() {
+    for op in ["==", "!=", "&&", "||"] {
+        let code = body(&format!("print(true {} false);", op));
+        assert_eq!(errors(&code), Vec::<&str>::new(), "{}", op);
+    }
+    for op in ["+", "-", "*", "/", "**", "<", ">", "<=", ">="] {
+        let code = body(&format!("print(true {} false);", op));
+        assert_eq!(errors(&code), [codes::INVALID_OPERAND], "{}", op);
+    }
+}
+
+#[test]
+fn string_operators_run() {
+    let code = body("print(\"ab\" + \"c\", \"a\" < \"b\", \"b\" <= \"a\", \"x\" == \"x\");");
+    assert_eq!(run_both(&code), Ok("abc true false true\n".to_string()));
+}
+
+#[test]
+fn remainder_is_int_only() {
+    assert_eq!(
+        errors(&body("print(7 % 2); int i = 7; i %= 2;")),
+        Vec::<&str>::new()
+    );
+    assert_eq!(errors(&body("print(7.5 % 2);")), [codes::INVALID_OPERAND]);
+    assert_eq!(errors(&body("print(7 % 2.0);")), [codes::INVALID_OPERAND]);
+    assert_eq!(
+        errors(&body("float f = 7.5; f %= 2;")),
+        [codes::INVALID_OPERAND]
+    );
+}
+
+#[test]
+fn for_body_can_shadow_the_loop_variable() {
+    let code = body("for (int i = 0; i < 3; i++) { int i = 5; print(i); }");
+    assert_eq!(errors(&code), Vec::<&str>::new());
+    assert_eq!(warnings(&code), [codes::SHADOWED_VARIABLE]);
+    assert_eq!(run_both(&code), Ok("5\n5\n5\n".to_string()));
+
+    // and `i` is gone after the loop
+    let code = body("for (int i = 0; i < 3; i++) { } int i = 1; print(i);");
+    assert_eq!(errors(&code), Vec::<&str>::new());
+}
+
+#[test]
+fn functions_can_be_called_before_they_are_defined() {
+    let code = "
+        fn int main() { print(is_even(10), is_odd(7), later); return 0; }
+        fn bool is_even(int n) { if (n == 0) { return true; } return is_odd(n - 1); }
+        fn bool is_odd(int n) { if (n == 0) { return false; } return is_even(n - 1); }
+        int later = twice(4);
+        fn int twice(int n) { return n * 2; }
+    ";
+    assert!(errors(code).is_empty());
+    assert_eq!(run_both(code), Ok("true true 8\n".to_string()));
+}
+
+#[test]
+fn hoisted_signatures_are_still_checked() {
+    let code = "fn int main() { return f(1, 2); } fn int f(int a) { return a; }";
+    assert_eq!(errors(code), [codes::WRONG_ARGUMENT_COUNT]);
+    let code = "fn int main() { string s = f(); return 0; } fn int f() { return 1; }";
+    assert_eq!(errors(code), [codes::MISMATCHED_TYPES]);
+    let code = "fn int main() { return missing(); }";
+    assert_eq!(errors(code), [codes::UNDEFINED_FUNCTION]);
+    let code = "fn int f() { return 1; } fn int main() { return 0; } fn int f() { return 2; }";
+    assert_eq!(errors(code), [codes::REDECLARATION]);
+}
+
+// The checked value of `x` declared as `decl`, after `int i = 1; float f = 2.5;`,
+// written out with the type of every node
+fn typed(decl: &str) -> String {
+    let code = body(&format!("int i = 1; float f = 2.5; {} print(x);", decl));
+    let mut session = Session::new("test.rlp", code);
+    let checked = session.check_source().expect("checks");
+    let program = &checked.program;
+    let main = program.function("main").unwrap();
+    let TypedStmtKind::VarDecl(var) = &main.body.statements[2].kind else {
+        panic!("a declaration");
+    };
+    render(program, var.value.as_ref().unwrap())
+}
+
+fn render(program: &TypedProgram, expr: &TypedExpr) -> String {
+    let kind = match &expr.kind {
+        TypedExprKind::Literal(Constants::Int(i)) => i.to_string(),
+        TypedExprKind::Literal(Constants::Float(f)) => format!("{:?}", f),
+        TypedExprKind::Variable(id) => program.symbol(*id).name.clone(),
+        TypedExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => format!(
+            "({} {} {})",
+            render(program, left),
+            operator.to_string().trim_matches('\''),
+            render(program, right)
+        ),
+        TypedExprKind::IntToFloat(inner) => format!("IntToFloat {}", render(program, inner)),
+        TypedExprKind::FloatToInt(inner) => format!("FloatToInt {}", render(program, inner)),
+        other => panic!("unexpected {:?}", other),
+    };
+    format!("{}: {}", kind, expr.ty)
+}
+
+#[test]
+fn every_expression_has_its_type() {
+    assert_eq!(typed("int x = i * 2;"), "(i: int * 2: int): int");
+    assert_eq!(typed("bool x = i < 3;"), "(i: int < 3: int): bool");
+    assert_eq!(typed("float x = f / f;"), "(f: float / f: float): float");
+}
+
+#[test]
+fn int_to_float_conversions_are_explicit() {
+    assert_eq!(typed("float x = i;"), "IntToFloat i: int: float");
+    assert_eq!(
+        typed("float x = i + f;"),
+        "(IntToFloat i: int: float + f: float): float"
+    );
+    assert_eq!(
+        typed("bool x = f > 1;"),
+        "(f: float > IntToFloat 1: int: float): bool"
+    );
+    assert_eq!(
+        typed("float x = i * 2;"),
+        "IntToFloat (i: int * 2: int): int: float"
+    );
+}
+
+#[test]
+fn names_resolve_to_the_declaration_they_mean() {
+    let code = body("int x = 1; if (true) { int x = 2; print(x); } print(x);");
+    let mut session = Session::new("test.rlp", code);
+    let checked = session.check_source().unwrap();
+    let program = &checked.program;
+    let main = program.function("main").unwrap();
+
+    // the symbol of every variable print() is called with, in order
+    let mut printed = Vec::new();
+    let mut collect = |stmt: &TypedStmt| {
+        if let TypedStmtKind::Expr(expr) = &stmt.kind
+            && let TypedExprKind::Call { args, .. } = &expr.kind
+            && let TypedExprKind::Variable(id) = args[0].kind
+        {
+            printed.push(id);
+        }
+    };
+    let TypedStmtKind::If(if_stmt) = &main.body.statements[1].kind else {
+        panic!("an if");
+    };
+    if_stmt.block.statements.iter().for_each(&mut collect);
+    main.body.statements.iter().for_each(&mut collect);
+
+    let [inner, outer] = printed[..] else {
+        panic!("{:?}", printed);
+    };
+    assert_ne!(inner, outer);
+    assert_eq!(program.symbol(inner).name, "x");
+    assert_eq!(program.symbol(outer).name, "x");
+    assert!(program.symbol(outer).span.start < program.symbol(inner).span.start);
+}
+
+#[test]
+fn each_error_is_reported_once() {
+    assert_eq!(
+        errors(&body("int x = (1 + \"a\") * (2 - true);")),
+        [codes::INVALID_OPERAND, codes::INVALID_OPERAND]
+    );
+    assert_eq!(
+        errors(&body("print(-y, y * 2);")),
+        [codes::UNDEFINED_VARIABLE, codes::UNDEFINED_VARIABLE]
+    );
+}
+
+#[test]
+fn void_functions_return_nothing() {
+    let program = |items: &str| format!("{} fn int main() {{ return 0; }}", items);
+    assert_eq!(
+        errors(&program("fn void f() { return 1; }")),
+        [codes::MISMATCHED_TYPES]
+    );
+    assert_eq!(
+        errors(&program("fn int f() { return; }")),
+        [codes::MISMATCHED_TYPES]
+    );
+    for used in [
+        "int x = f();",
+        "print(f());",
+        "return f();",
+        "int x = 1 + f();",
+    ] {
+        let code = program(&format!(
+            "fn void f() {{ }} fn int g() {{ {} return 0; }}",
+            used
+        ));
+        assert!(errors(&code).contains(&codes::VOID_VALUE_USED), "{}", used);
+    }
+}
+
+#[test]
+fn void_is_only_a_return_type() {
+    assert_eq!(
+        errors("void x; fn int main() { return 0; }"),
+        [codes::UNEXPECTED_TOKEN]
+    );
+    assert_eq!(
+        errors("fn void f(void a) { } fn int main() { return 0; }"),
+        [codes::EXPECTED_TYPE]
+    );
+}
+
+#[test]
+fn bare_returns_leave_void_functions() {
+    let code = "
+        fn void count(int n) {
+            int i = 0;
+            while (true) { if (i == n) { return; } print(i); i++; }
+        }
+        fn void nothing() { }
+        fn int main() { count(3); nothing(); return 0; }
+    ";
+    assert_eq!(run_both(code), Ok("0\n1\n2\n".to_string()));
+}
+
+#[test]
+fn bitwise_operators_are_int_only() {
+    assert!(
+        errors(&body(
+            "int i = 6; i <<= 1; print(~i, i & 3, i | 8, i ^ 1, i >> 1);"
+        ))
+        .is_empty()
+    );
+    for expr in ["1.5 & 2", "1 | 2.0", "1 ^ 2.0", "~1.5", "~true", "1.0 << 2"] {
+        assert_eq!(
+            errors(&body(&format!("print({});", expr))),
+            [codes::INVALID_OPERAND],
+            "{}",
+            expr
+        );
+    }
+    // a single & or | is not a logical operator
+    assert_eq!(
+        errors(&body("print(true & false);")),
+        [codes::INVALID_OPERAND; 2]
+    );
+}
+
+#[test]
+fn bitwise_and_remainder_values() {
+    let code = body(
+        "int a = 12; int b = -7;
+        print(a % 5, b % 3, 7 % -3, a & 10, a | 3, a ^ 5, ~a, ~0, 1 << 4, -16 >> 2, 6 ^ 3 & 5 | 8, 2 ** 10);",
+    );
+    // the remainder takes the sign of the left operand, >> keeps the sign
+    assert_eq!(
+        run_both(&code),
+        Ok("2 -1 1 8 15 9 -13 -1 16 -4 15 1024\n".to_string())
+    );
+    assert_eq!(
+        run_both(&body("int zero = 0; print(1 % zero);")),
+        Err((Phase::Runtime, vec![codes::DIVISION_BY_ZERO]))
+    );
+    assert_eq!(
+        run_both(&body("int n = 64; print(1 << n);")),
+        Err((Phase::Runtime, vec![codes::INVALID_SHIFT]))
+    );
+}
+
+#[test]
+fn compound_assignments_and_increments_are_type_checked() {
+    let check = |statements: &str| errors(&body(statements));
+    assert_eq!(check("bool b = true; b++;"), [codes::INVALID_OPERAND]);
+    assert_eq!(
+        check("string s = \"a\"; s -= \"b\";"),
+        [codes::INVALID_OPERAND]
+    );
+    assert_eq!(check("int x = 1; x += 1.5;"), [codes::NARROWING_CONVERSION]);
+    assert_eq!(
+        check("int x = 1; x <<= 1.5;"),
+        [codes::NARROWING_CONVERSION]
+    );
+    assert_eq!(check("float f = 1.0; f += 1;"), Vec::<&str>::new());
+    for target in [
+        "5++;",
+        "int x = 1; (x + 1)++;",
+        "int x = 1; ++x++;",
+        "int x; 1 += x;",
+    ] {
+        assert_eq!(
+            check(target),
+            [codes::INVALID_ASSIGNMENT_TARGET],
+            "{}",
+            target
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use relipmoc::diagnostics::codes;
+use relipmoc::ir::ir_generator::ir_generator;
+use relipmoc::lexer::lexer::lex;
+use relipmoc::parser::parser::parser;
+use relipmoc::semantics::lints::LintConfig;
+use relipmoc::semantics::semantic_analysis::semantic_analysis;
+use relipmoc::{Phase, Session};
+
+const CODE: &str = "fn int twice(int n) { return n * 2; } fn int main() { int unused = 1; print(twice(21)); return 0; }";
+
+#[test]
+fn phases_run_one_at_a_time() {
+    let mut session = Session::new("main.rlp", CODE);
+    let tokens = session.lex().unwrap();
+    assert_eq!(tokens.len(), 37);
+    let ast = session.parse(tokens).unwrap();
+    assert_eq!(ast.len(), 2);
+    let program = session.check(ast).unwrap();
+    assert_eq!(program.program.functions.len(), 2);
+    let module = session.generate_ir(&program).unwrap();
+    assert!(module.function("twice").is_some());
+
+    let mut out = Vec::new();
+    session.run(&program, &mut out).unwrap();
+    assert_eq!(out, b"42\n");
+    let mut out = Vec::new();
+    session.run_ir(&module, &mut out).unwrap();
+    assert_eq!(out, b"42\n");
+
+    // warnings don't stop the pipeline, they're kept on the session
+    let warnings: Vec<_> = session.diagnostics().iter().map(|d| d.code).collect();
+    assert_eq!(warnings, [codes::UNUSED_VARIABLE]);
+    let rendered = session.renderer().render_all(session.diagnostics());
+    assert!(rendered.contains("--> main.rlp:1:"), "{}", rendered);
+}
+
+#[test]
+fn compile_runs_every_phase_in_order() {
+    let staged = {
+        let mut session = Session::new("main.rlp", CODE);
+        let tokens = session.lex().unwrap();
+        let ast = session.parse(tokens).unwrap();
+        let program = session.check(ast).unwrap();
+        session.generate_ir(&program).unwrap()
+    };
+    assert_eq!(Session::new("main.rlp", CODE).compile().unwrap(), staged);
+}
+
+#[test]
+fn errors_say_which_phase_failed() {
+    let phase = |code: &str| Session::new("main.rlp", code).compile().unwrap_err().phase;
+    assert_eq!(phase("fn int main() { return @; }"), Phase::Lex);
+    assert_eq!(phase("fn int main() { return; "), Phase::Parse);
+    assert_eq!(phase("fn int main() { return x; }"), Phase::Semantic);
+
+    let mut session = Session::new(
+        "main.rlp",
+        "fn int main() { int[2] a = [1, 2]; int i = 2; return a[i]; }",
+    );
+    let program = session.check_source().unwrap();
+    let error = session.run(&program, &mut Vec::new()).unwrap_err();
+    assert_eq!(error.phase, Phase::Runtime);
+}
+
+#[test]
+fn phases_can_be_called_without_a_session() {
+    let tokens = lex(CODE.to_string()).unwrap();
+    let ast = parser(tokens).unwrap();
+    let (program, warnings) = semantic_analysis(&ast, &LintConfig::default()).unwrap();
+    assert_eq!(warnings.len(), 1);
+    let module = ir_generator(&program).unwrap();
+    assert_eq!(module, Session::new("main.rlp", CODE).compile().unwrap());
+}
+
+#[test]
+fn sessions_read_files() {
+    let path = std::env::temp_dir().join(format!("relipmoc-{}-session.rlp", std::process::id()));
+    std::fs::write(&path, CODE).unwrap();
+    let session = Session::from_file(&path).unwrap();
+    assert_eq!(session.code, CODE);
+    assert_eq!(session.file_name, path.display().to_string());
+    std::fs::remove_file(&path).unwrap();
+    assert!(Session::from_file(&path).is_err());
+}
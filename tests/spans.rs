@@ -0,0 +1,77 @@
+mod common;
+
+use common::check;
+use relipmoc::Session;
+use relipmoc::diagnostics::codes;
+use relipmoc::lexer::lexer::lex;
+use relipmoc::lexer::span::Span;
+use relipmoc::parser::enums::{ExpressionKind, Root, StatementKind};
+
+// Where a span starts as (line, column) and the text it covers
+fn at(code: &str, span: Span) -> (usize, usize, &str) {
+    (span.line, span.col, &code[span.start..span.end])
+}
+
+#[test]
+fn tokens_know_where_they_are() {
+    let code = "int x = 1;\n  \"é\" é_y >>= 0x1F;\n";
+    let tokens = lex(code.to_string()).unwrap();
+    let spans: Vec<_> = tokens.iter().map(|t| at(code, t.span)).collect();
+    assert_eq!(
+        spans,
+        [
+            (1, 1, "int"),
+            (1, 5, "x"),
+            (1, 7, "="),
+            (1, 9, "1"),
+            (1, 10, ";"),
+            (2, 3, "\"é\""),
+            // columns count characters, not bytes
+            (2, 7, "é_y"),
+            (2, 11, ">>="),
+            (2, 15, "0x1F"),
+            (2, 19, ";"),
+        ]
+    );
+}
+
+#[test]
+fn ast_nodes_cover_what_they_were_parsed_from() {
+    let code = "fn int main() {\n    int x = 1 + 2 * 3;\n    return x;\n}";
+    let mut session = Session::new("test.rlp", code);
+    let tokens = session.lex().unwrap();
+    let ast = session.parse(tokens).unwrap();
+    let Some(Root::Func(main)) = ast.first() else {
+        panic!("one function");
+    };
+    assert_eq!(at(code, main.identifier_span), (1, 8, "main"));
+    assert_eq!(at(code, main.span), (1, 1, code));
+    assert_eq!(at(code, main.block.span), (1, 15, &code[14..]));
+
+    let [decl, ret] = &main.block.statements[..] else {
+        panic!("two statements");
+    };
+    assert_eq!(at(code, decl.span), (2, 5, "int x = 1 + 2 * 3;"));
+    let StatementKind::VarDecl(var) = &decl.kind else {
+        panic!("a declaration");
+    };
+    assert_eq!(at(code, var.identifier_span), (2, 9, "x"));
+    let value = var.expression.as_ref().unwrap();
+    assert_eq!(at(code, value.span), (2, 13, "1 + 2 * 3"));
+    let ExpressionKind::BinaryOperation { right, .. } = &value.kind else {
+        panic!("a sum");
+    };
+    assert_eq!(at(code, right.span), (2, 17, "2 * 3"));
+    assert_eq!(at(code, ret.span), (3, 5, "return x;"));
+}
+
+#[test]
+fn semantic_errors_point_at_the_name() {
+    let code = "fn int main() {\n    int y = 1;\n    print(y + x);\n    return 0;\n}";
+    let diagnostics = check(code);
+    let [undefined] = &diagnostics[..] else {
+        panic!("{:?}", diagnostics);
+    };
+    assert_eq!(undefined.code, codes::UNDEFINED_VARIABLE);
+    assert_eq!(at(code, undefined.primary.span), (3, 15, "x"));
+}
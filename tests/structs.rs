@@ -0,0 +1,105 @@
+mod common;
+
+use common::{errors, run_both};
+use relipmoc::Session;
+use relipmoc::diagnostics::codes;
+
+const POINT: &str = "struct Point { int x; float y; }";
+
+#[test]
+fn structs_are_built_read_written_and_passed_around() {
+    let code = "
+        struct Point { int x; float y; }
+        struct Line { Point a; Point b; }
+        fn Point mid(Line l) { return Point { x: (l.a.x + l.b.x) / 2, y: (l.a.y + l.b.y) / 2 }; }
+        fn void shift(Point p) { p.x = 100; }
+        fn int main() {
+            Point p = Point { x: 1, y: 2 };
+            Line l = Line { a: p, b: Point { y: 4.0, x: 3 } };
+            shift(p);
+            l.b.x += 10;
+            Point m = mid(l);
+            Point z;
+            print(p, m, l.b.x, z);
+            return 0;
+        }
+    ";
+    // structs are values: shift() changed its own copy
+    assert_eq!(
+        run_both(code),
+        Ok("Point { x: 1, y: 2.0 } Point { x: 7, y: 3.0 } 13 Point { x: 0, y: 0.0 }\n".to_string())
+    );
+}
+
+#[test]
+fn fields_are_loaded_and_stored_at_their_offset() {
+    let code = format!(
+        "{} fn int main() {{ Point p; p.y = 1.5; print(p.y, p.x); return 0; }}",
+        POINT
+    );
+    let module = Session::new("test.rlp", code).compile().unwrap();
+    let main: Vec<String> = module
+        .function("main")
+        .unwrap()
+        .instrs()
+        .map(|instr| instr.to_string())
+        .collect();
+    assert!(main.iter().any(|l| l.ends_with("[1] = 1.5")), "{:#?}", main);
+    assert!(
+        main.iter()
+            .any(|l| l.ends_with("[1]") && l.starts_with('t'))
+    );
+    assert!(
+        main.iter()
+            .any(|l| l.ends_with("[0]") && l.starts_with('t'))
+    );
+}
+
+#[test]
+fn struct_types_are_checked() {
+    let check = |statements: &str| {
+        errors(&format!(
+            "{} fn int main() {{ {} return 0; }}",
+            POINT, statements
+        ))
+    };
+    assert_eq!(check("Point p; print(p.z);"), [codes::UNKNOWN_FIELD]);
+    assert_eq!(check("int x = 1; print(x.a);"), [codes::NOT_A_STRUCT]);
+    assert_eq!(check("Point p = Point { x: 1 };"), [codes::MISSING_FIELD]);
+    assert_eq!(
+        check("Point p = Point { x: 1, y: 2, x: 3 };"),
+        [codes::DUPLICATE_FIELD]
+    );
+    assert_eq!(
+        check("Point p = Point { x: 1, y: 2, z: 3 };"),
+        [codes::UNKNOWN_FIELD]
+    );
+    assert_eq!(check("Q q;"), [codes::UNKNOWN_TYPE]);
+    assert_eq!(check("Point p; p.x = 1.5;"), [codes::NARROWING_CONVERSION]);
+    assert_eq!(check("Point p; int x = p;"), [codes::MISMATCHED_TYPES]);
+}
+
+#[test]
+fn structs_cannot_contain_themselves() {
+    let main = "fn int main() { return 0; }";
+    assert_eq!(
+        errors(&format!("struct A {{ A a; }} {}", main)),
+        [codes::RECURSIVE_STRUCT]
+    );
+    assert_eq!(
+        errors(&format!("struct A {{ B b; }} struct B {{ A a; }} {}", main)),
+        [codes::RECURSIVE_STRUCT]
+    );
+    // through an array is still inside
+    assert_eq!(
+        errors(&format!("struct A {{ A[2] a; }} {}", main)),
+        [codes::RECURSIVE_STRUCT]
+    );
+    assert!(
+        errors(&format!(
+            "struct A {{ int x; }} struct B {{ A a; A b; }} {}",
+            main
+        ))
+        .is_empty()
+    );
+}